@@ -0,0 +1,105 @@
+//! JSON files in the app data and config directories
+//!
+//! Link profiles, the firmware trust config, transfer checkpoints and throughput
+//! history are each one JSON file. Saving writes and syncs a temporary file next to
+//! the target and renames it over the old one, so a crash or power loss leaves either
+//! the old or the new contents, never a torn file.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fs::{self, File};
+use std::io::{ErrorKind, Write};
+use std::path::Path;
+
+use crate::gcp::GcpCommError;
+
+// `None` when the file does not exist yet; `what` names the file in errors
+pub fn load_json<T: DeserializeOwned>(path: &Path, what: &str) -> Result<Option<T>, GcpCommError> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(GcpCommError::file(path, format!("Failed to read {}: {}", what, e))),
+    };
+    serde_json::from_str(&contents)
+        .map(Some)
+        .map_err(|e| GcpCommError::file(path, format!("Invalid {}: {}", what, e)))
+}
+
+pub fn save_json<T: Serialize>(path: &Path, value: &T, what: &str) -> Result<(), GcpCommError> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| GcpCommError::file(dir, format!("Failed to create directory for {}: {}", what, e)))?;
+    }
+    let contents = serde_json::to_string_pretty(value)
+        .map_err(|e| GcpCommError::internal(format!("Failed to serialize {}: {}", what, e)))?;
+
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = Path::new(&tmp_path);
+    // On disk before the rename, or a power loss could leave the renamed file empty
+    let written = File::create(tmp_path)
+        .and_then(|mut file| {
+            file.write_all(contents.as_bytes())?;
+            file.sync_all()
+        });
+    written.map_err(|e| GcpCommError::file(tmp_path, format!("Failed to write {}: {}", what, e)))?;
+    fs::rename(tmp_path, path)
+        .map_err(|e| GcpCommError::file(path, format!("Failed to write {}: {}", what, e)))?;
+
+    // The rename itself is only durable once the directory entry is synced
+    #[cfg(unix)]
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        File::open(dir)
+            .and_then(|dir| dir.sync_all())
+            .map_err(|e| GcpCommError::file(dir, format!("Failed to sync directory for {}: {}", what, e)))?;
+    }
+    Ok(())
+}
+
+// Removing a file that does not exist is not an error
+pub fn remove_json(path: &Path, what: &str) -> Result<(), GcpCommError> {
+    match fs::remove_file(path) {
+        Ok(()) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(GcpCommError::file(path, format!("Failed to remove {}: {}", what, e))),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+    use std::path::PathBuf;
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    static NEXT_TEST_DIR: AtomicU32 = AtomicU32::new(0);
+
+    // Fresh directory per call, so tests running in parallel never share files
+    pub(crate) fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "gcp_{}_{}_{}", name, std::process::id(), NEXT_TEST_DIR.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn test_json_store_round_trip() {
+        let dir = test_dir("app_store");
+        let path = dir.join("nested").join("values.json");
+        assert_eq!(load_json::<BTreeMap<String, u32>>(&path, "values").unwrap(), None);
+
+        let values = BTreeMap::from([("a".to_string(), 1u32), ("b".to_string(), 2)]);
+        save_json(&path, &values, "values").unwrap();
+        assert_eq!(load_json(&path, "values").unwrap(), Some(values));
+        assert!(!dir.join("nested").join("values.json.tmp").exists());
+
+        fs::write(&path, "{ torn").unwrap();
+        assert!(matches!(load_json::<BTreeMap<String, u32>>(&path, "values"), Err(GcpCommError::File { .. })));
+
+        remove_json(&path, "values").unwrap();
+        remove_json(&path, "values").unwrap();
+        assert!(!path.exists());
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,54 @@
+//! Replay a protocol capture without a device
+//!
+//!   gcp-replay [--json] <capture.jsonl>
+//!
+//! Runs every request in a capture taken with `start_protocol_capture` through the
+//! protocol handler again and prints each response or error, then where the replayed
+//! session left the captured one. `--json` prints the full `ReplayReport` instead.
+
+use std::path::Path;
+use std::process::exit;
+
+use app_lib::capture::replay_capture;
+
+fn main() {
+    let mut json = false;
+    let mut path = None;
+    for arg in std::env::args().skip(1) {
+        match arg.as_str() {
+            "--json" => json = true,
+            _ if path.is_none() => path = Some(arg),
+            _ => path = None,
+        }
+    }
+    let Some(path) = path else {
+        eprintln!("Usage: gcp-replay [--json] <capture.jsonl>");
+        exit(2);
+    };
+
+    let report = replay_capture(Path::new(&path)).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        exit(1);
+    });
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report).unwrap_or_default());
+        return;
+    }
+
+    println!("Capture of {} ({:?})", report.header.port_name, report.header.spec_revision);
+    for step in &report.steps {
+        let frame = step.msg_type.map_or_else(|| "invalid frame".to_string(), |msg_type| format!("{:?}", msg_type));
+        let kind = if step.unsolicited { "RX" } else { "TX" };
+        match (&step.response, &step.error) {
+            (_, Some(error)) => println!("{:>10} us  {} {}: {}", step.t_us, kind, frame, error),
+            (Some(response), None) => println!("{:>10} us  {} {}: {:?}", step.t_us, kind, frame, response),
+            (None, None) => println!("{:>10} us  {} {}", step.t_us, kind, frame),
+        }
+    }
+    for divergence in &report.divergences {
+        println!("Divergence: {}", divergence);
+    }
+    if !report.divergences.is_empty() {
+        exit(1);
+    }
+}
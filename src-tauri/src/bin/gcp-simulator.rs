@@ -0,0 +1,191 @@
+//! Standalone GCP v2.2 device simulator
+//!
+//! Serves a `SimulatedDevice` where the desktop app can reach it like a real board:
+//!
+//!   gcp-simulator [--pty] [--tcp <addr>] [--config <file.json>] [--faults <file.json>]
+//!                 [--serial <n>] [--fw-version <0.1.4a>]
+//!
+//! `--pty` (the default on Unix) prints the path of a pseudo-terminal to connect to as
+//! a COM port; `--tcp` listens for `tcp://<addr>` connections, one at a time. The
+//! config file is a JSON `SimulatorConfig`; missing fields keep their defaults. A
+//! `--faults` file is a JSON `FaultScript`; set `GCP_FAULT_SEED` to replay a run whose
+//! script has no seed. The seed, injected faults and dropped frames (log target
+//! `gcp::sim`) are printed as they happen.
+
+use std::net::TcpListener;
+use std::process::exit;
+use std::sync::atomic::AtomicBool;
+
+use app_lib::gcp::GcpFwVersionData;
+use app_lib::link_config::LinkConfig;
+use app_lib::simulator::{SimulatedDevice, SimulatorConfig};
+use app_lib::transport::TcpTransport;
+
+// Prints the device's own log, which the app sends to its protocol console instead
+struct SimulatorLog;
+
+impl log::Log for SimulatorLog {
+    fn enabled(&self, metadata: &log::Metadata) -> bool {
+        metadata.target() == "gcp::sim"
+    }
+
+    fn log(&self, record: &log::Record) {
+        if self.enabled(record.metadata()) {
+            println!("{}", record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static SIMULATOR_LOG: SimulatorLog = SimulatorLog;
+
+struct Options {
+    config: SimulatorConfig,
+    tcp_address: Option<String>,
+    pty: bool,
+}
+
+fn main() {
+    if log::set_logger(&SIMULATOR_LOG).is_ok() {
+        log::set_max_level(log::LevelFilter::Debug);
+    }
+
+    let options = parse_args(std::env::args().skip(1)).unwrap_or_else(|message| {
+        eprintln!("{}", message);
+        eprintln!("Usage: gcp-simulator [--pty] [--tcp <addr>] [--config <file.json>] [--faults <file.json>] [--serial <n>] [--fw-version <0.1.4a>]");
+        exit(2);
+    });
+
+    println!(
+        "Simulating device serial {} running firmware {}",
+        options.config.hardware.serial_number,
+        options.config.fw_version.version_string()
+    );
+
+    let result = match (options.pty, options.tcp_address) {
+        (true, Some(_)) => Err("--pty and --tcp cannot be combined".to_string()),
+        (_, Some(address)) => serve_tcp(&address, options.config),
+        (_, None) => serve_pty(options.config),
+    };
+    if let Err(message) = result {
+        eprintln!("{}", message);
+        exit(1);
+    }
+}
+
+fn parse_args(mut args: impl Iterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options { config: SimulatorConfig::default(), tcp_address: None, pty: false };
+    let mut serial_number = None;
+    let mut fw_version = None;
+    let mut faults = None;
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().ok_or_else(|| format!("{} needs a value", arg));
+        match arg.as_str() {
+            "--pty" => options.pty = true,
+            "--tcp" => options.tcp_address = Some(value()?),
+            "--config" => {
+                let path = value()?;
+                let contents = std::fs::read_to_string(&path)
+                    .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+                options.config = serde_json::from_str(&contents)
+                    .map_err(|e| format!("Invalid simulator config {}: {}", path, e))?;
+            }
+            "--faults" => {
+                let path = value()?;
+                let contents = std::fs::read_to_string(&path)
+                    .map_err(|e| format!("Failed to read {}: {}", path, e))?;
+                faults = Some(serde_json::from_str(&contents)
+                    .map_err(|e| format!("Invalid fault script {}: {}", path, e))?);
+            }
+            "--serial" => {
+                let serial = value()?;
+                serial_number = Some(serial.parse().map_err(|_| format!("Invalid serial number {}", serial))?);
+            }
+            "--fw-version" => fw_version = Some(parse_fw_version(&value()?)?),
+            other => return Err(format!("Unknown argument {}", other)),
+        }
+    }
+
+    // Flags override the config file regardless of order
+    if let Some(serial_number) = serial_number {
+        options.config.hardware.serial_number = serial_number;
+    }
+    if let Some(fw_version) = fw_version {
+        options.config.fw_version = fw_version;
+    }
+    if faults.is_some() {
+        options.config.faults = faults;
+    }
+    Ok(options)
+}
+
+// "0.1.4a" -> 0, 1, 4, "a"
+fn parse_fw_version(text: &str) -> Result<GcpFwVersionData, String> {
+    let invalid = || format!("Invalid firmware version {} (expected e.g. 0.1.4a)", text);
+    let mut parts = text.splitn(3, '.');
+    let mut number = || parts.next().and_then(|part| part.parse::<u8>().ok()).ok_or_else(invalid);
+    let (major, minor) = (number()?, number()?);
+
+    let rest = parts.next().ok_or_else(invalid)?;
+    let digits = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+    let patch = rest[..digits].parse::<u8>().map_err(|_| invalid())?;
+    let suffix = &rest.as_bytes()[digits..];
+    if suffix.len() > 3 {
+        return Err(invalid());
+    }
+
+    let mut fw_version_suffix = [0u8; 3];
+    fw_version_suffix[..suffix.len()].copy_from_slice(suffix);
+    Ok(GcpFwVersionData { fw_version_major: major, fw_version_minor: minor, fw_version_patch: patch, fw_version_suffix })
+}
+
+fn serve_tcp(address: &str, config: SimulatorConfig) -> Result<(), String> {
+    let listener = TcpListener::bind(address).map_err(|e| format!("Failed to listen on {}: {}", address, e))?;
+    let local_address = listener.local_addr().map_err(|e| e.to_string())?;
+    println!("Listening on tcp://{}", local_address);
+
+    // Device state survives reconnects, like a board that stays powered
+    let mut device = SimulatedDevice::new(config);
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                println!("Failed to accept connection: {}", e);
+                continue;
+            }
+        };
+        let peer = stream.peer_addr().map(|peer| peer.to_string()).unwrap_or_default();
+        println!("Host connected from {}", peer);
+
+        let mut transport = TcpTransport::from_stream(stream, &LinkConfig::default()).map_err(|e| e.to_string())?;
+        match device.serve(&mut transport, &AtomicBool::new(false)) {
+            Ok(()) => println!("Host {} disconnected", peer),
+            Err(e) => println!("Connection to {} failed: {}", peer, e),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(unix)]
+fn serve_pty(config: SimulatorConfig) -> Result<(), String> {
+    use app_lib::transport::SerialTransport;
+    use serialport::{SerialPort, TTYPort};
+
+    let (master, slave) = TTYPort::pair().map_err(|e| format!("Failed to open a PTY: {}", e))?;
+    let slave_path = slave.name().ok_or("PTY has no device path")?;
+    println!("Connect the app to {}", slave_path);
+
+    // Holding the slave open keeps the master readable while no host is attached
+    let _slave = slave;
+    let mut transport = SerialTransport::from_port(Box::new(master));
+    let mut device = SimulatedDevice::new(config);
+    device.serve(&mut transport, &AtomicBool::new(false))
+        .map_err(|e| format!("PTY link failed: {}", e))
+}
+
+#[cfg(not(unix))]
+fn serve_pty(_config: SimulatorConfig) -> Result<(), String> {
+    Err("PTYs are only available on Unix; use --tcp <addr> instead".to_string())
+}
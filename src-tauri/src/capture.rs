@@ -0,0 +1,415 @@
+//! Protocol traffic capture and offline replay
+//!
+//! A capture records the exact byte stream of a connection as JSON Lines: a header
+//! with the port, link parameters and spec revision, then one record per TX write,
+//! RX read and discarded input, each stamped with microseconds since the capture
+//! started (monotonic). Bytes are stored as hex strings.
+//!
+//! Replay feeds a capture back through `GcpDecoder` and the `GcpUartHandler` response
+//! logic without a device: `ReplayTransport` answers each captured request with the
+//! RX chunks recorded after it, so stale ACKs, CRC errors and timeouts reproduce
+//! exactly as the customer saw them. Requests the replay sends that differ from the
+//! capture are reported as divergences.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::gcp::{GcpCommError, GcpCommand, GcpResponse, GcpSpecRevision, GcpUartHandler};
+use crate::gcp_decoder::{DecodedFrame, GcpDecoder};
+use crate::link_config::LinkConfig;
+use crate::transport::GcpTransport;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CaptureHeader {
+    pub port_name: String,
+    pub started_at: u64,         // Unix time (milliseconds)
+    pub link: LinkConfig,
+    pub spec_revision: GcpSpecRevision,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "dir", rename_all = "snake_case")]
+pub enum CaptureRecord {
+    Tx { t_us: u64, #[serde(with = "hex_bytes")] data: Vec<u8> },
+    Rx { t_us: u64, #[serde(with = "hex_bytes")] data: Vec<u8> },
+    // Input dropped before a request without being read; only the count is known
+    Discard { t_us: u64, count: usize },
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CaptureSummary {
+    pub path: String,
+    pub records: u64,
+    pub tx_bytes: u64,
+    pub rx_bytes: u64,
+    pub duration_ms: u64,
+}
+
+pub struct ProtocolCapture {
+    file: File,
+    started: Instant,
+    summary: CaptureSummary,
+}
+
+// `<dir>/<port>-<unix ms>.jsonl`, with the port name made safe for a file name
+pub fn default_capture_path(dir: &Path, port_name: &str) -> PathBuf {
+    let port: String = port_name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    dir.join(format!("{}-{}.jsonl", port.trim_matches('_'), unix_millis()))
+}
+
+impl ProtocolCapture {
+    pub fn create(path: &Path, header: &CaptureHeader) -> Result<Self, GcpCommError> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| GcpCommError::file(dir, format!("Failed to create capture directory: {}", e)))?;
+        }
+        let file = File::create(path)
+            .map_err(|e| GcpCommError::file(path, format!("Failed to create capture: {}", e)))?;
+
+        let mut capture = Self {
+            file,
+            started: Instant::now(),
+            summary: CaptureSummary { path: path.display().to_string(), ..CaptureSummary::default() },
+        };
+        capture.write_line(header)
+            .map_err(|e| GcpCommError::file(path, format!("Failed to write capture: {}", e)))?;
+        Ok(capture)
+    }
+
+    pub fn header(port_name: &str, link: &LinkConfig, spec_revision: GcpSpecRevision) -> CaptureHeader {
+        CaptureHeader {
+            port_name: port_name.to_string(),
+            started_at: unix_millis(),
+            link: link.clone(),
+            spec_revision,
+        }
+    }
+
+    pub fn record_tx(&mut self, data: &[u8]) -> io::Result<()> {
+        self.summary.tx_bytes += data.len() as u64;
+        let record = CaptureRecord::Tx { t_us: self.elapsed_us(), data: data.to_vec() };
+        self.record(&record)
+    }
+
+    pub fn record_rx(&mut self, data: &[u8]) -> io::Result<()> {
+        self.summary.rx_bytes += data.len() as u64;
+        let record = CaptureRecord::Rx { t_us: self.elapsed_us(), data: data.to_vec() };
+        self.record(&record)
+    }
+
+    pub fn record_discard(&mut self, count: usize) -> io::Result<()> {
+        let record = CaptureRecord::Discard { t_us: self.elapsed_us(), count };
+        self.record(&record)
+    }
+
+    pub fn finish(self) -> CaptureSummary {
+        CaptureSummary { duration_ms: self.started.elapsed().as_millis() as u64, ..self.summary }
+    }
+
+    fn record(&mut self, record: &CaptureRecord) -> io::Result<()> {
+        self.summary.records += 1;
+        self.write_line(record)
+    }
+
+    // One write per line, so a capture cut short by a crash ends on a whole record
+    fn write_line<T: Serialize>(&mut self, value: &T) -> io::Result<()> {
+        let mut line = serde_json::to_vec(value).map_err(io::Error::other)?;
+        line.push(b'\n');
+        self.file.write_all(&line)
+    }
+
+    fn elapsed_us(&self) -> u64 {
+        self.started.elapsed().as_micros() as u64
+    }
+}
+
+pub fn load_capture(path: &Path) -> Result<(CaptureHeader, Vec<CaptureRecord>), GcpCommError> {
+    let file = File::open(path)
+        .map_err(|e| GcpCommError::file(path, format!("Failed to open capture: {}", e)))?;
+    let mut lines = BufReader::new(file).lines().enumerate();
+
+    let invalid = |line: usize, message: String| GcpCommError::file(path, format!("Line {}: {}", line + 1, message));
+    let (_, header) = lines.next()
+        .ok_or_else(|| GcpCommError::file(path, "Capture is empty"))?;
+    let header: CaptureHeader = header
+        .map_err(|e| invalid(0, e.to_string()))
+        .and_then(|line| serde_json::from_str(&line).map_err(|e| invalid(0, format!("Invalid capture header: {}", e))))?;
+
+    let mut records = Vec::new();
+    for (index, line) in lines {
+        let line = line.map_err(|e| invalid(index, e.to_string()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        records.push(serde_json::from_str(&line).map_err(|e| invalid(index, format!("Invalid record: {}", e)))?);
+    }
+    Ok((header, records))
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayStep {
+    pub t_us: u64,                       // When the frame was captured
+    pub msg_type: Option<GcpCommand>,    // None for RX bytes that did not form a frame
+    pub raw_msg_type: Option<u16>,       // As received, also for a MsgType outside the spec
+    pub unsolicited: bool,               // Sent by the device on its own, not a request
+    pub response: Option<GcpResponse>,
+    pub error: Option<GcpCommError>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReplayReport {
+    pub header: CaptureHeader,
+    pub steps: Vec<ReplayStep>,
+    pub divergences: Vec<String>,        // Where the replayed session left the captured one
+}
+
+// Run every request in a capture through a handler again, in order
+pub fn replay_capture(path: &Path) -> Result<ReplayReport, GcpCommError> {
+    let (header, records) = load_capture(path)?;
+
+    // The requests to replay, decoded from the captured TX bytes
+    let mut requests = VecDeque::new();
+    let mut decoder = GcpDecoder::default();
+    for record in &records {
+        if let CaptureRecord::Tx { t_us, data } = record {
+            decoder.push(data);
+            while let Some(frame) = decoder.next_frame() {
+                requests.push_back((*t_us, frame));
+            }
+        }
+    }
+
+    let transport = ReplayTransport::new(records);
+    let progress = transport.progress();
+    let mut handler = GcpUartHandler::with_transport(Box::new(transport));
+    handler.set_spec_revision(header.spec_revision);
+
+    let mut steps = Vec::new();
+    loop {
+        replay_unsolicited(&mut handler, &progress, &mut steps);
+        let Some((t_us, request)) = requests.pop_front() else {
+            break;
+        };
+        match request {
+            Ok(DecodedFrame::Frame(frame)) => {
+                let result = handler.exchange(&frame);
+                steps.push(ReplayStep {
+                    t_us,
+                    msg_type: Some(frame.msg_type),
+                    raw_msg_type: Some(frame.msg_type as u16),
+                    unsolicited: false,
+                    response: result.as_ref().ok().cloned(),
+                    error: result.err(),
+                });
+            }
+            Ok(unknown) => progress.diverged(format!("Captured request at {} us has unknown MsgType 0x{:04X} and was not replayed",
+                                                     t_us, unknown.msg_type())),
+            Err(e) => progress.diverged(format!("Captured request at {} us is not a valid frame: {}", t_us, e)),
+        }
+    }
+
+    let divergences = progress.state.lock().map(|state| state.divergences.clone()).unwrap_or_default();
+    Ok(ReplayReport { header, steps, divergences })
+}
+
+// Frames the device sent between requests (e.g. FW_UPDATE_REQUEST)
+fn replay_unsolicited(handler: &mut GcpUartHandler, progress: &ReplayProgress, steps: &mut Vec<ReplayStep>) {
+    loop {
+        let (msg_type, raw_msg_type, error) = match handler.poll_unsolicited() {
+            Ok(Some(DecodedFrame::Frame(frame))) => (Some(frame.msg_type), Some(frame.msg_type as u16), None),
+            Ok(Some(unknown)) => (None, Some(unknown.msg_type()), None),
+            Err(e @ GcpCommError::Framing { .. }) => (None, None, Some(e)),
+            // Anything else means the RX bytes before the next request are used up
+            Ok(None) | Err(_) => return,
+        };
+        steps.push(ReplayStep { t_us: progress.last_rx_us(), msg_type, raw_msg_type, unsolicited: true, response: None, error });
+    }
+}
+
+// Shared with the handler's transport while a replay runs
+#[derive(Clone, Default)]
+struct ReplayProgress {
+    state: Arc<Mutex<ReplayState>>,
+}
+
+#[derive(Default)]
+struct ReplayState {
+    last_rx_us: u64,
+    divergences: Vec<String>,
+}
+
+impl ReplayProgress {
+    fn diverged(&self, message: String) {
+        if let Ok(mut state) = self.state.lock() {
+            state.divergences.push(message);
+        }
+    }
+
+    fn last_rx_us(&self) -> u64 {
+        self.state.lock().map(|state| state.last_rx_us).unwrap_or(0)
+    }
+}
+
+// Plays the device side of a capture: reads return the RX chunks recorded after the
+// last write, and time out at once when the capture has nothing more before the next
+// TX. Timing is not reproduced, only order.
+pub struct ReplayTransport {
+    records: VecDeque<CaptureRecord>,
+    progress: ReplayProgress,
+}
+
+impl ReplayTransport {
+    pub fn new(records: Vec<CaptureRecord>) -> Self {
+        Self { records: records.into(), progress: ReplayProgress::default() }
+    }
+
+    fn progress(&self) -> ReplayProgress {
+        self.progress.clone()
+    }
+
+    fn timed_out() -> io::Error {
+        io::Error::new(io::ErrorKind::TimedOut, "No captured RX before the next TX")
+    }
+}
+
+impl GcpTransport for ReplayTransport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let Some(CaptureRecord::Rx { t_us, data }) = self.records.front_mut() else {
+            return Err(Self::timed_out());
+        };
+        if let Ok(mut state) = self.progress.state.lock() {
+            state.last_rx_us = *t_us;
+        }
+        let count = buf.len().min(data.len());
+        buf[..count].copy_from_slice(&data[..count]);
+        data.drain(..count);
+        if data.is_empty() {
+            self.records.pop_front();
+        }
+        Ok(count)
+    }
+
+    fn write_all(&mut self, data: &[u8]) -> io::Result<()> {
+        // RX the captured session read but the replay did not is left behind
+        while let Some(record) = self.records.pop_front() {
+            match record {
+                CaptureRecord::Tx { t_us, data: captured } => {
+                    if captured != data {
+                        self.progress.diverged(format!("TX at {} us differs: captured {:02X?}, replayed {:02X?}", t_us, captured, data));
+                    }
+                    return Ok(());
+                }
+                CaptureRecord::Rx { t_us, data: unread } => {
+                    self.progress.diverged(format!("{} RX bytes captured at {} us were not read", unread.len(), t_us));
+                }
+                CaptureRecord::Discard { .. } => {}
+            }
+        }
+        self.progress.diverged(format!("TX past the end of the capture: {:02X?}", data));
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn set_timeout(&mut self, _timeout: Duration) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn bytes_to_read(&mut self) -> io::Result<usize> {
+        match self.records.front() {
+            Some(CaptureRecord::Rx { data, .. }) => Ok(data.len()),
+            _ => Ok(0),
+        }
+    }
+
+    fn discard_input(&mut self) -> io::Result<usize> {
+        match self.records.front() {
+            Some(&CaptureRecord::Discard { count, .. }) => {
+                self.records.pop_front();
+                Ok(count)
+            }
+            _ => Ok(0),
+        }
+    }
+}
+
+fn unix_millis() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map(|t| t.as_millis() as u64).unwrap_or(0)
+}
+
+mod hex_bytes {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    use crate::hex::{decode_hex, encode_hex};
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&encode_hex(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        decode_hex(&String::deserialize(deserializer)?).map_err(D::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app_store::tests::test_dir;
+    use crate::gcp::{connect_with_transport, disconnect_from_port, execute_with_connection, GcpHelloResponse};
+    use crate::simulator::{spawn_memory_device, SimulatorConfig};
+
+    #[test]
+    fn test_capture_replays_offline() {
+        let port_name = "memory://test_capture".to_string();
+        let dir = test_dir("capture");
+        let path = dir.join("capture.jsonl");
+        let config = SimulatorConfig::default();
+        connect_with_transport(port_name.clone(), Box::new(spawn_memory_device(config.clone()))).unwrap();
+
+        let summary = execute_with_connection(&port_name, |handler| {
+            handler.start_capture(&path)?;
+            handler.send_hello()?;
+            handler.get_fw_version()?;
+            handler.ping()?;
+            Ok(handler.stop_capture())
+        }).unwrap().unwrap();
+        disconnect_from_port(port_name.clone()).unwrap();
+        assert!(summary.tx_bytes > 0 && summary.rx_bytes > 0 && summary.records >= 6);
+
+        let (header, records) = load_capture(&path).unwrap();
+        assert_eq!(header.port_name, port_name);
+        assert!(matches!(&records[0], CaptureRecord::Tx { data, .. } if data[..2] == [0xAA, 0x55]));
+
+        // Same responses without the device
+        let report = replay_capture(&path).unwrap();
+        assert!(report.divergences.is_empty(), "{:?}", report.divergences);
+        let msg_types: Vec<Option<GcpCommand>> = report.steps.iter().map(|step| step.msg_type).collect();
+        assert_eq!(msg_types, vec![Some(GcpCommand::Hello), Some(GcpCommand::GetFwVersion), Some(GcpCommand::Ping)]);
+        match &report.steps[0].response {
+            Some(GcpResponse::Hello(GcpHelloResponse::Hardware(hardware))) => {
+                assert_eq!(hardware.serial_number, config.hardware.serial_number)
+            }
+            other => panic!("Expected hardware data, got {:?}", other),
+        }
+        assert!(matches!(&report.steps[1].response, Some(GcpResponse::FwVersion(version)) if *version == config.fw_version));
+
+        // A capture cut off before the last response times out where the device went quiet
+        let (_, mut records) = load_capture(&path).unwrap();
+        records.pop();
+        let mut handler = GcpUartHandler::with_transport(Box::new(ReplayTransport::new(records)));
+        handler.send_hello().unwrap();
+        handler.get_fw_version().unwrap();
+        assert!(matches!(handler.ping(), Err(GcpCommError::Timeout { .. })));
+        let _ = fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,259 @@
+//! Background health monitoring for pooled connections
+//!
+//! A pooled connection stays open after the device goes away, so one thread per
+//! connection watches the link while nothing else is using it:
+//!
+//! - A serial port that disappears from the system port list (USB cable pulled), or
+//!   whose handle starts failing I/O, puts the connection in `ConnectionState::Error`
+//! - Once the device has been silent for the configured interval, a PING (§2.1)
+//!   checks that it still answers; HEALTH_MAX_MISSED_PINGS unanswered in a row is an
+//!   error too
+//! - Frames arriving again, or an answered PING, return the connection to Connected
+//!
+//! Every state change is emitted as a "connection-state-changed" event.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Runtime};
+
+use crate::firmware_job;
+use crate::gcp::{get_connection_handle, set_connection_state, ConnectionState, GcpCommError, GcpUartHandler};
+use crate::simulator::SIMULATOR_PORT_PREFIX;
+use crate::transport::TCP_TRANSPORT_PREFIX;
+
+pub const HEALTH_DEFAULT_PING_INTERVAL_MS: u64 = 5000;
+const HEALTH_MIN_PING_INTERVAL_MS: u64 = 500;
+const HEALTH_MAX_MISSED_PINGS: u32 = 2;
+// Port list and I/O checks are cheap enough to run more often than PING
+const HEALTH_LINK_CHECK_INTERVAL_MS: u64 = 1000;
+const MONITOR_POLL_INTERVAL_MS: u64 = 100;
+
+static PING_INTERVAL_MS: AtomicU64 = AtomicU64::new(HEALTH_DEFAULT_PING_INTERVAL_MS);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConnectionStateEvent {
+    pub port_name: String,
+    #[serde(flatten)]
+    pub state: ConnectionState,
+}
+
+struct MonitorHandle {
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+lazy_static::lazy_static! {
+    static ref MONITORS: Mutex<HashMap<String, MonitorHandle>> = Mutex::new(HashMap::new());
+}
+
+pub fn ping_interval() -> Duration {
+    Duration::from_millis(PING_INTERVAL_MS.load(Ordering::Relaxed))
+}
+
+// Idle time before a PING is sent; applies to every monitored connection
+pub fn set_ping_interval(interval_ms: u64) -> Result<u64, GcpCommError> {
+    if interval_ms < HEALTH_MIN_PING_INTERVAL_MS {
+        return Err(GcpCommError::invalid_parameter(format!(
+            "PING interval must be at least {} ms", HEALTH_MIN_PING_INTERVAL_MS)));
+    }
+    PING_INTERVAL_MS.store(interval_ms, Ordering::Relaxed);
+    Ok(interval_ms)
+}
+
+// Start monitoring a pooled connection and report it Connected. Does nothing if a
+// monitor is already running.
+pub fn start_monitor<R, E>(emitter: E, port_name: String) -> Result<(), GcpCommError>
+where
+    R: Runtime,
+    E: Emitter<R> + Send + 'static,
+{
+    let mut monitors = MONITORS.lock()
+        .map_err(|_| GcpCommError::internal("Failed to lock health monitor registry"))?;
+
+    if let Some(existing) = monitors.get(&port_name) {
+        if !existing.thread.is_finished() {
+            return Ok(());
+        }
+    }
+
+    report_state(&emitter, &port_name, ConnectionState::Connected);
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread_stop = stop.clone();
+    let thread_port = port_name.clone();
+    let thread = std::thread::Builder::new()
+        .name(format!("gcp-health-{}", port_name))
+        .spawn(move || monitor(emitter, thread_port, thread_stop))
+        .map_err(|e| GcpCommError::internal(format!("Failed to start health monitor: {}", e)))?;
+
+    monitors.insert(port_name, MonitorHandle { stop, thread });
+    Ok(())
+}
+
+pub fn stop_monitor(port_name: &str) {
+    let handle = match MONITORS.lock() {
+        Ok(mut monitors) => monitors.remove(port_name),
+        Err(_) => None,
+    };
+
+    if let Some(handle) = handle {
+        handle.stop.store(true, Ordering::Relaxed);
+        let _ = handle.thread.join();
+    }
+}
+
+// Record a connection's state and emit it if it changed
+pub fn report_state<R: Runtime, E: Emitter<R>>(emitter: &E, port_name: &str, state: ConnectionState) {
+    let previous = set_connection_state(port_name, state.clone());
+    if previous.as_ref() == Some(&state) {
+        return;
+    }
+
+    log::info!(target: "gcp::monitor", "Connection {} is now {:?}", port_name, state);
+    let _ = emitter.emit("connection-state-changed", ConnectionStateEvent {
+        port_name: port_name.to_string(),
+        state,
+    });
+}
+
+fn monitor<R: Runtime, E: Emitter<R>>(emitter: E, port_name: String, stop: Arc<AtomicBool>) {
+    log::debug!(target: "gcp::monitor", "Health monitor started for {}", port_name);
+    let mut health = LinkHealth::new(&port_name);
+    let mut last_check = Instant::now();
+
+    while !stop.load(Ordering::Relaxed) {
+        std::thread::sleep(Duration::from_millis(MONITOR_POLL_INTERVAL_MS));
+
+        // Connection was closed without stopping the monitor
+        let Some(handler_arc) = get_connection_handle(&port_name) else {
+            break;
+        };
+
+        if last_check.elapsed() < Duration::from_millis(HEALTH_LINK_CHECK_INTERVAL_MS) {
+            continue;
+        }
+        last_check = Instant::now();
+
+        if let Some(state) = health.check(&handler_arc) {
+            report_state(&emitter, &port_name, state);
+        }
+    }
+
+    log::debug!(target: "gcp::monitor", "Health monitor stopped for {}", port_name);
+}
+
+struct LinkHealth {
+    port_name: String,
+    listed: bool,               // The port was in the system port list when monitoring started
+    missed_pings: u32,
+    last_ping: Option<Instant>,
+}
+
+impl LinkHealth {
+    fn new(port_name: &str) -> Self {
+        Self {
+            port_name: port_name.to_string(),
+            listed: port_listed(port_name),
+            missed_pings: 0,
+            last_ping: None,
+        }
+    }
+
+    // The state the link is in, or None while there is nothing new to tell
+    fn check(&mut self, handler_arc: &Mutex<GcpUartHandler>) -> Option<ConnectionState> {
+        if self.listed && !port_listed(&self.port_name) {
+            return Some(ConnectionState::Error(format!("{} was removed", self.port_name)));
+        }
+
+        // A firmware job or command in progress owns the port, and its own responses
+        // show whether the device is alive
+        if firmware_job::active_job_for_port(&self.port_name).is_some() {
+            return None;
+        }
+        let Ok(mut handler) = handler_arc.try_lock() else {
+            return None;
+        };
+
+        if let Err(e) = handler.check_link() {
+            return Some(ConnectionState::Error(e.to_string()));
+        }
+
+        let interval = ping_interval();
+        if handler.idle_time() < interval {
+            self.missed_pings = 0;
+            return Some(ConnectionState::Connected);
+        }
+        if self.last_ping.is_some_and(|last_ping| last_ping.elapsed() < interval) {
+            return None;
+        }
+
+        self.last_ping = Some(Instant::now());
+        match handler.ping() {
+            // Even a NACK shows the device is there
+            Ok(()) | Err(GcpCommError::Nack { .. }) => {
+                self.missed_pings = 0;
+                Some(ConnectionState::Connected)
+            }
+            Err(e @ GcpCommError::Transport { .. }) => Some(ConnectionState::Error(e.to_string())),
+            Err(e) => {
+                self.missed_pings += 1;
+                log::warn!(target: "gcp::monitor", "PING on {} unanswered ({} of {}): {}", self.port_name, self.missed_pings, HEALTH_MAX_MISSED_PINGS, e);
+                (self.missed_pings >= HEALTH_MAX_MISSED_PINGS)
+                    .then(|| ConnectionState::Error(format!("Device not responding to PING: {}", e)))
+            }
+        }
+    }
+}
+
+// Whether the system lists this serial port. Only ports listed when monitoring starts
+// are checked, so PTYs and TCP or simulated links rely on I/O errors alone.
+fn port_listed(port_name: &str) -> bool {
+    if port_name.starts_with(TCP_TRANSPORT_PREFIX) || port_name.starts_with(SIMULATOR_PORT_PREFIX) {
+        return false;
+    }
+    serialport::available_ports()
+        .map(|ports| ports.iter().any(|port| port.port_name == port_name))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fault_injection::{Fault, FaultRule, FaultScript, FaultTrigger};
+    use crate::gcp::{connect_with_transport, disconnect_from_port, GcpCommand};
+    use crate::simulator::{spawn_memory_device, SimulatorConfig};
+    use std::sync::mpsc;
+    use tauri::Listener;
+
+    #[test]
+    fn test_unanswered_pings_put_connection_in_error() {
+        let port_name = "memory://test_connection_monitor";
+        // The device keeps running but none of its PING ACKs arrive
+        let lost_pings = FaultRule::new(GcpCommand::Ping, FaultTrigger::Always, Fault::DropResponse);
+        let config = SimulatorConfig { faults: Some(FaultScript { seed: None, rules: vec![lost_pings] }), ..SimulatorConfig::default() };
+        connect_with_transport(port_name.to_string(), Box::new(spawn_memory_device(config))).unwrap();
+        set_ping_interval(HEALTH_MIN_PING_INTERVAL_MS).unwrap();
+
+        let app = tauri::test::mock_app();
+        let (state_tx, states) = mpsc::channel();
+        app.handle().listen_any("connection-state-changed", move |event| {
+            let _ = state_tx.send(serde_json::from_str::<ConnectionStateEvent>(event.payload()).unwrap());
+        });
+        start_monitor(app.handle().clone(), port_name.to_string()).unwrap();
+
+        let connected = states.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert_eq!((connected.port_name.as_str(), connected.state), (port_name, ConnectionState::Connected));
+        let failed = states.recv_timeout(Duration::from_secs(20)).unwrap();
+        assert_eq!(failed.port_name, port_name);
+        assert!(matches!(&failed.state, ConnectionState::Error(message) if message.starts_with("Device not responding to PING")),
+                "{:?}", failed.state);
+
+        stop_monitor(port_name);
+        set_ping_interval(HEALTH_DEFAULT_PING_INTERVAL_MS).unwrap();
+        disconnect_from_port(port_name.to_string()).unwrap();
+    }
+}
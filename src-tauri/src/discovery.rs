@@ -0,0 +1,156 @@
+//! Glitchi device discovery
+//!
+//! Probes every listed port with a single HELLO (§4.1), all ports in parallel, and
+//! sorts them into devices that answered with HWVersion_t and ports skipped with the
+//! reason why. Ports opened for probing are closed again before discovery returns;
+//! ports already connected are asked over their pooled connection instead.
+
+use serde::{Deserialize, Serialize};
+
+use crate::firmware_job;
+use crate::gcp::{get_connection_handle, GcpCommError, GcpHardwareData, GcpHelloResponse, GcpUartHandler};
+use crate::COMPortInfo;
+
+pub const DISCOVERY_DEFAULT_TIMEOUT_MS: u64 = 300;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscoveredDevice {
+    pub port: COMPortInfo,
+    pub serial_number: u16,
+    pub board_type: String,
+    pub chip_model: String,
+    pub features: Vec<String>,
+    pub hardware: GcpHardwareData,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SkippedPort {
+    pub port: COMPortInfo,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DiscoveryReport {
+    pub devices: Vec<DiscoveredDevice>,
+    pub skipped: Vec<SkippedPort>,
+}
+
+impl DiscoveredDevice {
+    fn new(port: COMPortInfo, hardware: GcpHardwareData) -> Self {
+        let unknown = |value: u8| format!("Unknown (0x{:02X})", value);
+        Self {
+            port,
+            serial_number: hardware.serial_number,
+            board_type: hardware.board_type_name().map_or_else(|| unknown(hardware.board_type), str::to_string),
+            chip_model: hardware.chip_model_name().map_or_else(|| unknown(hardware.chip_model), str::to_string),
+            features: hardware.feature_names().into_iter().map(str::to_string).collect(),
+            hardware,
+        }
+    }
+}
+
+pub fn discover_devices(ports: Vec<COMPortInfo>, timeout_ms: u64) -> DiscoveryReport {
+    let results: Vec<(COMPortInfo, Result<GcpHardwareData, String>)> = std::thread::scope(|scope| {
+        let probes: Vec<_> = ports
+            .into_iter()
+            .map(|port| {
+                let port_name = port.port.clone();
+                (port, scope.spawn(move || probe_port(&port_name, timeout_ms)))
+            })
+            .collect();
+        // A probe that panicked still leaves its port in the report
+        probes
+            .into_iter()
+            .map(|(port, probe)| {
+                let result = probe.join().unwrap_or_else(|panic| Err(format!("Probe failed: {}", panic_message(&*panic))));
+                (port, result)
+            })
+            .collect()
+    });
+
+    let mut report = DiscoveryReport::default();
+    for (port, result) in results {
+        match result {
+            Ok(hardware) => report.devices.push(DiscoveredDevice::new(port, hardware)),
+            Err(reason) => report.skipped.push(SkippedPort { port, reason }),
+        }
+    }
+    log::info!(target: "gcp::discovery", "Discovery found {} device(s), skipped {} port(s)", report.devices.len(), report.skipped.len());
+    report
+}
+
+fn probe_port(port_name: &str, timeout_ms: u64) -> Result<GcpHardwareData, String> {
+    // Connected ports can't be opened twice; ask the pooled connection, unless a
+    // firmware update owns it
+    if let Some(handler_arc) = get_connection_handle(port_name) {
+        if firmware_job::active_job_for_port(port_name).is_some() {
+            return Err("Firmware update in progress".to_string());
+        }
+        let mut handler = handler_arc.lock().map_err(|_| "Connection lock failed".to_string())?;
+        if let Some(hardware) = handler.hardware() {
+            return Ok(hardware);
+        }
+        return identify(&mut handler, timeout_ms);
+    }
+
+    // Dropped on return, closing the port
+    let mut handler = GcpUartHandler::new(port_name).map_err(|e| e.to_string())?;
+    identify(&mut handler, timeout_ms)
+}
+
+fn panic_message(panic: &(dyn std::any::Any + Send)) -> &str {
+    match (panic.downcast_ref::<&str>(), panic.downcast_ref::<String>()) {
+        (Some(message), _) => message,
+        (_, Some(message)) => message,
+        _ => "panicked",
+    }
+}
+
+fn identify(handler: &mut GcpUartHandler, timeout_ms: u64) -> Result<GcpHardwareData, String> {
+    match handler.probe_hello(timeout_ms) {
+        Ok(GcpHelloResponse::Hardware(hardware)) => Ok(hardware),
+        Ok(GcpHelloResponse::Status(_)) => Err("Answered HELLO with status data (pre-v2.2 firmware)".to_string()),
+        Err(GcpCommError::Timeout { .. }) if handler.discarded_bytes() > 0 => {
+            Err(format!("Sent {} bytes that are not GCP frames", handler.discarded_bytes()))
+        }
+        Err(GcpCommError::Timeout { .. }) => Err("No answer to HELLO".to_string()),
+        Err(e) => Err(format!("Invalid HELLO response: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulator::SIMULATOR_PORT_PREFIX;
+
+    fn port(name: &str) -> COMPortInfo {
+        COMPortInfo {
+            port: name.to_string(),
+            description: name.to_string(),
+            manufacturer: None,
+            serial_number: None,
+            vendor_id: None,
+            product_id: None,
+            port_type: "Unknown".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_discover_simulated_devices() {
+        let ports = vec![
+            port(&format!("{}4242", SIMULATOR_PORT_PREFIX)),
+            port("/nonexistent/ttyGCP0"),
+        ];
+        let report = discover_devices(ports, DISCOVERY_DEFAULT_TIMEOUT_MS);
+
+        assert_eq!(report.devices.len(), 1);
+        let device = &report.devices[0];
+        assert_eq!(device.serial_number, 4242);
+        assert_eq!(device.hardware.serial_number, 4242);
+        assert_eq!(device.chip_model, "Apollo4Lite");
+
+        assert_eq!(report.skipped.len(), 1);
+        assert_eq!(report.skipped[0].port.port, "/nonexistent/ttyGCP0");
+        assert!(get_connection_handle(&device.port.port).is_none());
+    }
+}
@@ -0,0 +1,278 @@
+//! Scriptable fault injection for the simulated device
+//!
+//! A `FaultScript` is a list of rules that pick host frames by command and occurrence
+//! and disturb how the simulated device answers them: the request or its response
+//! goes missing, the response arrives corrupted, late, behind line noise, split over
+//! several reads or coalesced with a stale frame, the device NACKs, or it reboots.
+//!
+//! Probabilistic rules draw from a SplitMix64 generator. The seed comes from the
+//! script, else from `GCP_FAULT_SEED`, else from the clock, and is logged when the
+//! injector starts, so a failing run can be replayed exactly.
+
+use serde::{Deserialize, Serialize};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::gcp::{GcpCommand, GcpError};
+use crate::protocol_log::{LogLevel, ProtocolEvent, TARGET_SIM};
+
+pub const FAULT_SEED_ENV: &str = "GCP_FAULT_SEED";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Fault {
+    DropRequest,        // The frame is lost on the wire; the device never sees it
+    DropResponse,       // The device handles the frame but its response is lost
+    CorruptCrc,         // The response CRC16 is flipped
+    Garbage(usize),     // This many noise bytes precede the response
+    Split(usize),       // The response is written in pieces of this many bytes
+    Coalesce,           // The previous response is repeated in the same write
+    Delay(u64),         // The response is held back this many milliseconds
+    Nack(GcpError),     // The device NACKs with this error instead of handling the frame
+    Reboot,             // The device reboots on receipt and answers nothing
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FaultTrigger {
+    #[default]
+    Always,
+    Nth(u32),           // Only the nth matching frame (1-based)
+    Every(u32),         // Every nth matching frame
+    Probability(f64),   // Each matching frame with this probability
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FaultRule {
+    #[serde(default)]
+    pub command: Option<GcpCommand>,    // None matches every command
+    #[serde(default)]
+    pub trigger: FaultTrigger,
+    pub fault: Fault,
+    #[serde(default)]
+    pub times: Option<u32>,             // Stop after injecting this many faults
+}
+
+impl FaultRule {
+    pub fn new(command: GcpCommand, trigger: FaultTrigger, fault: Fault) -> Self {
+        Self { command: Some(command), trigger, fault, times: None }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FaultScript {
+    pub seed: Option<u64>,
+    pub rules: Vec<FaultRule>,
+}
+
+// A fault that was applied, for test assertions and replay logs
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InjectedFault {
+    pub frame_index: u64,
+    pub msg_type: u16,
+    pub fault: Fault,
+}
+
+pub struct FaultInjector {
+    seed: u64,
+    rng: SplitMix64,
+    rules: Vec<RuleState>,
+    frames: u64,
+    injected: Vec<InjectedFault>,
+}
+
+struct RuleState {
+    rule: FaultRule,
+    matched: u32,
+    injected: u32,
+}
+
+impl FaultInjector {
+    pub fn new(script: FaultScript) -> Self {
+        let seed = script.seed
+            .or_else(|| std::env::var(FAULT_SEED_ENV).ok().and_then(|seed| seed.parse().ok()))
+            .unwrap_or_else(clock_seed);
+        ProtocolEvent::new(LogLevel::Info, TARGET_SIM, "")
+            .log(format!("Fault injection seed {} (replay with {}={})", seed, FAULT_SEED_ENV, seed));
+
+        Self {
+            seed,
+            rng: SplitMix64(seed),
+            rules: script.rules.into_iter().map(|rule| RuleState { rule, matched: 0, injected: 0 }).collect(),
+            frames: 0,
+            injected: Vec::new(),
+        }
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn injected(&self) -> &[InjectedFault] {
+        &self.injected
+    }
+
+    // Decide the fault, if any, for the next host frame. Every matching rule counts the
+    // frame; the first one whose trigger fires wins.
+    pub fn next_fault(&mut self, msg_type: u16) -> Option<Fault> {
+        self.frames += 1;
+
+        let mut chosen = None;
+        for state in &mut self.rules {
+            let matches = match state.rule.command {
+                Some(command) => command as u16 == msg_type,
+                None => true,
+            };
+            if !matches {
+                continue;
+            }
+            state.matched += 1;
+
+            let exhausted = state.rule.times.is_some_and(|times| state.injected >= times);
+            if chosen.is_some() || exhausted {
+                continue;
+            }
+            let fires = match state.rule.trigger {
+                FaultTrigger::Always => true,
+                FaultTrigger::Nth(n) => state.matched == n,
+                FaultTrigger::Every(n) => n > 0 && state.matched % n == 0,
+                FaultTrigger::Probability(p) => self.rng.next_f64() < p,
+            };
+            if fires {
+                state.injected += 1;
+                chosen = Some(state.rule.fault);
+            }
+        }
+
+        if let Some(fault) = chosen {
+            ProtocolEvent::new(LogLevel::Debug, TARGET_SIM, "")
+                .msg_type(msg_type)
+                .log(format!("Injecting {:?} on frame {}", fault, self.frames));
+            self.injected.push(InjectedFault { frame_index: self.frames, msg_type, fault });
+        }
+        chosen
+    }
+
+    // Line noise that cannot be mistaken for a preamble
+    pub fn garbage(&mut self, len: usize) -> Vec<u8> {
+        (0..len)
+            .map(|_| match self.rng.next_u64() as u8 {
+                0xAA => 0x00,
+                byte => byte,
+            })
+            .collect()
+    }
+}
+
+fn clock_seed() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_nanos() as u64)
+        .unwrap_or(0)
+}
+
+// SplitMix64: tiny, seedable and identical on every platform
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E3779B97F4A7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+        z ^ (z >> 31)
+    }
+
+    // Uniform in [0, 1)
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gcp::{connect_with_transport, disconnect_from_port, execute_with_connection, GcpCommError};
+    use crate::simulator::{spawn_memory_device, SimulatorConfig};
+
+    #[test]
+    fn test_fault_script_is_reproducible() {
+        let script: FaultScript = serde_json::from_str(r#"{
+            "seed": 1234,
+            "rules": [
+                {"command": "FW_UPDATE_DATA", "trigger": {"nth": 2}, "fault": "drop_response"},
+                {"command": "FW_UPDATE_DATA", "trigger": {"probability": 0.3}, "fault": {"nack": "CRC"}, "times": 5},
+                {"trigger": {"every": 4}, "fault": {"delay": 50}}
+            ]
+        }"#).unwrap();
+
+        let run = |script: FaultScript| {
+            let mut injector = FaultInjector::new(script);
+            for _ in 0..200 {
+                injector.next_fault(GcpCommand::FwUpdateData as u16);
+                injector.next_fault(GcpCommand::GetStatus as u16);
+            }
+            injector.injected().to_vec()
+        };
+
+        let first = run(script.clone());
+        assert_eq!(first, run(script));
+        assert!(first.contains(&InjectedFault { frame_index: 3, msg_type: 0x1002, fault: Fault::DropResponse }));
+        assert_eq!(first.iter().filter(|f| f.fault == Fault::Nack(GcpError::Crc)).count(), 5);
+        assert!(first.iter().any(|f| f.fault == Fault::Delay(50)));
+    }
+
+    #[test]
+    fn test_retry_paths_under_faults() {
+        let data_fault = |n, fault| FaultRule::new(GcpCommand::FwUpdateData, FaultTrigger::Nth(n), fault);
+        let config = SimulatorConfig {
+            faults: Some(FaultScript {
+                seed: Some(7),
+                rules: vec![
+                    FaultRule::new(GcpCommand::GetStatus, FaultTrigger::Nth(1), Fault::Garbage(24)),
+                    FaultRule::new(GcpCommand::GetStatus, FaultTrigger::Nth(2), Fault::Split(3)),
+                    FaultRule::new(GcpCommand::GetFwVersion, FaultTrigger::Nth(1), Fault::CorruptCrc),
+                    FaultRule::new(GcpCommand::GetDiagnostics, FaultTrigger::Nth(1), Fault::Delay(1200)),
+                    FaultRule::new(GcpCommand::FwUpdateStart, FaultTrigger::Nth(1), Fault::Nack(GcpError::Busy)),
+                    data_fault(2, Fault::DropResponse),
+                    data_fault(3, Fault::Reboot),
+                    data_fault(5, Fault::Coalesce),
+                    data_fault(8, Fault::DropRequest),
+                    FaultRule::new(GcpCommand::FwUpdateEnd, FaultTrigger::Nth(1), Fault::DropRequest),
+                ],
+            }),
+            ..SimulatorConfig::default()
+        };
+        let port = "faulty-device".to_string();
+        connect_with_transport(port.clone(), Box::new(spawn_memory_device(config))).unwrap();
+
+        let image: Vec<u8> = (0..6000u32).map(|i| (i * 13) as u8).collect();
+        let result = execute_with_connection(&port, |handler| {
+            handler.get_status()?;
+            handler.get_status()?;
+            handler.get_fw_version()?;
+            handler.get_diagnostics()?;
+
+            // NACKs are final for the command; the caller decides whether to try again
+            let busy = handler.start_firmware_update(&image, 1000);
+            assert!(matches!(busy, Err(GcpCommError::Nack { error: Some(GcpError::Busy), .. })));
+            handler.start_firmware_update(&image, 1000)?;
+
+            handler.send_firmware_chunk(&image[..1000], 0)?;
+
+            // The second chunk's ACK is lost and the device reboots on its resend, which
+            // drops the transfer: the next resend is refused, and the host starts over
+            let lost = handler.send_firmware_chunk(&image[1000..2000], 1000);
+            assert!(matches!(lost, Err(GcpCommError::Nack { error: Some(GcpError::InvalidParam), .. })));
+
+            handler.start_firmware_update(&image, 1000)?;
+            for (index, chunk) in image.chunks(1000).enumerate() {
+                handler.send_firmware_chunk(chunk, (index * 1000) as u32)?;
+            }
+            handler.end_firmware_update()
+        });
+        disconnect_from_port(port).unwrap();
+
+        assert!(result.unwrap());
+    }
+}
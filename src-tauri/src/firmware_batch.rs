@@ -0,0 +1,404 @@
+//! Batch firmware flashing for the production line
+//!
+//! A batch flashes one image to many ports at once. Each port gets its own firmware
+//! job (FW_UPDATE_START / DATA / END, see firmware_job) and, once the image is
+//! verified, RESET to apply it. Every device runs on its own thread, so a failing or
+//! slow board never holds up the others. Jobs report their own progress
+//! ("firmware-progress" and "firmware-job-updated", tagged with the port); the batch
+//! emits "firmware-batch-updated" whenever a device changes state and, when every
+//! device is done, the report sorted by device serial as "firmware-batch-completed".
+//!
+//! Ports that aren't connected are opened for the batch and closed again afterwards;
+//! ports that were already connected are reconnected after the RESET, as with
+//! gcp_reset_device. A finished batch's report can be read for ten minutes.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Runtime};
+
+use crate::firmware_checkpoint::checkpoint_dir;
+use crate::firmware_image::FirmwareImage;
+use crate::firmware_job::{self, FirmwareJobOptions, FirmwareJobState};
+use crate::link_config;
+use crate::reconnect::{self, DeviceIdentity};
+use crate::gcp::{connect_to_port, disconnect_from_port, execute_with_connection, gcp_crc32, get_connection_handle, GcpCommError};
+
+const BATCH_POLL_INTERVAL_MS: u64 = 200;
+// How long a finished batch's report stays readable
+const BATCH_REPORT_RETENTION: Duration = Duration::from_secs(600);
+// RESET type that applies the new image (§4.8)
+const RESET_APPLY_FIRMWARE: u16 = 0x0002;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchDeviceState {
+    Pending,
+    Flashing,
+    Resetting,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatchDeviceStatus {
+    pub port_name: String,
+    pub job_id: Option<String>,
+    pub device_serial: Option<u16>,
+    pub state: BatchDeviceState,
+    pub bytes_acked: u32,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FirmwareBatchStatus {
+    pub batch_id: String,
+    pub total_bytes: u32,
+    pub image_crc32: u32,
+    pub finished: bool,
+    pub succeeded: u32,
+    pub failed: u32,               // Failed or cancelled
+    pub devices: Vec<BatchDeviceStatus>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FirmwareBatchOptions {
+    pub image_path: Option<String>,
+    // Send RESET to apply the image once a device has verified it
+    pub reset: bool,
+}
+
+struct FirmwareBatch {
+    status: Mutex<FirmwareBatchStatus>,
+    cancel: AtomicBool,
+    finished_at: Mutex<Option<Instant>>,
+}
+
+lazy_static::lazy_static! {
+    static ref BATCHES: Mutex<HashMap<String, Arc<FirmwareBatch>>> = Mutex::new(HashMap::new());
+}
+
+static NEXT_BATCH_ID: AtomicU64 = AtomicU64::new(1);
+
+pub fn start_batch<R: Runtime>(
+    app: AppHandle<R>,
+    port_names: Vec<String>,
+    image: FirmwareImage,
+    options: FirmwareBatchOptions,
+) -> Result<FirmwareBatchStatus, GcpCommError> {
+    if port_names.is_empty() {
+        return Err(GcpCommError::invalid_parameter("No ports given for batch flashing"));
+    }
+    if image.data.is_empty() {
+        return Err(GcpCommError::invalid_parameter("Firmware image is empty"));
+    }
+    let mut seen = HashSet::new();
+    if let Some(duplicate) = port_names.iter().find(|port_name| !seen.insert(*port_name)) {
+        return Err(GcpCommError::invalid_parameter(format!("{} is listed more than once", duplicate)));
+    }
+
+    let batch_id = format!("batch-{}", NEXT_BATCH_ID.fetch_add(1, Ordering::Relaxed));
+    let batch = Arc::new(FirmwareBatch {
+        status: Mutex::new(FirmwareBatchStatus {
+            batch_id: batch_id.clone(),
+            total_bytes: image.data.len() as u32,
+            image_crc32: gcp_crc32(&image.data),
+            finished: false,
+            succeeded: 0,
+            failed: 0,
+            devices: port_names.iter().map(|port_name| BatchDeviceStatus {
+                port_name: port_name.clone(),
+                job_id: None,
+                device_serial: None,
+                state: BatchDeviceState::Pending,
+                bytes_acked: 0,
+                message: "Waiting to start".to_string(),
+            }).collect(),
+        }),
+        cancel: AtomicBool::new(false),
+        finished_at: Mutex::new(None),
+    });
+
+    {
+        let mut batches = BATCHES.lock()
+            .map_err(|_| GcpCommError::internal("Failed to lock firmware batch registry"))?;
+        batches.retain(|_, batch| !batch.expired());
+        batches.insert(batch_id.clone(), batch.clone());
+    }
+
+    let thread_batch = batch.clone();
+    std::thread::Builder::new()
+        .name(format!("gcp-fw-{}", batch_id))
+        .spawn(move || run_batch(thread_batch, port_names, image, options, app))
+        .map_err(|e| GcpCommError::internal(format!("Failed to start firmware batch: {}", e)))?;
+
+    Ok(batch.snapshot())
+}
+
+pub fn batch_status(batch_id: &str) -> Result<FirmwareBatchStatus, GcpCommError> {
+    Ok(find_batch(batch_id)?.snapshot())
+}
+
+// Cancel every device still flashing; devices already resetting are left to finish
+pub fn cancel_batch(batch_id: &str) -> Result<FirmwareBatchStatus, GcpCommError> {
+    let batch = find_batch(batch_id)?;
+    batch.cancel.store(true, Ordering::Relaxed);
+    Ok(batch.snapshot())
+}
+
+fn find_batch(batch_id: &str) -> Result<Arc<FirmwareBatch>, GcpCommError> {
+    let mut batches = BATCHES.lock()
+        .map_err(|_| GcpCommError::internal("Failed to lock firmware batch registry"))?;
+    batches.retain(|_, batch| !batch.expired());
+    batches.get(batch_id)
+        .cloned()
+        .ok_or_else(|| GcpCommError::invalid_parameter(format!("Unknown firmware batch {}", batch_id)))
+}
+
+impl FirmwareBatch {
+    fn snapshot(&self) -> FirmwareBatchStatus {
+        match self.status.lock() {
+            Ok(status) => status.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    fn expired(&self) -> bool {
+        let finished_at = match self.finished_at.lock() {
+            Ok(finished_at) => *finished_at,
+            Err(poisoned) => *poisoned.into_inner(),
+        };
+        finished_at.is_some_and(|finished_at| finished_at.elapsed() >= BATCH_REPORT_RETENTION)
+    }
+
+    // Update one device's entry and emit the batch if its state changed
+    fn update_device<R: Runtime, E: Emitter<R>>(&self, emitter: &E, index: usize, update: impl FnOnce(&mut BatchDeviceStatus)) {
+        let snapshot = {
+            let mut status = match self.status.lock() {
+                Ok(status) => status,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            let previous = status.devices[index].state;
+            update(&mut status.devices[index]);
+            if status.devices[index].state == previous {
+                return;
+            }
+            status.clone()
+        };
+        let _ = emitter.emit("firmware-batch-updated", &snapshot);
+    }
+
+    fn finish_device<R: Runtime, E: Emitter<R>>(&self, emitter: &E, index: usize, state: BatchDeviceState, message: String) {
+        self.update_device(emitter, index, |device| {
+            device.state = state;
+            device.message = message;
+        });
+    }
+}
+
+fn run_batch<R: Runtime>(batch: Arc<FirmwareBatch>, port_names: Vec<String>, image: FirmwareImage, options: FirmwareBatchOptions, app: AppHandle<R>) {
+    log::info!(target: "gcp::fw", "Firmware {} flashing {} bytes to {} devices", batch.snapshot().batch_id, image.data.len(), port_names.len());
+
+    std::thread::scope(|scope| {
+        for (index, port_name) in port_names.iter().enumerate() {
+            let (batch, image, options, app) = (&batch, image.clone(), &options, app.clone());
+            scope.spawn(move || flash_device(batch, index, port_name, image, options, app));
+        }
+    });
+
+    let report = {
+        let mut status = match batch.status.lock() {
+            Ok(status) => status,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        // Devices that couldn't be identified go last
+        status.devices.sort_by_key(|device| (device.device_serial.is_none(), device.device_serial, device.port_name.clone()));
+        status.succeeded = status.devices.iter().filter(|device| device.state == BatchDeviceState::Completed).count() as u32;
+        status.failed = status.devices.len() as u32 - status.succeeded;
+        status.finished = true;
+        status.clone()
+    };
+    if let Ok(mut finished_at) = batch.finished_at.lock() {
+        *finished_at = Some(Instant::now());
+    }
+
+    log::info!(target: "gcp::fw", "Firmware {} finished: {} succeeded, {} failed", report.batch_id, report.succeeded, report.failed);
+    for device in &report.devices {
+        let serial = device.device_serial.map_or_else(|| "unknown".to_string(), |serial| serial.to_string());
+        log::info!(target: "gcp::fw", "  serial {} on {}: {:?} - {}", serial, device.port_name, device.state, device.message);
+    }
+    let _ = app.emit("firmware-batch-completed", &report);
+}
+
+fn flash_device<R: Runtime>(batch: &FirmwareBatch, index: usize, port_name: &str, image: FirmwareImage, options: &FirmwareBatchOptions, app: AppHandle<R>) {
+    if batch.cancel.load(Ordering::Relaxed) {
+        batch.finish_device(&app, index, BatchDeviceState::Cancelled, "Batch cancelled before start".to_string());
+        return;
+    }
+
+    let opened = get_connection_handle(port_name).is_none();
+    if opened {
+        if let Err(e) = connect_to_port(port_name.to_string(), link_config::profile_for(port_name)) {
+            batch.finish_device(&app, index, BatchDeviceState::Failed, format!("Failed to open port: {}", e));
+            return;
+        }
+    }
+
+    let (state, message) = flash_and_reset(batch, index, port_name, image, options, &app, opened);
+    batch.finish_device(&app, index, state, message);
+
+    if opened {
+        let _ = disconnect_from_port(port_name.to_string());
+    }
+}
+
+// `opened` is set for ports the batch opened itself and closes again afterwards
+fn flash_and_reset<R: Runtime>(
+    batch: &FirmwareBatch,
+    index: usize,
+    port_name: &str,
+    image: FirmwareImage,
+    options: &FirmwareBatchOptions,
+    app: &AppHandle<R>,
+    opened: bool,
+) -> (BatchDeviceState, String) {
+    let job_options = FirmwareJobOptions {
+        image_path: options.image_path.clone(),
+        resume: false,
+        checkpoint_dir: checkpoint_dir().ok(),
+    };
+    let job = match firmware_job::start_job(app.clone(), port_name, image, job_options) {
+        Ok(job) => job,
+        Err(e) => return (BatchDeviceState::Failed, format!("Failed to start firmware update: {}", e)),
+    };
+    batch.update_device(app, index, |device| {
+        device.job_id = Some(job.job_id.clone());
+        device.state = BatchDeviceState::Flashing;
+        device.message = job.message.clone();
+    });
+
+    let mut cancel_sent = false;
+    let finished = loop {
+        std::thread::sleep(Duration::from_millis(BATCH_POLL_INTERVAL_MS));
+        if batch.cancel.load(Ordering::Relaxed) && !cancel_sent {
+            let _ = firmware_job::cancel_job(&job.job_id);
+            cancel_sent = true;
+        }
+
+        let status = match firmware_job::job_status(&job.job_id) {
+            Ok(status) => status,
+            Err(e) => return (BatchDeviceState::Failed, e.to_string()),
+        };
+        batch.update_device(app, index, |device| {
+            device.device_serial = status.device_serial;
+            device.bytes_acked = status.bytes_acked;
+        });
+        if status.state.is_finished() {
+            break status;
+        }
+    };
+
+    match finished.state {
+        FirmwareJobState::Completed if options.reset => {}
+        FirmwareJobState::Completed => return (BatchDeviceState::Completed, finished.message),
+        FirmwareJobState::Cancelled => return (BatchDeviceState::Cancelled, finished.message),
+        _ => return (BatchDeviceState::Failed, finished.message),
+    }
+
+    batch.update_device(app, index, |device| {
+        device.state = BatchDeviceState::Resetting;
+        device.message = "Applying firmware".to_string();
+    });
+    let reset = execute_with_connection(port_name, |handler| {
+        let identity = DeviceIdentity::capture(port_name, handler);
+        handler.reset_device(RESET_APPLY_FIRMWARE)?;
+        Ok(identity)
+    });
+    match reset {
+        Ok(identity) => {
+            // The pooled handler may be dead after the reset; bring the connection back
+            if !opened {
+                if let Err(e) = reconnect::start_reconnect(app.clone(), port_name.to_string(), identity) {
+                    log::warn!(target: "gcp::fw", "Failed to start reconnect on {}: {}", port_name, e);
+                }
+            }
+            (BatchDeviceState::Completed, format!("{}; reset to apply", finished.message))
+        }
+        Err(e) => (BatchDeviceState::Failed, format!("Firmware verified but reset failed: {}", e)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fault_injection::{Fault, FaultRule, FaultScript, FaultTrigger};
+    use crate::firmware_signing::tests::{image_from, trusted_test_container};
+    use crate::gcp::{connect_with_transport, GcpCommand, GcpError};
+    use crate::reconnect::{cancel_reconnect, DeviceRebootedEvent};
+    use crate::simulator::{spawn_memory_device, SimulatorConfig};
+    use std::sync::mpsc;
+    use tauri::Listener;
+
+    fn simulated_device(port_name: &str, serial_number: u16, faults: Option<FaultScript>) {
+        let mut config = SimulatorConfig { faults, ..SimulatorConfig::default() };
+        config.hardware.serial_number = serial_number;
+        connect_with_transport(port_name.to_string(), Box::new(spawn_memory_device(config))).unwrap();
+    }
+
+    #[test]
+    fn test_batch_reports_each_device_by_serial() {
+        let (good_port, failing_port) = ("memory://test_firmware_batch_good", "memory://test_firmware_batch_failing");
+        simulated_device(good_port, 7, None);
+        // MRAM write errors are not retried
+        let mram_error = FaultRule::new(GcpCommand::FwUpdateData, FaultTrigger::Always, Fault::Nack(GcpError::Mram));
+        simulated_device(failing_port, 3, Some(FaultScript { seed: None, rules: vec![mram_error] }));
+
+        let app = tauri::test::mock_app();
+        let (report_tx, reports) = mpsc::channel();
+        app.handle().listen_any("firmware-batch-completed", move |event| {
+            let _ = report_tx.send(serde_json::from_str::<FirmwareBatchStatus>(event.payload()).unwrap());
+        });
+        let (rebooted_tx, rebooted) = mpsc::channel();
+        app.handle().listen_any("device-rebooted", move |event| {
+            let _ = rebooted_tx.send(serde_json::from_str::<DeviceRebootedEvent>(event.payload()).unwrap());
+        });
+
+        let payload: Vec<u8> = (0..5000u32).map(|i| (i * 11) as u8).collect();
+        let image = image_from(&trusted_test_container(&payload));
+        let options = FirmwareBatchOptions { image_path: None, reset: true };
+        let started = start_batch(app.handle().clone(), vec![good_port.to_string(), failing_port.to_string()], image, options).unwrap();
+
+        let report = reports.recv_timeout(Duration::from_secs(20)).unwrap();
+        assert_eq!(report.batch_id, started.batch_id);
+        assert!(report.finished);
+        assert_eq!((report.succeeded, report.failed), (1, 1));
+
+        let devices: Vec<_> = report.devices.iter().map(|device| (device.device_serial, device.port_name.as_str(), device.state)).collect();
+        assert_eq!(devices, [
+            (Some(3), failing_port, BatchDeviceState::Failed),
+            (Some(7), good_port, BatchDeviceState::Completed),
+        ]);
+        assert!(report.devices[0].message.contains("Mram"), "{}", report.devices[0].message);
+        assert_eq!(report.devices[1].bytes_acked, payload.len() as u32);
+        assert!(report.devices[1].message.ends_with("reset to apply"), "{}", report.devices[1].message);
+
+        // The flashed device, connected before the batch, is reconnected after its reset
+        let event = rebooted.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!(event.port_name, good_port);
+        assert_eq!(event.hardware.map(|hardware| hardware.serial_number), Some(7));
+
+        // The report can be read again until it expires
+        assert_eq!(batch_status(&report.batch_id).unwrap().succeeded, 1);
+        assert_eq!(batch_status(&report.batch_id).unwrap().succeeded, 1);
+        let finished_at = Instant::now().checked_sub(BATCH_REPORT_RETENTION).unwrap();
+        *find_batch(&report.batch_id).unwrap().finished_at.lock().unwrap() = Some(finished_at);
+        assert!(batch_status(&report.batch_id).is_err());
+
+        cancel_reconnect(good_port);
+        disconnect_from_port(good_port.to_string()).unwrap();
+        disconnect_from_port(failing_port.to_string()).unwrap();
+    }
+}
@@ -0,0 +1,136 @@
+//! Persisted firmware transfer checkpoints
+//!
+//! A firmware job records the image it is sending and the last ACK'd offset while
+//! it runs, keyed by the device serial number from HELLO (HWVersion_t). If the
+//! link or the device drops mid-transfer, the app can offer to send that image
+//! again. The transfer then restarts at offset 0: GCP v2.2 has no way to ask the
+//! device how much of the interrupted transfer it kept.
+
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::app_store::{load_json, remove_json, save_json};
+use crate::gcp::{gcp_crc32, GcpCommError};
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FirmwareCheckpoint {
+    pub device_serial: u16,
+    pub image_path: Option<String>,
+    pub image_size: u32,
+    pub image_crc32: u32,
+    pub chunk_size: u16,
+    pub acked_offset: u32,       // Everything before this offset was ACK'd by the device
+    pub updated_at: u64,         // Unix time (seconds)
+}
+
+lazy_static::lazy_static! {
+    static ref CHECKPOINT_DIR: Mutex<Option<PathBuf>> = Mutex::new(None);
+}
+
+impl FirmwareCheckpoint {
+    pub fn new(device_serial: u16, image_path: Option<String>, firmware_data: &[u8], chunk_size: u16) -> Self {
+        Self {
+            device_serial,
+            image_path,
+            image_size: firmware_data.len() as u32,
+            image_crc32: gcp_crc32(firmware_data),
+            chunk_size,
+            acked_offset: 0,
+            updated_at: unix_time(),
+        }
+    }
+
+    // Resuming is only valid for the same image, started with the same chunk size.
+    // The offset needn't be a chunk boundary: GCP_ERROR_SIZE can shrink the chunks
+    // mid-transfer, and SeqNo is the byte offset anyway.
+    pub fn matches(&self, firmware_data: &[u8], chunk_size: u16) -> bool {
+        self.image_size == firmware_data.len() as u32
+            && self.chunk_size == chunk_size
+            && self.image_crc32 == gcp_crc32(firmware_data)
+            && self.acked_offset < self.image_size
+    }
+}
+
+// Checkpoints live in the app data directory, set once during startup
+pub fn set_checkpoint_dir(dir: PathBuf) {
+    if let Ok(mut current) = CHECKPOINT_DIR.lock() {
+        *current = Some(dir);
+    }
+}
+
+pub fn checkpoint_dir() -> Result<PathBuf, GcpCommError> {
+    CHECKPOINT_DIR.lock()
+        .map_err(|_| GcpCommError::internal("Failed to lock checkpoint directory"))?
+        .clone()
+        .ok_or_else(|| GcpCommError::internal("Checkpoint directory is not configured"))
+}
+
+fn checkpoint_path(dir: &Path, device_serial: u16) -> PathBuf {
+    dir.join(format!("fw_checkpoint_{}.json", device_serial))
+}
+
+pub fn load_checkpoint(dir: &Path, device_serial: u16) -> Result<Option<FirmwareCheckpoint>, GcpCommError> {
+    load_json(&checkpoint_path(dir, device_serial), "firmware checkpoint")
+}
+
+pub fn save_checkpoint(dir: &Path, checkpoint: &FirmwareCheckpoint) -> Result<(), GcpCommError> {
+    let checkpoint = FirmwareCheckpoint { updated_at: unix_time(), ..checkpoint.clone() };
+    save_json(&checkpoint_path(dir, checkpoint.device_serial), &checkpoint, "firmware checkpoint")
+}
+
+pub fn clear_checkpoint(dir: &Path, device_serial: u16) -> Result<(), GcpCommError> {
+    remove_json(&checkpoint_path(dir, device_serial), "firmware checkpoint")
+}
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app_store::tests::test_dir;
+
+    #[test]
+    fn test_checkpoint_matches_image() {
+        let image: Vec<u8> = (0..5000u32).map(|i| i as u8).collect();
+        let mut checkpoint = FirmwareCheckpoint::new(1000, None, &image, 2036);
+        checkpoint.acked_offset = 2036;
+
+        assert!(checkpoint.matches(&image, 2036));
+        assert!(!checkpoint.matches(&image, 1024));
+        assert!(!checkpoint.matches(&image[..4999], 2036));
+
+        let mut modified = image.clone();
+        modified[10] ^= 0xFF;
+        assert!(!checkpoint.matches(&modified, 2036));
+
+        // Offsets after shrunk chunks can be resumed; a finished transfer cannot
+        checkpoint.acked_offset = 2036 + 1018;
+        assert!(checkpoint.matches(&image, 2036));
+        checkpoint.acked_offset = 5000;
+        assert!(!checkpoint.matches(&image, 2036));
+    }
+
+    #[test]
+    fn test_checkpoint_round_trip() {
+        let dir = test_dir("checkpoint");
+
+        let mut checkpoint = FirmwareCheckpoint::new(4242, Some("glitchi_v0.1.5a.bin".to_string()), &[1, 2, 3, 4], 2036);
+        checkpoint.acked_offset = 0;
+        save_checkpoint(&dir, &checkpoint).unwrap();
+
+        let loaded = load_checkpoint(&dir, 4242).unwrap().unwrap();
+        assert_eq!(loaded.image_crc32, checkpoint.image_crc32);
+        assert_eq!(loaded.image_path, checkpoint.image_path);
+
+        clear_checkpoint(&dir, 4242).unwrap();
+        assert!(load_checkpoint(&dir, 4242).unwrap().is_none());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
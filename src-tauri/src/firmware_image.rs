@@ -0,0 +1,322 @@
+//! Firmware image loading
+//!
+//! The device is sent a flat binary: FW_UPDATE_START carries its size and CRC32
+//! and FW_UPDATE_DATA streams it in chunks. `.bin` files are used as-is, `.fw`
+//! containers are unwrapped to their signed payload, and Intel HEX files are
+//! parsed into one contiguous image starting at the lowest record address, with
+//! gaps between records filled with a pad byte.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::firmware_signing::{parse_firmware_container, FirmwareContainerHeader};
+use crate::gcp::GcpCommError;
+use crate::hex::decode_hex;
+
+// Erased flash reads as 0xFF, so gaps padded with it leave those bytes untouched
+pub const FIRMWARE_DEFAULT_PAD_BYTE: u8 = 0xFF;
+// Keeps a stray high-address record from padding the image out to gigabytes
+pub const FIRMWARE_MAX_IMAGE_SIZE: u32 = 16 * 1024 * 1024;
+
+const HEX_RECORD_DATA: u8 = 0x00;
+const HEX_RECORD_EOF: u8 = 0x01;
+const HEX_RECORD_EXTENDED_SEGMENT_ADDRESS: u8 = 0x02;
+const HEX_RECORD_START_SEGMENT_ADDRESS: u8 = 0x03;
+const HEX_RECORD_EXTENDED_LINEAR_ADDRESS: u8 = 0x04;
+const HEX_RECORD_START_LINEAR_ADDRESS: u8 = 0x05;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FirmwareImageFormat {
+    Binary,
+    IntelHex,
+    SignedContainer,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FirmwareImage {
+    pub format: FirmwareImageFormat,
+    pub base_address: u32,           // Address of data[0] (0 for raw binaries)
+    pub entry_point: Option<u32>,    // From a start address record, if present
+    pub data: Vec<u8>,
+    pub container: Option<FirmwareContainerHeader>,   // Present for .fw images only
+}
+
+impl FirmwareImage {
+    pub fn from_binary(data: Vec<u8>) -> Self {
+        Self {
+            format: FirmwareImageFormat::Binary,
+            base_address: 0,
+            entry_point: None,
+            data,
+            container: None,
+        }
+    }
+}
+
+// Load a firmware file as the flat binary that is sent to the device
+pub fn load_firmware_image(path: impl AsRef<Path>, pad_byte: u8) -> Result<FirmwareImage, GcpCommError> {
+    let path = path.as_ref();
+    let contents = fs::read(path)
+        .map_err(|e| GcpCommError::file(path, format!("Failed to read firmware file: {}", e)))?;
+
+    let extension = path.extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+
+    match extension.as_deref() {
+        Some("hex") => {
+            let text = std::str::from_utf8(&contents)
+                .map_err(|_| GcpCommError::file(path, "Intel HEX file is not valid text"))?;
+            parse_intel_hex(text, pad_byte)
+                .map_err(|e| GcpCommError::file(path, e.to_string()))
+        }
+        Some("fw") => {
+            let (header, payload) = parse_firmware_container(&contents).map_err(|e| match e {
+                GcpCommError::UntrustedFirmware { .. } => e,
+                other => GcpCommError::file(path, other.to_string()),
+            })?;
+            Ok(FirmwareImage {
+                format: FirmwareImageFormat::SignedContainer,
+                base_address: 0,
+                entry_point: None,
+                data: payload.to_vec(),
+                container: Some(header),
+            })
+        }
+        _ => Ok(FirmwareImage::from_binary(contents)),
+    }
+}
+
+pub fn parse_intel_hex(text: &str, pad_byte: u8) -> Result<FirmwareImage, GcpCommError> {
+    let mut segments: Vec<(u64, Vec<u8>, usize)> = Vec::new();   // (address, data, line)
+    let mut upper_address: u64 = 0;
+    let mut entry_point = None;
+    let mut seen_eof = false;
+
+    for (index, raw_line) in text.lines().enumerate() {
+        let line_no = index + 1;
+        let line = raw_line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if seen_eof {
+            return Err(hex_error(line_no, "record after end-of-file record"));
+        }
+
+        let record = parse_hex_record(line).map_err(|reason| hex_error(line_no, reason))?;
+        let data = record.data;
+
+        match record.record_type {
+            HEX_RECORD_DATA => {
+                if data.is_empty() {
+                    continue;
+                }
+                let address = upper_address + record.offset as u64;
+                if address + data.len() as u64 > u32::MAX as u64 + 1 {
+                    return Err(hex_error(line_no, format!("data at 0x{:X} runs past the 32-bit address space", address)));
+                }
+                segments.push((address, data, line_no));
+            }
+            HEX_RECORD_EOF => seen_eof = true,
+            HEX_RECORD_EXTENDED_SEGMENT_ADDRESS => {
+                require_hex_len(&data, 2, line_no)?;
+                upper_address = (u16::from_be_bytes([data[0], data[1]]) as u64) << 4;
+            }
+            HEX_RECORD_EXTENDED_LINEAR_ADDRESS => {
+                require_hex_len(&data, 2, line_no)?;
+                upper_address = (u16::from_be_bytes([data[0], data[1]]) as u64) << 16;
+            }
+            HEX_RECORD_START_SEGMENT_ADDRESS => {
+                require_hex_len(&data, 4, line_no)?;
+                let segment = u16::from_be_bytes([data[0], data[1]]) as u32;
+                let offset = u16::from_be_bytes([data[2], data[3]]) as u32;
+                entry_point = Some((segment << 4) + offset);
+            }
+            HEX_RECORD_START_LINEAR_ADDRESS => {
+                require_hex_len(&data, 4, line_no)?;
+                entry_point = Some(u32::from_be_bytes([data[0], data[1], data[2], data[3]]));
+            }
+            other => return Err(hex_error(line_no, format!("unsupported record type 0x{:02X}", other))),
+        }
+    }
+
+    if !seen_eof {
+        return Err(GcpCommError::invalid_parameter("Intel HEX file has no end-of-file record"));
+    }
+    if segments.is_empty() {
+        return Err(GcpCommError::invalid_parameter("Intel HEX file contains no data records"));
+    }
+
+    segments.sort_by_key(|(address, _, _)| *address);
+    for pair in segments.windows(2) {
+        let (prev_address, prev_data, prev_line) = &pair[0];
+        let (address, _, line_no) = &pair[1];
+        if prev_address + prev_data.len() as u64 > *address {
+            return Err(hex_error(*line_no, format!("data at 0x{:X} overlaps the record on line {}", address, prev_line)));
+        }
+    }
+
+    let base_address = segments[0].0;
+    let end_address = segments.iter()
+        .map(|(address, data, _)| address + data.len() as u64)
+        .max()
+        .unwrap_or(base_address);
+    let image_size = end_address - base_address;
+    if image_size > FIRMWARE_MAX_IMAGE_SIZE as u64 {
+        return Err(GcpCommError::invalid_parameter(format!(
+            "Intel HEX data spans 0x{:X}..0x{:X} ({} bytes), more than the {} byte limit",
+            base_address, end_address, image_size, FIRMWARE_MAX_IMAGE_SIZE
+        )));
+    }
+
+    let mut image = vec![pad_byte; image_size as usize];
+    for (address, data, _) in &segments {
+        let start = (address - base_address) as usize;
+        image[start..start + data.len()].copy_from_slice(data);
+    }
+
+    Ok(FirmwareImage {
+        format: FirmwareImageFormat::IntelHex,
+        base_address: base_address as u32,
+        entry_point,
+        data: image,
+        container: None,
+    })
+}
+
+struct HexRecord {
+    record_type: u8,
+    offset: u16,
+    data: Vec<u8>,
+}
+
+// ":LLAAAATT<data>CC" where the checksum makes all bytes sum to zero
+fn parse_hex_record(line: &str) -> Result<HexRecord, String> {
+    let hex = line.strip_prefix(':').ok_or("record does not start with ':'")?;
+    let bytes = decode_hex(hex).map_err(|reason| format!("record has an {}", reason))?;
+
+    if bytes.len() < 5 {
+        return Err("record is too short".to_string());
+    }
+    let data_len = bytes[0] as usize;
+    if bytes.len() != data_len + 5 {
+        return Err(format!("byte count {} does not match record length", data_len));
+    }
+
+    let checksum = bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte));
+    if checksum != 0 {
+        let expected = bytes[..bytes.len() - 1].iter()
+            .fold(0u8, |sum, byte| sum.wrapping_add(*byte))
+            .wrapping_neg();
+        return Err(format!("checksum mismatch (expected {:02X}, found {:02X})", expected, bytes[bytes.len() - 1]));
+    }
+
+    Ok(HexRecord {
+        record_type: bytes[3],
+        offset: u16::from_be_bytes([bytes[1], bytes[2]]),
+        data: bytes[4..4 + data_len].to_vec(),
+    })
+}
+
+fn require_hex_len(data: &[u8], len: usize, line_no: usize) -> Result<(), GcpCommError> {
+    if data.len() != len {
+        return Err(hex_error(line_no, format!("expected {} data bytes, found {}", len, data.len())));
+    }
+    Ok(())
+}
+
+fn hex_error(line_no: usize, reason: impl std::fmt::Display) -> GcpCommError {
+    GcpCommError::invalid_parameter(format!("Intel HEX line {}: {}", line_no, reason))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Build a record with a valid checksum
+    fn record(record_type: u8, offset: u16, data: &[u8]) -> String {
+        let mut bytes = vec![data.len() as u8, (offset >> 8) as u8, offset as u8, record_type];
+        bytes.extend_from_slice(data);
+        let checksum = bytes.iter().fold(0u8, |sum, byte| sum.wrapping_add(*byte)).wrapping_neg();
+        bytes.push(checksum);
+        format!(":{}", bytes.iter().map(|b| format!("{:02X}", b)).collect::<String>())
+    }
+
+    #[test]
+    fn test_intel_hex_parsing() {
+        // Known-good record from the format description
+        let text = ":10010000214601360121470136007EFE09D2190140\n:00000001FF\n";
+        let image = parse_intel_hex(text, FIRMWARE_DEFAULT_PAD_BYTE).unwrap();
+        assert_eq!(image.format, FirmwareImageFormat::IntelHex);
+        assert_eq!(image.base_address, 0x0100);
+        assert_eq!(image.data.len(), 16);
+        assert_eq!(&image.data[..4], &[0x21, 0x46, 0x01, 0x36]);
+
+        // Extended linear address, a gap and a start address
+        let text = [
+            record(HEX_RECORD_EXTENDED_LINEAR_ADDRESS, 0, &[0x08, 0x00]),
+            record(HEX_RECORD_DATA, 0x0000, &[1, 2, 3, 4]),
+            record(HEX_RECORD_DATA, 0x0008, &[5, 6]),
+            record(HEX_RECORD_START_LINEAR_ADDRESS, 0, &[0x08, 0x00, 0x01, 0x01]),
+            record(HEX_RECORD_EOF, 0, &[]),
+        ].join("\r\n");
+        let image = parse_intel_hex(&text, 0xAA).unwrap();
+        assert_eq!(image.base_address, 0x0800_0000);
+        assert_eq!(image.entry_point, Some(0x0800_0101));
+        assert_eq!(image.data, vec![1, 2, 3, 4, 0xAA, 0xAA, 0xAA, 0xAA, 5, 6]);
+
+        // Extended segment address shifts by 4 bits
+        let text = [
+            record(HEX_RECORD_EXTENDED_SEGMENT_ADDRESS, 0, &[0x10, 0x00]),
+            record(HEX_RECORD_DATA, 0x0010, &[7]),
+            record(HEX_RECORD_EOF, 0, &[]),
+        ].join("\n");
+        assert_eq!(parse_intel_hex(&text, 0xFF).unwrap().base_address, 0x10010);
+    }
+
+    #[test]
+    fn test_intel_hex_rejects_invalid_files() {
+        let eof = record(HEX_RECORD_EOF, 0, &[]);
+
+        // Bad checksum
+        assert!(parse_intel_hex(":0100000001FF\n:00000001FF", 0xFF).is_err());
+        // Missing EOF record
+        assert!(parse_intel_hex(&record(HEX_RECORD_DATA, 0, &[1]), 0xFF).is_err());
+        // Data after EOF
+        let text = [eof.clone(), record(HEX_RECORD_DATA, 0, &[1])].join("\n");
+        assert!(parse_intel_hex(&text, 0xFF).is_err());
+        // Byte count does not match the record
+        assert!(parse_intel_hex(":0200000001FD\n:00000001FF", 0xFF).is_err());
+        // Non-ASCII characters where hex digits belong
+        assert!(parse_intel_hex(":aé0\n:00000001FF", 0xFF).is_err());
+        assert!(parse_intel_hex(":0é00000001FF\n:00000001FF", 0xFF).is_err());
+
+        // Overlapping records
+        let text = [
+            record(HEX_RECORD_DATA, 0x0000, &[1, 2, 3, 4]),
+            record(HEX_RECORD_DATA, 0x0002, &[5]),
+            eof.clone(),
+        ].join("\n");
+        let err = parse_intel_hex(&text, 0xFF).unwrap_err();
+        assert!(err.to_string().contains("overlaps"));
+
+        // Span larger than the image limit
+        let text = [
+            record(HEX_RECORD_DATA, 0x0000, &[1]),
+            record(HEX_RECORD_EXTENDED_LINEAR_ADDRESS, 0, &[0x10, 0x00]),
+            record(HEX_RECORD_DATA, 0x0000, &[2]),
+            eof.clone(),
+        ].join("\n");
+        assert!(parse_intel_hex(&text, 0xFF).is_err());
+
+        // Past the end of the 32-bit address space
+        let text = [
+            record(HEX_RECORD_EXTENDED_LINEAR_ADDRESS, 0, &[0xFF, 0xFF]),
+            record(HEX_RECORD_DATA, 0xFFFF, &[1, 2]),
+            eof,
+        ].join("\n");
+        assert!(parse_intel_hex(&text, 0xFF).is_err());
+    }
+}
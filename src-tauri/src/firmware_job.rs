@@ -0,0 +1,728 @@
+//! Background firmware update jobs
+//!
+//! A job runs FW_UPDATE_START / DATA / END on its own thread and locks the port
+//! handler for one request at a time. Abort, pause and status commands are
+//! therefore served between chunks instead of waiting for the whole transfer.
+//! Pause and resume continue from the last ACK'd offset; cancel sends
+//! FW_UPDATE_ABORT before the next chunk. Progress is checkpointed to disk per
+//! device serial so an interrupted transfer can be offered again later; it then
+//! restarts at offset 0, since the device cannot report what it kept. Images are
+//! verified against the trusted signing keys before FW_UPDATE_START.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::cell::Cell;
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+use tauri::{Emitter, Runtime};
+
+use crate::firmware_checkpoint::{clear_checkpoint, load_checkpoint, save_checkpoint, FirmwareCheckpoint};
+use crate::firmware_image::FirmwareImage;
+use crate::firmware_signing::{verify_firmware_image, FirmwareSignatureInfo};
+use crate::firmware_transfer::{record_transfer, Recovery, TransferPacer};
+use crate::gcp::{gcp_crc32, get_connection_handle, GcpCommError, GcpHelloResponse, GcpUartHandler};
+
+// How often a running transfer rewrites its checkpoint
+const CHECKPOINT_SAVE_INTERVAL: Duration = Duration::from_secs(1);
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FirmwareUpdateProgress {
+    pub job_id: String,
+    pub port_name: String,          // Several jobs can run at once, one per port
+    pub stage: String,
+    pub current_chunk: u32,
+    pub total_chunks: u32,
+    pub bytes_sent: u32,
+    pub total_bytes: u32,
+    pub percentage: f64,
+    pub chunk_size: u32,            // Shrinks when the device NACKs with GCP_ERROR_SIZE
+    pub bytes_per_second: f64,      // Measured since FW_UPDATE_START was ACK'd
+    pub status: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FirmwareUpdateResult {
+    pub success: bool,
+    pub message: String,
+    pub crc32_match: bool,
+    pub total_chunks: u32,
+    pub total_bytes: u32,
+    pub bytes_per_second: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FirmwareJobState {
+    Running,
+    Paused,
+    Completed,
+    Failed,
+    Cancelled,
+}
+
+impl FirmwareJobState {
+    pub fn is_finished(self) -> bool {
+        matches!(self, FirmwareJobState::Completed | FirmwareJobState::Failed | FirmwareJobState::Cancelled)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FirmwareJobStatus {
+    pub job_id: String,
+    pub port_name: String,
+    pub state: FirmwareJobState,
+    pub bytes_acked: u32,        // Everything before this offset was ACK'd by the device
+    pub device_serial: Option<u16>,
+    pub signature: Option<FirmwareSignatureInfo>,
+    pub total_bytes: u32,
+    pub total_chunks: u32,       // 0 until the job thread has read the link config
+    pub chunk_size: u32,
+    pub bytes_per_second: f64,
+    pub message: String,
+    pub result: Option<FirmwareUpdateResult>,
+    pub error: Option<GcpCommError>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FirmwareJobOptions {
+    pub image_path: Option<String>,
+    // Continue from the device's checkpoint if it matches this image
+    pub resume: bool,
+    // Where progress is checkpointed per device; None keeps no checkpoints
+    pub checkpoint_dir: Option<PathBuf>,
+}
+
+#[derive(Default)]
+struct JobControl {
+    pause: bool,
+    cancel: bool,
+}
+
+struct FirmwareJob {
+    status: Mutex<FirmwareJobStatus>,
+    control: Mutex<JobControl>,
+    wake: Condvar,
+}
+
+enum Checkpoint {
+    Continue,
+    Cancel,
+}
+
+lazy_static::lazy_static! {
+    static ref JOBS: Mutex<HashMap<String, Arc<FirmwareJob>>> = Mutex::new(HashMap::new());
+}
+
+static NEXT_JOB_ID: AtomicU64 = AtomicU64::new(1);
+
+// Start updating the device on `port_name` in the background. Progress is reported
+// as "firmware-progress" events, state changes as "firmware-job-updated".
+pub fn start_job<R, E>(
+    emitter: E,
+    port_name: &str,
+    image: FirmwareImage,
+    options: FirmwareJobOptions,
+) -> Result<FirmwareJobStatus, GcpCommError>
+where
+    R: Runtime,
+    E: Emitter<R> + Send + 'static,
+{
+    if image.data.is_empty() {
+        return Err(GcpCommError::invalid_parameter("Firmware image is empty"));
+    }
+
+    let handler = get_connection_handle(port_name)
+        .ok_or_else(|| GcpCommError::NotConnected { port_name: port_name.to_string() })?;
+    // Never lock the handler here: the firmware update listener calls this while it
+    // holds it. The job thread fills in the chunking from the connection's link config.
+    let mut jobs = JOBS.lock()
+        .map_err(|_| GcpCommError::internal("Failed to lock firmware job registry"))?;
+
+    if jobs.values().any(|job| job.is_active_on(port_name)) {
+        return Err(GcpCommError::invalid_parameter(format!("A firmware update is already running on {}", port_name)));
+    }
+    // Only the latest job per port is kept for status queries
+    jobs.retain(|_, job| job.snapshot().port_name != port_name);
+
+    let total_bytes = image.data.len() as u32;
+    let job_id = format!("fw-{}", NEXT_JOB_ID.fetch_add(1, Ordering::Relaxed));
+    let job = Arc::new(FirmwareJob {
+        status: Mutex::new(FirmwareJobStatus {
+            job_id: job_id.clone(),
+            port_name: port_name.to_string(),
+            state: FirmwareJobState::Running,
+            bytes_acked: 0,
+            device_serial: None,
+            signature: None,
+            total_bytes,
+            total_chunks: 0,
+            chunk_size: 0,
+            bytes_per_second: 0.0,
+            message: "Firmware update queued".to_string(),
+            result: None,
+            error: None,
+        }),
+        control: Mutex::new(JobControl::default()),
+        wake: Condvar::new(),
+    });
+
+    let thread_job = job.clone();
+    std::thread::Builder::new()
+        .name(format!("gcp-fw-job-{}", job_id))
+        .spawn(move || run_job(thread_job, handler, image, options, emitter))
+        .map_err(|e| GcpCommError::internal(format!("Failed to start firmware update job: {}", e)))?;
+
+    jobs.insert(job_id, job.clone());
+    Ok(job.snapshot())
+}
+
+pub fn job_status(job_id: &str) -> Result<FirmwareJobStatus, GcpCommError> {
+    Ok(find_job(job_id)?.snapshot())
+}
+
+// Job currently transferring (or paused) on a port, if any
+pub fn active_job_for_port(port_name: &str) -> Option<FirmwareJobStatus> {
+    let jobs = JOBS.lock().ok()?;
+    jobs.values()
+        .find(|job| job.is_active_on(port_name))
+        .map(|job| job.snapshot())
+}
+
+pub fn pause_job(job_id: &str) -> Result<FirmwareJobStatus, GcpCommError> {
+    find_job(job_id)?.update_control(|control| control.pause = true)
+}
+
+pub fn resume_job(job_id: &str) -> Result<FirmwareJobStatus, GcpCommError> {
+    find_job(job_id)?.update_control(|control| control.pause = false)
+}
+
+pub fn cancel_job(job_id: &str) -> Result<FirmwareJobStatus, GcpCommError> {
+    find_job(job_id)?.update_control(|control| control.cancel = true)
+}
+
+fn find_job(job_id: &str) -> Result<Arc<FirmwareJob>, GcpCommError> {
+    let jobs = JOBS.lock()
+        .map_err(|_| GcpCommError::internal("Failed to lock firmware job registry"))?;
+    jobs.get(job_id)
+        .cloned()
+        .ok_or_else(|| GcpCommError::invalid_parameter(format!("Unknown firmware update job {}", job_id)))
+}
+
+impl FirmwareJob {
+    fn snapshot(&self) -> FirmwareJobStatus {
+        match self.status.lock() {
+            Ok(status) => status.clone(),
+            Err(poisoned) => poisoned.into_inner().clone(),
+        }
+    }
+
+    fn is_active_on(&self, port_name: &str) -> bool {
+        let status = self.snapshot();
+        status.port_name == port_name && !status.state.is_finished()
+    }
+
+    fn update_control(&self, update: impl FnOnce(&mut JobControl)) -> Result<FirmwareJobStatus, GcpCommError> {
+        if self.snapshot().state.is_finished() {
+            return Ok(self.snapshot());
+        }
+        let mut control = self.control.lock()
+            .map_err(|_| GcpCommError::internal("Failed to lock firmware job control"))?;
+        update(&mut control);
+        self.wake.notify_all();
+        Ok(self.snapshot())
+    }
+
+    fn set_state<R: Runtime, E: Emitter<R>>(&self, emitter: &E, state: FirmwareJobState, message: &str) {
+        let snapshot = {
+            let mut status = match self.status.lock() {
+                Ok(status) => status,
+                Err(poisoned) => poisoned.into_inner(),
+            };
+            status.state = state;
+            status.message = message.to_string();
+            status.clone()
+        };
+        let _ = emitter.emit("firmware-job-updated", &snapshot);
+    }
+
+    fn update_status(&self, update: impl FnOnce(&mut FirmwareJobStatus)) {
+        if let Ok(mut status) = self.status.lock() {
+            update(&mut status);
+        }
+    }
+
+    // Called between requests: blocks while paused, reports a pending cancel
+    fn checkpoint<R: Runtime, E: Emitter<R>>(&self, emitter: &E) -> Checkpoint {
+        let Ok(mut control) = self.control.lock() else {
+            return Checkpoint::Cancel;
+        };
+
+        if control.pause && !control.cancel {
+            let offset = self.snapshot().bytes_acked;
+            self.set_state(emitter, FirmwareJobState::Paused, &format!("Paused at offset {}", offset));
+            while control.pause && !control.cancel {
+                control = match self.wake.wait(control) {
+                    Ok(control) => control,
+                    Err(_) => return Checkpoint::Cancel,
+                };
+            }
+            if !control.cancel {
+                self.set_state(emitter, FirmwareJobState::Running, &format!("Resuming from offset {}", offset));
+            }
+        }
+
+        if control.cancel {
+            Checkpoint::Cancel
+        } else {
+            Checkpoint::Continue
+        }
+    }
+}
+
+fn run_job<R: Runtime, E: Emitter<R>>(
+    job: Arc<FirmwareJob>,
+    handler: Arc<Mutex<GcpUartHandler>>,
+    image: FirmwareImage,
+    options: FirmwareJobOptions,
+    emitter: E,
+) {
+    job.set_state(&emitter, FirmwareJobState::Running, "Firmware update started");
+
+    let outcome = transfer(&job, &handler, &image, &options, &emitter);
+
+    let snapshot = {
+        let mut status = match job.status.lock() {
+            Ok(status) => status,
+            Err(poisoned) => poisoned.into_inner(),
+        };
+        match outcome {
+            Ok(Some(result)) => {
+                status.state = if result.success { FirmwareJobState::Completed } else { FirmwareJobState::Failed };
+                status.message = result.message.clone();
+                status.result = Some(result);
+            }
+            Ok(None) => {
+                status.state = FirmwareJobState::Cancelled;
+                status.message = format!("Firmware update cancelled at offset {}", status.bytes_acked);
+            }
+            Err(e) => {
+                status.state = FirmwareJobState::Failed;
+                status.message = e.to_string();
+                status.error = Some(e);
+            }
+        }
+        status.clone()
+    };
+
+    log::info!(target: "gcp::fw", "Firmware job {} finished: {:?} - {}", snapshot.job_id, snapshot.state, snapshot.message);
+    let _ = emitter.emit("firmware-job-updated", &snapshot);
+}
+
+// Returns Ok(None) when the job was cancelled
+fn transfer<R: Runtime, E: Emitter<R>>(
+    job: &FirmwareJob,
+    handler: &Mutex<GcpUartHandler>,
+    image: &FirmwareImage,
+    options: &FirmwareJobOptions,
+    emitter: &E,
+) -> Result<Option<FirmwareUpdateResult>, GcpCommError> {
+    let lock_handler = || handler.lock().map_err(|_| GcpCommError::internal("Failed to lock handler"));
+    let firmware_data = &image.data[..];
+
+    let total_bytes = firmware_data.len() as u32;
+    // Announced in FW_UPDATE_START; the chunks actually sent may shrink below it
+    let link = lock_handler()?.link_config().clone();
+    let chunk_size = link.chunk_size as usize;
+    let total_chunks = firmware_data.len().div_ceil(chunk_size) as u32;
+    job.update_status(|status| {
+        status.chunk_size = chunk_size as u32;
+        status.total_chunks = total_chunks;
+    });
+    let firmware_crc32 = gcp_crc32(firmware_data);
+
+    log::info!(target: "gcp::fw", "Starting firmware update: {} bytes, {} chunks, CRC32: {:08X}",
+                                total_bytes, total_chunks, firmware_crc32);
+
+    // Helper function to emit progress
+    let FirmwareJobStatus { job_id, port_name, .. } = job.snapshot();
+    let pacing = Cell::new((total_chunks, chunk_size, 0.0));   // Total chunks, chunk size, bytes/s
+    let emit_progress = |stage: &str, current: u32, status: &str, bytes_sent: u32| {
+        let (total_chunks, chunk_size, bytes_per_second) = pacing.get();
+        let progress = FirmwareUpdateProgress {
+            job_id: job_id.clone(),
+            port_name: port_name.clone(),
+            stage: stage.to_string(),
+            current_chunk: current,
+            total_chunks,
+            bytes_sent,
+            total_bytes,
+            percentage: (bytes_sent as f64 / total_bytes as f64) * 100.0,
+            chunk_size: chunk_size as u32,
+            bytes_per_second,
+            status: status.to_string(),
+        };
+        let _ = emitter.emit("firmware-progress", &progress);
+    };
+
+    // Signature targets and checkpoints need HWVersion_t; pre-v2.2 devices cannot be identified
+    let hardware = match lock_handler()?.send_hello() {
+        Ok(GcpHelloResponse::Hardware(hardware)) => Some(hardware),
+        Ok(GcpHelloResponse::Status(_)) => None,
+        Err(e) => {
+            log::warn!(target: "gcp::fw", "Could not identify device: {}", e);
+            None
+        }
+    };
+    let device_serial = hardware.as_ref().map(|hw| hw.serial_number);
+
+    match verify_firmware_image(image, hardware.as_ref()) {
+        Ok(signature) => {
+            let status_msg = match &signature.trusted_key {
+                Some(key) => format!("Firmware signature verified (key '{}')", key),
+                None => "Flashing untrusted firmware (developer override)".to_string(),
+            };
+            emit_progress("Verified", 0, &status_msg, 0);
+            job.update_status(|status| status.signature = Some(signature));
+        }
+        Err(e) => {
+            emit_progress("Error", 0, &e.to_string(), 0);
+            return Err(e);
+        }
+    }
+    let checkpoint_dir = options.checkpoint_dir.as_deref();
+    let mut checkpoint = match (device_serial, checkpoint_dir) {
+        (Some(serial), Some(_)) => Some(FirmwareCheckpoint::new(serial, options.image_path.clone(), firmware_data, chunk_size as u16)),
+        _ => None,
+    };
+    let discard_checkpoint = || {
+        if let (Some(serial), Some(dir)) = (device_serial, checkpoint_dir) {
+            let _ = clear_checkpoint(dir, serial);
+        }
+    };
+
+    // GCP v2.2 has no command that reports how much of an earlier transfer the device
+    // kept, and every FW_UPDATE_START begins a new one (§4.3). Resuming therefore
+    // restarts at offset 0; the checkpoint only tells which image was interrupted.
+    if let (Some(serial), Some(dir)) = (device_serial, checkpoint_dir) {
+        if options.resume {
+            match load_checkpoint(dir, serial) {
+                Ok(Some(saved)) if saved.matches(firmware_data, chunk_size as u16) => {
+                    log::info!(target: "gcp::fw", "Restarting interrupted transfer for device {} at offset 0 (stopped at {})",
+                                                  serial, saved.acked_offset);
+                }
+                Ok(_) => log::info!(target: "gcp::fw", "No matching checkpoint for device {}, starting at offset 0", serial),
+                Err(e) => log::warn!(target: "gcp::fw", "Failed to load checkpoint for device {}: {}", serial, e),
+            }
+        }
+    }
+    job.update_status(|status| status.device_serial = device_serial);
+
+    let start_time = Instant::now();
+
+    // Stage 1: Start firmware update
+    if let Checkpoint::Cancel = job.checkpoint(emitter) {
+        emit_progress("Cancelled", 0, "Firmware update cancelled before start", 0);
+        return Ok(None);
+    }
+    emit_progress("Initiating", 0, "Sending firmware update start command...", 0);
+
+    if let Err(e) = lock_handler()?.start_firmware_update(firmware_data, chunk_size as u16) {
+        emit_progress("Error", 0, &format!("Failed to start firmware update: {}", e), 0);
+        return Err(e);
+    }
+    emit_progress("Initiated", 0, "Device acknowledged firmware update start", 0);
+
+    // Stage 2: Send firmware chunks, one handler lock per chunk. The next chunk goes
+    // out as soon as the previous one is ACK'd; the pacer only steps in on errors.
+    let mut bytes_sent = 0u32;
+    emit_progress("Transferring", 0, "Starting firmware data transfer...", 0);
+
+    let mut pacer = TransferPacer::new(chunk_size, link.max_retries);
+    let mut chunk_index = 0u32;
+    let mut last_checkpoint_save: Option<Instant> = None;
+    let transfer_start = Instant::now();
+
+    while (bytes_sent as usize) < firmware_data.len() {
+        if let Checkpoint::Cancel = job.checkpoint(emitter) {
+            let abort = lock_handler()?.abort_firmware_update();
+            let status_msg = match abort {
+                Ok(()) => format!("Firmware update aborted at offset {}", bytes_sent),
+                Err(e) => format!("Firmware update cancelled at offset {}, abort failed: {}", bytes_sent, e),
+            };
+            emit_progress("Cancelled", chunk_index, &status_msg, bytes_sent);
+            discard_checkpoint();
+            return Ok(None);
+        }
+
+        let chunk_start = bytes_sent as usize;
+        let chunk_end = std::cmp::min(chunk_start + pacer.chunk_size(), firmware_data.len());
+        let chunk_data = &firmware_data[chunk_start..chunk_end];
+        let (total_chunks, _, _) = pacing.get();
+
+        // Released before any backoff, so status and abort commands get through
+        let sent = lock_handler()?.send_firmware_chunk_once(chunk_data, chunk_start as u32);
+        match sent {
+            Ok(()) => {
+                pacer.on_ack();
+                bytes_sent += chunk_data.len() as u32;
+                chunk_index += 1;
+
+                let bytes_per_second = bytes_sent as f64 / transfer_start.elapsed().as_secs_f64();
+                pacing.set((total_chunks, pacer.chunk_size(), bytes_per_second));
+                job.update_status(|status| {
+                    status.bytes_acked = bytes_sent;
+                    status.bytes_per_second = bytes_per_second;
+                });
+
+                // Saved at most once per interval rather than after every ACK
+                let save_due = last_checkpoint_save.map_or(true, |saved| saved.elapsed() >= CHECKPOINT_SAVE_INTERVAL);
+                if let (Some(checkpoint), Some(dir), true) = (checkpoint.as_mut(), checkpoint_dir, save_due) {
+                    checkpoint.acked_offset = bytes_sent;
+                    if let Err(e) = save_checkpoint(dir, checkpoint) {
+                        log::warn!(target: "gcp::fw", "Failed to save firmware checkpoint: {}", e);
+                    }
+                    last_checkpoint_save = Some(Instant::now());
+                }
+
+                // Emit progress every few chunks or at the end
+                if chunk_index % 5 == 1 || bytes_sent == total_bytes {
+                    let progress_msg = format!("Sent chunk {} of {} ({:.1}%, {:.1} KB/s)",
+                                             chunk_index, total_chunks,
+                                             (bytes_sent as f64 / total_bytes as f64) * 100.0,
+                                             bytes_per_second / 1024.0);
+                    emit_progress("Transferring", chunk_index, &progress_msg, bytes_sent);
+                }
+            }
+            Err(e) => match pacer.on_error(&e) {
+                // GCP v2.2 §9: on a CRC or sequence error, resend from the last ACK'd offset
+                Recovery::Resend => {
+                    let status_msg = format!("{} at offset {}, resending from last ACK'd offset {}",
+                                             e, chunk_start, bytes_sent);
+                    emit_progress("Transferring", chunk_index, &status_msg, bytes_sent);
+                }
+                Recovery::ResendAfter(backoff) => {
+                    let status_msg = format!("{} at offset {}, retrying in {} ms",
+                                             e, chunk_start, backoff.as_millis());
+                    emit_progress("Transferring", chunk_index, &status_msg, bytes_sent);
+                    std::thread::sleep(backoff);
+                }
+                Recovery::ShrinkTo(new_chunk_size) => {
+                    let remaining = firmware_data.len() - bytes_sent as usize;
+                    let total_chunks = chunk_index + remaining.div_ceil(new_chunk_size) as u32;
+                    pacing.set((total_chunks, new_chunk_size, pacing.get().2));
+                    job.update_status(|status| {
+                        status.chunk_size = new_chunk_size as u32;
+                        status.total_chunks = total_chunks;
+                    });
+                    let status_msg = format!("Device rejected {}-byte chunk, continuing with {}-byte chunks",
+                                             chunk_data.len(), new_chunk_size);
+                    emit_progress("Transferring", chunk_index, &status_msg, bytes_sent);
+                }
+                Recovery::Abort => {
+                    // Only a transfer cut off by the link is worth resuming; one the device
+                    // refused would be refused again
+                    if !e.is_retryable() {
+                        discard_checkpoint();
+                    }
+                    let error_msg = format!("Failed to send chunk {}: {}", chunk_index, e);
+                    emit_progress("Error", chunk_index, &error_msg, bytes_sent);
+                    return Err(e);
+                }
+            },
+        }
+    }
+    let (total_chunks, _, _) = pacing.get();
+
+    // Stage 3: End firmware update and verify
+    emit_progress("Verifying", total_chunks, "Requesting firmware verification...", bytes_sent);
+
+    let verified = lock_handler()?.end_firmware_update();
+    match verified {
+        Ok(crc_match) => {
+            // Either way there is nothing left to resume
+            discard_checkpoint();
+
+            let elapsed = start_time.elapsed();
+            let transfer_rate = bytes_sent as f64 / elapsed.as_secs_f64();
+            if crc_match {
+                record_transfer(&port_name, &link, bytes_sent, elapsed, pacer.chunk_size());
+            }
+
+            let message = if crc_match {
+                let success_msg = format!("Firmware update completed successfully in {:.1}s ({:.1} KB/s)",
+                                         elapsed.as_secs_f64(), transfer_rate / 1024.0);
+                emit_progress("Completed", total_chunks, &success_msg, bytes_sent);
+                success_msg
+            } else {
+                let error_msg = "Firmware verification failed - CRC32 mismatch".to_string();
+                emit_progress("Failed", total_chunks, &error_msg, bytes_sent);
+                error_msg
+            };
+
+            Ok(Some(FirmwareUpdateResult {
+                success: crc_match,
+                message,
+                crc32_match: crc_match,
+                total_chunks,
+                total_bytes: bytes_sent,
+                bytes_per_second: transfer_rate,
+            }))
+        }
+        Err(e) => {
+            let error_msg = format!("Firmware verification failed: {}", e);
+            emit_progress("Failed", total_chunks, &error_msg, bytes_sent);
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app_store::tests::test_dir;
+    use crate::fault_injection::{Fault, FaultRule, FaultScript, FaultTrigger};
+    use crate::firmware_signing::tests::{image_from, trusted_test_container};
+    use crate::gcp::{connect_with_transport, disconnect_from_port, execute_with_connection, GcpCommand, GcpError};
+    use crate::link_config::profile_for;
+    use crate::simulator::{spawn_memory_device, SimulatorConfig};
+    use std::sync::atomic::AtomicBool;
+    use std::sync::mpsc;
+    use tauri::test::MockRuntime;
+    use tauri::{App, Listener};
+
+    fn test_image(len: u32) -> FirmwareImage {
+        let payload: Vec<u8> = (0..len).map(|i| (i * 7) as u8).collect();
+        image_from(&trusted_test_container(&payload))
+    }
+
+    fn next_state(jobs: &mpsc::Receiver<FirmwareJobStatus>, state: FirmwareJobState) -> FirmwareJobStatus {
+        loop {
+            let status = jobs.recv_timeout(Duration::from_secs(10)).unwrap();
+            if status.state == state || status.state.is_finished() {
+                assert_eq!(status.state, state, "{}", status.message);
+                return status;
+            }
+        }
+    }
+
+    // Start a job on a simulated device that pauses itself once the first chunk is ACK'd
+    fn start_paused_job(port_name: &str, image: &FirmwareImage) -> (App<MockRuntime>, mpsc::Receiver<FirmwareJobStatus>, FirmwareJobStatus) {
+        connect_with_transport(port_name.to_string(), Box::new(spawn_memory_device(SimulatorConfig::default()))).unwrap();
+
+        let app = tauri::test::mock_app();
+        let paused = AtomicBool::new(false);
+        app.handle().listen_any("firmware-progress", move |event| {
+            let progress: FirmwareUpdateProgress = serde_json::from_str(event.payload()).unwrap();
+            if progress.bytes_sent > 0 && !paused.swap(true, Ordering::Relaxed) {
+                pause_job(&progress.job_id).unwrap();
+            }
+        });
+        let (job_tx, jobs) = mpsc::channel();
+        app.handle().listen_any("firmware-job-updated", move |event| {
+            let _ = job_tx.send(serde_json::from_str::<FirmwareJobStatus>(event.payload()).unwrap());
+        });
+
+        start_job(app.handle().clone(), port_name, image.clone(), FirmwareJobOptions::default()).unwrap();
+        let status = next_state(&jobs, FirmwareJobState::Paused);
+        (app, jobs, status)
+    }
+
+    // The device cannot report what it kept of the interrupted transfer, so resuming
+    // from a checkpoint sends the whole image again
+    #[test]
+    fn test_resume_restarts_at_offset_zero() {
+        let port_name = "memory://test_firmware_job_resume";
+        let image = test_image(5000);
+        let chunk_size = profile_for(port_name).chunk_size as usize;
+        let config = SimulatorConfig {
+            faults: Some(FaultScript {
+                seed: None,
+                rules: vec![FaultRule::new(GcpCommand::Ping, FaultTrigger::Nth(1), Fault::Reboot)],
+            }),
+            ..SimulatorConfig::default()
+        };
+        let serial = config.hardware.serial_number;
+        connect_with_transport(port_name.to_string(), Box::new(spawn_memory_device(config))).unwrap();
+
+        execute_with_connection(port_name, |handler| {
+            handler.start_firmware_update(&image.data, chunk_size as u16)?;
+            handler.send_firmware_chunk_once(&image.data[..chunk_size], 0)?;
+            // Rebooting, the device does not answer
+            let _ = handler.ping();
+            Ok(())
+        }).unwrap();
+        let dir = test_dir("firmware_job_resume");
+        let interrupted = FirmwareCheckpoint {
+            acked_offset: chunk_size as u32,
+            ..FirmwareCheckpoint::new(serial, None, &image.data, chunk_size as u16)
+        };
+        save_checkpoint(&dir, &interrupted).unwrap();
+
+        let app = tauri::test::mock_app();
+        let (progress_tx, progress) = mpsc::channel();
+        app.handle().listen_any("firmware-progress", move |event| {
+            let _ = progress_tx.send(serde_json::from_str::<FirmwareUpdateProgress>(event.payload()).unwrap().bytes_sent);
+        });
+        let (job_tx, jobs) = mpsc::channel();
+        app.handle().listen_any("firmware-job-updated", move |event| {
+            let _ = job_tx.send(serde_json::from_str::<FirmwareJobStatus>(event.payload()).unwrap());
+        });
+        let options = FirmwareJobOptions { image_path: None, resume: true, checkpoint_dir: Some(dir.clone()) };
+        start_job(app.handle().clone(), port_name, image.clone(), options).unwrap();
+        let finished = next_state(&jobs, FirmwareJobState::Completed);
+        assert_eq!(finished.bytes_acked, image.data.len() as u32);
+        assert_eq!(progress.try_iter().find(|&bytes_sent| bytes_sent > 0), Some(chunk_size as u32));
+        assert_eq!(load_checkpoint(&dir, serial).unwrap(), None);
+
+        disconnect_from_port(port_name.to_string()).unwrap();
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_paused_job_serves_commands_and_resumes() {
+        let port_name = "memory://test_firmware_job_pause";
+        let image = test_image(10000);
+        let (_app, jobs, paused) = start_paused_job(port_name, &image);
+        assert_eq!(paused.bytes_acked, profile_for(port_name).chunk_size as u32);
+        assert_eq!(job_status(&paused.job_id).unwrap().state, FirmwareJobState::Paused);
+        assert!(active_job_for_port(port_name).is_some());
+
+        // The handler is free between chunks
+        execute_with_connection(port_name, |handler| handler.ping()).unwrap();
+
+        resume_job(&paused.job_id).unwrap();
+        next_state(&jobs, FirmwareJobState::Running);
+        let finished = next_state(&jobs, FirmwareJobState::Completed);
+        assert_eq!(finished.bytes_acked, image.data.len() as u32);
+        assert!(finished.result.is_some_and(|result| result.crc32_match));
+        assert!(active_job_for_port(port_name).is_none());
+
+        disconnect_from_port(port_name.to_string()).unwrap();
+    }
+
+    #[test]
+    fn test_cancelled_job_aborts_the_device_update() {
+        let port_name = "memory://test_firmware_job_cancel";
+        let image = test_image(10000);
+        let (_app, jobs, paused) = start_paused_job(port_name, &image);
+
+        cancel_job(&paused.job_id).unwrap();
+        let cancelled = next_state(&jobs, FirmwareJobState::Cancelled);
+        assert_eq!(cancelled.bytes_acked, paused.bytes_acked);
+        assert_eq!(job_status(&paused.job_id).unwrap().state, FirmwareJobState::Cancelled);
+        // Cancelling a finished job changes nothing
+        assert_eq!(cancel_job(&paused.job_id).unwrap().state, FirmwareJobState::Cancelled);
+
+        // FW_UPDATE_ABORT closed the device's session, so the next chunk has nothing to extend
+        let offset = cancelled.bytes_acked as usize;
+        let next_chunk = execute_with_connection(port_name, |handler| {
+            handler.send_firmware_chunk_once(&image.data[offset..offset + 16], offset as u32)
+        });
+        assert!(matches!(next_chunk, Err(GcpCommError::Nack { error: Some(GcpError::InvalidParam), .. })),
+                "{:?}", next_chunk);
+
+        disconnect_from_port(port_name.to_string()).unwrap();
+    }
+}
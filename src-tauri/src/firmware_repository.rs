@@ -0,0 +1,149 @@
+//! Local firmware repository
+//!
+//! Answers device-initiated update checks (GCP v2.2 §4.6/§5.2) from a directory
+//! of firmware images. Image versions are taken from the file name, which must end
+//! in `v<major>.<minor>.<patch>[suffix]`, e.g. `glitchi_v0.1.5a.bin`.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::gcp::{GcpCommError, GcpFwVersionData};
+
+// Image formats the firmware image loader can turn into a flat binary
+const FIRMWARE_EXTENSIONS: &[&str] = &["bin", "hex", "fw"];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FirmwareRelease {
+    pub version: GcpFwVersionData,
+    pub version_string: String,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone)]
+pub struct FirmwareRepository {
+    dir: PathBuf,
+}
+
+lazy_static::lazy_static! {
+    static ref FIRMWARE_REPOSITORY: Mutex<Option<FirmwareRepository>> = Mutex::new(None);
+}
+
+impl FirmwareRepository {
+    pub fn new(dir: impl Into<PathBuf>) -> Result<Self, GcpCommError> {
+        let dir = dir.into();
+        if !dir.is_dir() {
+            return Err(GcpCommError::invalid_parameter(format!("Firmware repository {} is not a directory", dir.display())));
+        }
+        Ok(Self { dir })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    // All images in the repository with a parseable version, oldest first
+    pub fn releases(&self) -> Result<Vec<FirmwareRelease>, GcpCommError> {
+        let entries = fs::read_dir(&self.dir)
+            .map_err(|e| GcpCommError::file(&self.dir, format!("Failed to read firmware repository: {}", e)))?;
+
+        let mut releases: Vec<FirmwareRelease> = entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.is_file()
+                    && path.extension()
+                        .and_then(|ext| ext.to_str())
+                        .map(|ext| FIRMWARE_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+                        .unwrap_or(false)
+            })
+            .filter_map(|path| {
+                let stem = path.file_stem()?.to_str()?;
+                let version = parse_version_from_file_stem(stem)?;
+                Some(FirmwareRelease {
+                    version_string: version.version_string(),
+                    version,
+                    path,
+                })
+            })
+            .collect();
+
+        releases.sort_by(|a, b| {
+            if a.version.is_newer_than(&b.version) {
+                std::cmp::Ordering::Greater
+            } else if b.version.is_newer_than(&a.version) {
+                std::cmp::Ordering::Less
+            } else {
+                std::cmp::Ordering::Equal
+            }
+        });
+        Ok(releases)
+    }
+
+    // Newest image, if it is newer than the version the device reported
+    pub fn find_update(&self, current: &GcpFwVersionData) -> Result<Option<FirmwareRelease>, GcpCommError> {
+        Ok(self.releases()?
+            .pop()
+            .filter(|latest| latest.version.is_newer_than(current)))
+    }
+}
+
+pub fn set_firmware_repository(repository: Option<FirmwareRepository>) -> Result<(), GcpCommError> {
+    let mut current = FIRMWARE_REPOSITORY.lock()
+        .map_err(|_| GcpCommError::internal("Failed to lock firmware repository"))?;
+    *current = repository;
+    Ok(())
+}
+
+pub fn get_firmware_repository() -> Option<FirmwareRepository> {
+    FIRMWARE_REPOSITORY.lock().ok()?.clone()
+}
+
+// Parse "name_v0.1.5a" / "name-v1.2.0rc1" / "v0.2.0" into a firmware version
+fn parse_version_from_file_stem(stem: &str) -> Option<GcpFwVersionData> {
+    let version = stem.rsplit(['_', '-']).next()?;
+    let version = version.strip_prefix('v').or_else(|| version.strip_prefix('V'))?;
+
+    let mut parts = version.splitn(3, '.');
+    let major = parts.next()?.parse::<u8>().ok()?;
+    let minor = parts.next()?.parse::<u8>().ok()?;
+    let patch_and_suffix = parts.next()?;
+
+    let suffix_start = patch_and_suffix
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(patch_and_suffix.len());
+    let patch = patch_and_suffix[..suffix_start].parse::<u8>().ok()?;
+    let suffix = &patch_and_suffix[suffix_start..];
+    if suffix.len() > 3 || !suffix.is_ascii() {
+        return None;
+    }
+
+    let mut fw_version_suffix = [0u8; 3];
+    fw_version_suffix[..suffix.len()].copy_from_slice(suffix.as_bytes());
+
+    Some(GcpFwVersionData {
+        fw_version_major: major,
+        fw_version_minor: minor,
+        fw_version_patch: patch,
+        fw_version_suffix,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_version_from_file_stem() {
+        let version = parse_version_from_file_stem("glitchi_v0.1.5a").unwrap();
+        assert_eq!(version.version_string(), "0.1.5a");
+
+        let version = parse_version_from_file_stem("glitchi-v1.2.10rc1").unwrap();
+        assert_eq!(version.version_string(), "1.2.10rc1");
+
+        assert!(parse_version_from_file_stem("glitchi_latest").is_none());
+        assert!(parse_version_from_file_stem("glitchi_v1.2").is_none());
+        assert!(parse_version_from_file_stem("glitchi_v1.2.3beta").is_none());
+    }
+}
@@ -0,0 +1,399 @@
+//! Signed firmware containers (.fw) and host-side verification
+//!
+//! A `.fw` file is a fixed header followed by the flat firmware payload. All
+//! integers are little-endian, like GCP frames:
+//!
+//! | Offset | Size | Field                                   |
+//! |--------|------|-----------------------------------------|
+//! | 0      | 4    | Magic `GLFW`                            |
+//! | 4      | 2    | Container version (1)                   |
+//! | 6      | 1    | Target board_type (HWVersion_t)         |
+//! | 7      | 1    | Target chip_model (HWVersion_t)         |
+//! | 8      | 4    | Payload length                          |
+//! | 12     | 32   | SHA-256 of the payload                  |
+//! | 44     | 64   | Ed25519 signature over bytes 0..44      |
+//!
+//! Before FW_UPDATE_START the image must be signed by a key from the trusted key
+//! list in `firmware_trust.json` (app config directory) and target the connected
+//! device. Unsigned or mis-signed images are refused unless the developer
+//! override in the same file is enabled. The app only reads the override; it is
+//! changed by editing the file.
+
+use ed25519_dalek::{Signature, VerifyingKey};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::Path;
+use std::sync::Mutex;
+
+use crate::app_store::load_json;
+use crate::firmware_image::FirmwareImage;
+use crate::gcp::{GcpCommError, GcpHardwareData};
+use crate::hex::{decode_hex, encode_hex};
+
+pub const FIRMWARE_CONTAINER_MAGIC: &[u8; 4] = b"GLFW";
+pub const FIRMWARE_CONTAINER_VERSION: u16 = 1;
+pub const FIRMWARE_CONTAINER_HEADER_LEN: usize = 108;
+const SIGNED_HEADER_LEN: usize = 44;
+const TRUST_CONFIG_FILE: &str = "firmware_trust.json";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FirmwareContainerHeader {
+    pub version: u16,
+    pub board_type: u8,
+    pub chip_model: u8,
+    pub payload_len: u32,
+    pub payload_sha256: [u8; 32],
+    pub signature: [u8; 64],
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrustedFirmwareKey {
+    pub name: String,
+    pub public_key: String,      // Ed25519 public key, 64 hex digits
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FirmwareTrustConfig {
+    #[serde(default)]
+    pub trusted_keys: Vec<TrustedFirmwareKey>,
+    // Developer override: flash unsigned, mis-signed or mistargeted images
+    #[serde(default)]
+    pub allow_untrusted: bool,
+}
+
+// Result of checking an image, reported to the UI
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FirmwareSignatureInfo {
+    pub signed: bool,
+    pub trusted_key: Option<String>,
+    pub target_board_type: Option<u8>,
+    pub target_chip_model: Option<u8>,
+    pub payload_sha256: Option<String>,
+    pub developer_override: bool,
+}
+
+lazy_static::lazy_static! {
+    static ref TRUST_CONFIG: Mutex<FirmwareTrustConfig> = Mutex::new(FirmwareTrustConfig::default());
+}
+
+impl FirmwareContainerHeader {
+    // The signed part of the header, which commits to the target and payload hash
+    pub fn signed_bytes(&self) -> [u8; SIGNED_HEADER_LEN] {
+        let mut bytes = [0u8; SIGNED_HEADER_LEN];
+        bytes[0..4].copy_from_slice(FIRMWARE_CONTAINER_MAGIC);
+        bytes[4..6].copy_from_slice(&self.version.to_le_bytes());
+        bytes[6] = self.board_type;
+        bytes[7] = self.chip_model;
+        bytes[8..12].copy_from_slice(&self.payload_len.to_le_bytes());
+        bytes[12..44].copy_from_slice(&self.payload_sha256);
+        bytes
+    }
+}
+
+pub fn is_firmware_container(data: &[u8]) -> bool {
+    data.starts_with(FIRMWARE_CONTAINER_MAGIC)
+}
+
+// Split a container into its header and payload, checking length and SHA-256.
+// The signature is checked separately against the trusted keys.
+pub fn parse_firmware_container(data: &[u8]) -> Result<(FirmwareContainerHeader, &[u8]), GcpCommError> {
+    if data.len() < FIRMWARE_CONTAINER_HEADER_LEN {
+        return Err(GcpCommError::invalid_parameter(format!(
+            "Firmware container is {} bytes, shorter than its {} byte header", data.len(), FIRMWARE_CONTAINER_HEADER_LEN
+        )));
+    }
+    if !is_firmware_container(data) {
+        return Err(GcpCommError::invalid_parameter("Not a GLFW firmware container"));
+    }
+
+    let version = u16::from_le_bytes([data[4], data[5]]);
+    if version != FIRMWARE_CONTAINER_VERSION {
+        return Err(GcpCommError::invalid_parameter(format!("Unsupported firmware container version {}", version)));
+    }
+
+    let mut payload_sha256 = [0u8; 32];
+    payload_sha256.copy_from_slice(&data[12..44]);
+    let mut signature = [0u8; 64];
+    signature.copy_from_slice(&data[44..FIRMWARE_CONTAINER_HEADER_LEN]);
+    let header = FirmwareContainerHeader {
+        version,
+        board_type: data[6],
+        chip_model: data[7],
+        payload_len: u32::from_le_bytes([data[8], data[9], data[10], data[11]]),
+        payload_sha256,
+        signature,
+    };
+
+    let payload = &data[FIRMWARE_CONTAINER_HEADER_LEN..];
+    if payload.len() != header.payload_len as usize {
+        return Err(GcpCommError::invalid_parameter(format!(
+            "Firmware container declares {} payload bytes but holds {}", header.payload_len, payload.len()
+        )));
+    }
+    if Sha256::digest(payload).as_slice() != header.payload_sha256 {
+        return Err(GcpCommError::untrusted_firmware("payload SHA-256 does not match the container header"));
+    }
+
+    Ok((header, payload))
+}
+
+// Load firmware_trust.json from the app config directory, set once during startup.
+// A missing file means no trusted keys and no override.
+pub fn load_trust_config(dir: &Path) -> Result<FirmwareTrustConfig, GcpCommError> {
+    let config = read_trust_config(dir)?;
+    *TRUST_CONFIG.lock().map_err(|_| GcpCommError::internal("Failed to lock firmware trust config"))? = config.clone();
+    Ok(config)
+}
+
+fn read_trust_config(dir: &Path) -> Result<FirmwareTrustConfig, GcpCommError> {
+    let path = dir.join(TRUST_CONFIG_FILE);
+    let config: FirmwareTrustConfig = load_json(&path, "firmware trust config")?.unwrap_or_default();
+
+    for key in &config.trusted_keys {
+        decode_public_key(&key.public_key)
+            .map_err(|reason| GcpCommError::file(&path, format!("Trusted key '{}': {}", key.name, reason)))?;
+    }
+    Ok(config)
+}
+
+pub fn trust_config() -> FirmwareTrustConfig {
+    match TRUST_CONFIG.lock() {
+        Ok(config) => config.clone(),
+        Err(poisoned) => poisoned.into_inner().clone(),
+    }
+}
+
+// Describe an image's signature without checking it against a device
+pub fn signature_info(image: &FirmwareImage) -> FirmwareSignatureInfo {
+    let config = trust_config();
+    describe(&config, image, trusted_key(&config, image))
+}
+
+// Gate run before FW_UPDATE_START. `hardware` is None for devices that do not
+// report HWVersion_t (pre-v2.2), whose target cannot be checked.
+pub fn verify_firmware_image(image: &FirmwareImage, hardware: Option<&GcpHardwareData>) -> Result<FirmwareSignatureInfo, GcpCommError> {
+    verify_with(&trust_config(), image, hardware)
+}
+
+fn verify_with(
+    config: &FirmwareTrustConfig,
+    image: &FirmwareImage,
+    hardware: Option<&GcpHardwareData>,
+) -> Result<FirmwareSignatureInfo, GcpCommError> {
+    let key = trusted_key(config, image);
+    let info = describe(config, image, key.clone());
+
+    let problem = match (&image.container, key, hardware) {
+        (None, _, _) => Some("image is not signed".to_string()),
+        (Some(_), None, _) => Some("signature does not match any trusted key".to_string()),
+        (Some(_), Some(_), None) => Some("device did not report its board type and chip model".to_string()),
+        (Some(header), Some(_), Some(hw)) if header.board_type != hw.board_type || header.chip_model != hw.chip_model => {
+            Some(format!(
+                "image targets board 0x{:02X} / chip 0x{:02X}, device is board 0x{:02X} / chip 0x{:02X}",
+                header.board_type, header.chip_model, hw.board_type, hw.chip_model
+            ))
+        }
+        _ => None,
+    };
+
+    match problem {
+        None => Ok(info),
+        Some(reason) if config.allow_untrusted => {
+            log::warn!(target: "gcp::fw", "Developer override: flashing untrusted firmware ({})", reason);
+            Ok(info)
+        }
+        Some(reason) => Err(GcpCommError::untrusted_firmware(reason)),
+    }
+}
+
+fn trusted_key(config: &FirmwareTrustConfig, image: &FirmwareImage) -> Option<String> {
+    let header = image.container.as_ref()?;
+    let signature = Signature::from_bytes(&header.signature);
+    let message = header.signed_bytes();
+
+    config.trusted_keys.iter()
+        .find(|key| match decode_public_key(&key.public_key) {
+            Ok(public_key) => public_key.verify_strict(&message, &signature).is_ok(),
+            Err(_) => false,
+        })
+        .map(|key| key.name.clone())
+}
+
+fn describe(config: &FirmwareTrustConfig, image: &FirmwareImage, trusted_key: Option<String>) -> FirmwareSignatureInfo {
+    let header = image.container.as_ref();
+    FirmwareSignatureInfo {
+        signed: header.is_some(),
+        trusted_key,
+        target_board_type: header.map(|h| h.board_type),
+        target_chip_model: header.map(|h| h.chip_model),
+        payload_sha256: header.map(|h| encode_hex(&h.payload_sha256)),
+        developer_override: config.allow_untrusted,
+    }
+}
+
+fn decode_public_key(hex: &str) -> Result<VerifyingKey, String> {
+    let hex = hex.trim();
+    if hex.len() != 64 {
+        return Err(format!("expected 64 hex digits, found {}", hex.len()));
+    }
+
+    let mut bytes = [0u8; 32];
+    bytes.copy_from_slice(&decode_hex(hex)?);
+    VerifyingKey::from_bytes(&bytes).map_err(|e| format!("invalid Ed25519 public key: {}", e))
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::app_store::save_json;
+    use crate::app_store::tests::test_dir;
+    use crate::firmware_image::FirmwareImageFormat;
+    use ed25519_dalek::{Signer, SigningKey};
+
+    const BOARD_REV0: u8 = 0x10;
+    const CHIP_APOLLO4_LITE: u8 = 0x40;
+
+    fn hardware() -> GcpHardwareData {
+        GcpHardwareData {
+            manufacture_date: 0x0719,
+            serial_number: 1000,
+            board_type: BOARD_REV0,
+            hw_revision: 1,
+            chip_model: CHIP_APOLLO4_LITE,
+            features: 0x03,
+        }
+    }
+
+    fn build_container(signing_key: &SigningKey, payload: &[u8]) -> Vec<u8> {
+        let mut payload_sha256 = [0u8; 32];
+        payload_sha256.copy_from_slice(&Sha256::digest(payload));
+        let mut header = FirmwareContainerHeader {
+            version: FIRMWARE_CONTAINER_VERSION,
+            board_type: BOARD_REV0,
+            chip_model: CHIP_APOLLO4_LITE,
+            payload_len: payload.len() as u32,
+            payload_sha256,
+            signature: [0u8; 64],
+        };
+        header.signature = signing_key.sign(&header.signed_bytes()).to_bytes();
+
+        let mut container = header.signed_bytes().to_vec();
+        container.extend_from_slice(&header.signature);
+        container.extend_from_slice(payload);
+        container
+    }
+
+    pub(crate) fn image_from(container: &[u8]) -> FirmwareImage {
+        let (header, payload) = parse_firmware_container(container).unwrap();
+        FirmwareImage {
+            format: FirmwareImageFormat::SignedContainer,
+            base_address: 0,
+            entry_point: None,
+            data: payload.to_vec(),
+            container: Some(header),
+        }
+    }
+
+    fn config_trusting(signing_key: &SigningKey) -> FirmwareTrustConfig {
+        let public_key = signing_key.verifying_key().to_bytes();
+        FirmwareTrustConfig {
+            trusted_keys: vec![TrustedFirmwareKey {
+                name: "release".to_string(),
+                public_key: encode_hex(&public_key),
+            }],
+            allow_untrusted: false,
+        }
+    }
+
+    // Container for the simulated device's board and chip, signed by a key the app's
+    // trust config trusts; for tests that run whole firmware jobs
+    pub(crate) fn trusted_test_container(payload: &[u8]) -> Vec<u8> {
+        let signing_key = SigningKey::from_bytes(&[42u8; 32]);
+        let test_key = config_trusting(&signing_key).trusted_keys.remove(0);
+        if let Ok(mut config) = TRUST_CONFIG.lock() {
+            if !config.trusted_keys.iter().any(|key| key.public_key == test_key.public_key) {
+                config.trusted_keys.push(test_key);
+            }
+        }
+        build_container(&signing_key, payload)
+    }
+
+    #[test]
+    fn test_container_parsing() {
+        let signing_key = SigningKey::from_bytes(&[7u8; 32]);
+        let payload: Vec<u8> = (0..300u32).map(|i| i as u8).collect();
+        let container = build_container(&signing_key, &payload);
+
+        let (header, parsed) = parse_firmware_container(&container).unwrap();
+        assert_eq!(header.board_type, BOARD_REV0);
+        assert_eq!(header.payload_len, 300);
+        assert_eq!(parsed, &payload[..]);
+
+        // Payload modified after signing
+        let mut tampered = container.clone();
+        tampered[FIRMWARE_CONTAINER_HEADER_LEN + 5] ^= 0x01;
+        assert!(matches!(parse_firmware_container(&tampered), Err(GcpCommError::UntrustedFirmware { .. })));
+
+        // Truncated payload and wrong magic
+        assert!(parse_firmware_container(&container[..container.len() - 1]).is_err());
+        assert!(parse_firmware_container(&payload).is_err());
+    }
+
+    #[test]
+    fn test_trust_config_rejects_malformed_keys() {
+        let signing_key = SigningKey::from_bytes(&[7u8; 32]);
+        let dir = test_dir("firmware_trust");
+        assert!(read_trust_config(&dir).unwrap().trusted_keys.is_empty());
+
+        let config = config_trusting(&signing_key);
+        save_json(&dir.join(TRUST_CONFIG_FILE), &config, "firmware trust config").unwrap();
+        assert_eq!(read_trust_config(&dir).unwrap().trusted_keys.len(), 1);
+
+        // A bad key in firmware_trust.json is a file error, never a panic in startup
+        let public_key = config.trusted_keys[0].public_key.clone();
+        for bad_key in [public_key[..63].to_string(), format!("a{}a", "é".repeat(31)), "zz".repeat(32)] {
+            let key = TrustedFirmwareKey { name: "bad".to_string(), public_key: bad_key };
+            let bad = FirmwareTrustConfig { trusted_keys: vec![key], ..config.clone() };
+            save_json(&dir.join(TRUST_CONFIG_FILE), &bad, "firmware trust config").unwrap();
+            assert!(matches!(read_trust_config(&dir), Err(GcpCommError::File { .. })));
+        }
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_signature_verification() {
+        let signing_key = SigningKey::from_bytes(&[7u8; 32]);
+        let other_key = SigningKey::from_bytes(&[9u8; 32]);
+        let config = config_trusting(&signing_key);
+        let hw = hardware();
+
+        let image = image_from(&build_container(&signing_key, &[1, 2, 3, 4]));
+        let info = verify_with(&config, &image, Some(&hw)).unwrap();
+        assert_eq!(info.trusted_key.as_deref(), Some("release"));
+
+        // Signed by a key that is not trusted
+        let untrusted = image_from(&build_container(&other_key, &[1, 2, 3, 4]));
+        assert!(verify_with(&config, &untrusted, Some(&hw)).is_err());
+
+        // Header changed after signing
+        let mut retargeted = image.clone();
+        if let Some(header) = retargeted.container.as_mut() {
+            header.board_type = 0x01;
+        }
+        assert!(verify_with(&config, &retargeted, Some(&hw)).is_err());
+
+        // Built for another device, or a device that cannot be identified
+        let other_device = GcpHardwareData { chip_model: 0x41, ..hw.clone() };
+        assert!(verify_with(&config, &image, Some(&other_device)).is_err());
+        assert!(verify_with(&config, &image, None).is_err());
+
+        // Raw binaries are unsigned
+        let unsigned = FirmwareImage::from_binary(vec![1, 2, 3, 4]);
+        assert!(verify_with(&config, &unsigned, Some(&hw)).is_err());
+
+        // Developer override accepts all of the above
+        let developer = FirmwareTrustConfig { allow_untrusted: true, ..config };
+        assert!(verify_with(&developer, &untrusted, Some(&hw)).is_ok());
+        assert!(verify_with(&developer, &unsigned, None).is_ok());
+    }
+}
@@ -0,0 +1,269 @@
+//! Adaptive pacing and throughput history for firmware transfers
+//!
+//! FW_UPDATE_DATA is sent as soon as the previous chunk's ACK arrives; the pacer only
+//! decides what to do when a chunk fails (GCP v2.2 §9):
+//!
+//! - GCP_ERROR_BUSY and timeouts back off, doubling the delay on every failure
+//! - GCP_ERROR_CRC and GCP_ERROR_SEQ resend from the last ACK'd offset right away
+//! - GCP_ERROR_SIZE halves the chunk size for the rest of the transfer
+//!
+//! Completed transfers are recorded as throughput samples in the app data directory,
+//! and transfer time estimates are made from the samples taken at the same baud rate
+//! and chunk size instead of a fixed overhead factor.
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::app_store::{load_json, save_json};
+use crate::gcp::{GcpCommError, GcpError};
+use crate::link_config::LinkConfig;
+
+const TRANSFER_BACKOFF_INITIAL_MS: u64 = 50;
+const TRANSFER_BACKOFF_MAX_MS: u64 = 1000;
+// Smallest chunk GCP_ERROR_SIZE may shrink the transfer to
+pub const TRANSFER_MIN_CHUNK_SIZE: usize = 128;
+const THROUGHPUT_HISTORY_LEN: usize = 20;
+const THROUGHPUT_HISTORY_FILE: &str = "fw_throughput.json";
+// FW_UPDATE_DATA framing around the chunk, and the ACK echoing its SeqNo (§4.2, §4.4)
+const DATA_FRAME_OVERHEAD: usize = 12;
+const DATA_ACK_FRAME_LEN: usize = 14;
+
+// What to do after a failed FW_UPDATE_DATA
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Recovery {
+    Resend,
+    ResendAfter(Duration),
+    ShrinkTo(usize),
+    Abort,
+}
+
+pub struct TransferPacer {
+    chunk_size: usize,
+    max_retries: u32,
+    failures: u32,           // Consecutive failures since the last ACK
+}
+
+impl TransferPacer {
+    pub fn new(chunk_size: usize, max_retries: u32) -> Self {
+        Self { chunk_size, max_retries, failures: 0 }
+    }
+
+    pub fn chunk_size(&self) -> usize {
+        self.chunk_size
+    }
+
+    pub fn on_ack(&mut self) {
+        self.failures = 0;
+    }
+
+    pub fn on_error(&mut self, error: &GcpCommError) -> Recovery {
+        // A smaller chunk is a new attempt, not a retry of the failed one
+        if let GcpCommError::Nack { error: Some(GcpError::Size), .. } = error {
+            if self.chunk_size <= TRANSFER_MIN_CHUNK_SIZE {
+                return Recovery::Abort;
+            }
+            self.chunk_size = (self.chunk_size / 2).max(TRANSFER_MIN_CHUNK_SIZE);
+            return Recovery::ShrinkTo(self.chunk_size);
+        }
+
+        // `max_retries` counts attempts including the first, like the command retry loops
+        self.failures += 1;
+        if self.failures >= self.max_retries {
+            return Recovery::Abort;
+        }
+
+        match error {
+            GcpCommError::Nack { error: Some(GcpError::Busy), .. } | GcpCommError::Timeout { .. } => {
+                let backoff = TRANSFER_BACKOFF_INITIAL_MS << (self.failures - 1);
+                Recovery::ResendAfter(Duration::from_millis(backoff.min(TRANSFER_BACKOFF_MAX_MS)))
+            }
+            GcpCommError::Nack { error: Some(GcpError::Crc | GcpError::Seq), .. } => Recovery::Resend,
+            e if e.is_retryable() => Recovery::Resend,
+            _ => Recovery::Abort,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ThroughputSample {
+    pub port_name: String,
+    pub bytes: u32,
+    pub elapsed_ms: u64,
+    #[serde(default)]
+    pub baud_rate: u32,          // Link the transfer ran on; 0 in history from older versions
+    #[serde(default)]
+    pub chunk_size: u32,         // Chunk size the transfer started with
+    pub final_chunk_size: u32,
+    pub recorded_at: u64,        // Unix time (seconds)
+}
+
+impl ThroughputSample {
+    pub fn bytes_per_second(&self) -> f64 {
+        self.bytes as f64 / (self.elapsed_ms.max(1) as f64 / 1000.0)
+    }
+
+    // Only transfers over the same baud rate and chunk size predict another one
+    fn matches(&self, link: &LinkConfig) -> bool {
+        self.baud_rate == link.baud_rate && self.chunk_size == link.chunk_size as u32
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EstimateSource {
+    Measured,    // Recent transfers
+    LineRate,    // No history yet: frame bytes at the UART baud rate
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct TransferEstimate {
+    pub seconds: f64,
+    pub bytes_per_second: f64,
+    pub source: EstimateSource,
+}
+
+struct ThroughputHistory {
+    path: Option<PathBuf>,
+    samples: Vec<ThroughputSample>,
+}
+
+lazy_static::lazy_static! {
+    static ref HISTORY: Mutex<ThroughputHistory> = Mutex::new(ThroughputHistory { path: None, samples: Vec::new() });
+}
+
+// History lives in the app data directory, set once during startup
+pub fn set_history_dir(dir: PathBuf) {
+    let path = dir.join(THROUGHPUT_HISTORY_FILE);
+    let samples = load_json(&path, "throughput history").unwrap_or_else(|e| {
+        log::warn!(target: "gcp::fw", "Ignoring throughput history: {}", e);
+        None
+    });
+    if let Ok(mut history) = HISTORY.lock() {
+        *history = ThroughputHistory { path: Some(path), samples: samples.unwrap_or_default() };
+    }
+}
+
+pub fn throughput_history() -> Vec<ThroughputSample> {
+    HISTORY.lock().map(|history| history.samples.clone()).unwrap_or_default()
+}
+
+pub fn record_transfer(port_name: &str, link: &LinkConfig, bytes: u32, elapsed: Duration, final_chunk_size: usize) {
+    let sample = ThroughputSample {
+        port_name: port_name.to_string(),
+        bytes,
+        elapsed_ms: elapsed.as_millis() as u64,
+        baud_rate: link.baud_rate,
+        chunk_size: link.chunk_size as u32,
+        final_chunk_size: final_chunk_size as u32,
+        recorded_at: SystemTime::now().duration_since(UNIX_EPOCH).map(|t| t.as_secs()).unwrap_or(0),
+    };
+    log::info!(target: "gcp::fw", "Firmware transfer on {}: {} bytes in {} ms ({:.1} KB/s)",
+                                  port_name, bytes, sample.elapsed_ms, sample.bytes_per_second() / 1024.0);
+
+    let Ok(mut history) = HISTORY.lock() else {
+        return;
+    };
+    history.samples.push(sample);
+    let excess = history.samples.len().saturating_sub(THROUGHPUT_HISTORY_LEN);
+    history.samples.drain(..excess);
+
+    if let Some(path) = &history.path {
+        if let Err(e) = save_json(path, &history.samples, "throughput history") {
+            log::warn!(target: "gcp::fw", "{}", e);
+        }
+    }
+}
+
+// From earlier transfers over the same baud rate and chunk size as `link`; without
+// any, from the line rate
+pub fn estimate_transfer(image_size: usize, link: &LinkConfig) -> TransferEstimate {
+    estimate_from(&throughput_history(), image_size, link)
+}
+
+fn estimate_from(samples: &[ThroughputSample], image_size: usize, link: &LinkConfig) -> TransferEstimate {
+    let matching = || samples.iter().filter(|sample| sample.matches(link));
+    let bytes: u64 = matching().map(|sample| sample.bytes as u64).sum();
+    let elapsed_ms: u64 = matching().map(|sample| sample.elapsed_ms).sum();
+
+    let (bytes_per_second, source) = if bytes > 0 && elapsed_ms > 0 {
+        (bytes as f64 / (elapsed_ms as f64 / 1000.0), EstimateSource::Measured)
+    } else {
+        // 10 bits per byte (8N1); every chunk also carries its frame and ACK
+        let chunks = image_size.div_ceil((link.chunk_size as usize).max(1)).max(1);
+        let wire_bytes = image_size + chunks * (DATA_FRAME_OVERHEAD + DATA_ACK_FRAME_LEN);
+        let wire_seconds = wire_bytes as f64 * 10.0 / link.baud_rate.max(1) as f64;
+        (image_size.max(1) as f64 / wire_seconds, EstimateSource::LineRate)
+    };
+
+    TransferEstimate {
+        seconds: image_size as f64 / bytes_per_second,
+        bytes_per_second,
+        source,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nack(error: GcpError) -> GcpCommError {
+        GcpCommError::Nack { msg_type: 0x1002, seq_no: 0, error_code: error as u16, error: Some(error) }
+    }
+
+    #[test]
+    fn test_pacer_recovery() {
+        let mut pacer = TransferPacer::new(2036, 3);
+
+        // Busy and timeouts back off, doubling until the third attempt has failed
+        assert_eq!(pacer.on_error(&nack(GcpError::Busy)), Recovery::ResendAfter(Duration::from_millis(50)));
+        assert_eq!(pacer.on_error(&GcpCommError::Timeout { timeout_ms: 1000 }), Recovery::ResendAfter(Duration::from_millis(100)));
+        assert_eq!(pacer.on_error(&nack(GcpError::Crc)), Recovery::Abort);
+
+        // An ACK resets the retries; size errors shrink down to the minimum
+        pacer.on_ack();
+        assert_eq!(pacer.on_error(&nack(GcpError::Seq)), Recovery::Resend);
+        assert_eq!(pacer.on_error(&nack(GcpError::Size)), Recovery::ShrinkTo(1018));
+        assert_eq!(pacer.chunk_size(), 1018);
+        while pacer.chunk_size() > TRANSFER_MIN_CHUNK_SIZE {
+            assert!(matches!(pacer.on_error(&nack(GcpError::Size)), Recovery::ShrinkTo(_)));
+        }
+        assert_eq!(pacer.on_error(&nack(GcpError::Size)), Recovery::Abort);
+        assert_eq!(pacer.on_error(&nack(GcpError::Mram)), Recovery::Abort);
+    }
+
+    #[test]
+    fn test_estimate_from_history() {
+        // Without history: 200 KB at 115200 baud, framing included
+        let line_rate = estimate_from(&[], 200 * 1024, &LinkConfig::default());
+        assert_eq!(line_rate.source, EstimateSource::LineRate);
+        assert!(line_rate.seconds > 17.7 && line_rate.seconds < 18.5, "{}", line_rate.seconds);
+        let faster = estimate_from(&[], 200 * 1024, &LinkConfig { baud_rate: 921_600, ..LinkConfig::default() });
+        assert!(faster.seconds > 2.2 && faster.seconds < 2.4, "{}", faster.seconds);
+
+        let link = LinkConfig::default();
+        let sample = |bytes, elapsed_ms, baud_rate| ThroughputSample {
+            port_name: "COM3".to_string(),
+            bytes,
+            elapsed_ms,
+            baud_rate,
+            chunk_size: link.chunk_size as u32,
+            final_chunk_size: link.chunk_size as u32,
+            recorded_at: 0,
+        };
+        let history = [
+            sample(100_000, 10_000, link.baud_rate),
+            sample(300_000, 30_000, link.baud_rate),
+            sample(400_000, 5_000, 921_600),
+        ];
+        let measured = estimate_from(&history, 200_000, &link);
+        assert_eq!(measured.source, EstimateSource::Measured);
+        assert_eq!(measured.bytes_per_second, 10_000.0);
+        assert_eq!(measured.seconds, 20.0);
+
+        // History from other links does not apply
+        let other_chunk = LinkConfig { chunk_size: 1018, ..link.clone() };
+        assert_eq!(estimate_from(&history, 200_000, &other_chunk).source, EstimateSource::LineRate);
+    }
+}
@@ -0,0 +1,291 @@
+//! Listener for device-initiated firmware updates (GCP v2.2 §4.6/§5.2)
+//!
+//! The host only reads in response to its own requests, so one background thread
+//! per pooled connection watches for unsolicited FW_UPDATE_REQUEST frames. Each
+//! request is reported as a "firmware-update-request" event and answered from the
+//! configured firmware repository: a firmware update job when a newer image
+//! exists, FW_NO_UPDATE_AVAILABLE otherwise.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Runtime};
+
+use crate::firmware_checkpoint::checkpoint_dir;
+use crate::firmware_image::{load_firmware_image, FIRMWARE_DEFAULT_PAD_BYTE};
+use crate::firmware_job::{self, FirmwareJobOptions};
+use crate::firmware_repository::get_firmware_repository;
+use crate::gcp::{get_connection_handle, parse_fw_update_request, GcpCommError, GcpCommand, GcpFwVersionData, GcpUartHandler};
+use crate::gcp_decoder::DecodedFrame;
+
+const LISTENER_POLL_INTERVAL_MS: u64 = 100;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FirmwareUpdateRequestEvent {
+    pub port_name: String,
+    pub current_version: GcpFwVersionData,
+    pub current_version_string: String,
+    pub available_version: Option<String>,
+    pub action: String,            // "update" or "no-update"
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FirmwareUpdateRequestOutcome {
+    pub port_name: String,
+    pub success: bool,
+    pub message: String,
+}
+
+struct ListenerHandle {
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+lazy_static::lazy_static! {
+    static ref LISTENERS: Mutex<HashMap<String, ListenerHandle>> = Mutex::new(HashMap::new());
+}
+
+// Start listening on a pooled connection. Does nothing if a listener is already running.
+pub fn start_listener<R: Runtime>(app: AppHandle<R>, port_name: String) -> Result<(), GcpCommError> {
+    let mut listeners = LISTENERS.lock()
+        .map_err(|_| GcpCommError::internal("Failed to lock listener registry"))?;
+
+    if let Some(existing) = listeners.get(&port_name) {
+        if !existing.thread.is_finished() {
+            return Ok(());
+        }
+    }
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread_stop = stop.clone();
+    let thread_port = port_name.clone();
+    let thread = std::thread::Builder::new()
+        .name(format!("gcp-fw-listener-{}", port_name))
+        .spawn(move || listen(app, thread_port, thread_stop))
+        .map_err(|e| GcpCommError::internal(format!("Failed to start firmware update listener: {}", e)))?;
+
+    listeners.insert(port_name, ListenerHandle { stop, thread });
+    Ok(())
+}
+
+pub fn stop_listener(port_name: &str) {
+    let handle = match LISTENERS.lock() {
+        Ok(mut listeners) => listeners.remove(port_name),
+        Err(_) => None,
+    };
+
+    if let Some(handle) = handle {
+        handle.stop.store(true, Ordering::Relaxed);
+        let _ = handle.thread.join();
+    }
+}
+
+fn listen<R: Runtime>(app: AppHandle<R>, port_name: String, stop: Arc<AtomicBool>) {
+    log::debug!(target: "gcp::listener", "Firmware update listener started for {}", port_name);
+
+    while !stop.load(Ordering::Relaxed) {
+        std::thread::sleep(Duration::from_millis(LISTENER_POLL_INTERVAL_MS));
+
+        // Connection was closed without stopping the listener
+        let Some(handler_arc) = get_connection_handle(&port_name) else {
+            break;
+        };
+
+        // A firmware job owns the RX stream between its chunks as well
+        if firmware_job::active_job_for_port(&port_name).is_some() {
+            continue;
+        }
+
+        // Never wait for the handler: a command in progress owns the RX stream
+        let Ok(mut handler) = handler_arc.try_lock() else {
+            continue;
+        };
+
+        let polled = handler.poll_unsolicited();
+        drop(handler);
+        match polled {
+            Ok(Some(DecodedFrame::Frame(frame))) if frame.msg_type == GcpCommand::FwUpdateRequest => {
+                match parse_fw_update_request(&frame) {
+                    Ok(current_version) => handle_update_request(&app, &port_name, &handler_arc, current_version),
+                    Err(e) => log::warn!(target: "gcp::listener", "Ignoring malformed FW_UPDATE_REQUEST on {}: {}", port_name, e),
+                }
+            }
+            Ok(Some(DecodedFrame::Frame(frame))) => {
+                log::debug!(target: "gcp::listener", "Ignoring unsolicited {:?} frame on {}", frame.msg_type, port_name);
+            }
+            Ok(Some(unknown)) => {
+                log::warn!(target: "gcp::listener", "Ignoring unsolicited frame with unknown MsgType 0x{:04X} on {}",
+                                                    unknown.msg_type(), port_name);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                log::warn!(target: "gcp::listener", "Firmware update listener read error on {}: {}", port_name, e);
+            }
+        }
+    }
+
+    log::debug!(target: "gcp::listener", "Firmware update listener stopped for {}", port_name);
+}
+
+// Answered without holding the handler, so loading a large image doesn't block
+// commands. The device is told FW_NO_UPDATE_AVAILABLE whenever no job could be
+// started; otherwise it would wait for a FW_UPDATE_START that never comes.
+fn handle_update_request<R: Runtime>(
+    app: &AppHandle<R>,
+    port_name: &str,
+    handler: &Mutex<GcpUartHandler>,
+    current_version: GcpFwVersionData,
+) {
+    log::info!(target: "gcp::listener", "Device on {} requested firmware update check (current version {})",
+                                        port_name, current_version.version_string());
+
+    // Without a configured repository there is nothing newer to offer
+    let release = match get_firmware_repository() {
+        Some(repository) => repository.find_update(&current_version).unwrap_or_else(|e| {
+            log::warn!(target: "gcp::listener", "Firmware repository lookup failed: {}", e);
+            None
+        }),
+        None => None,
+    };
+
+    let _ = app.emit("firmware-update-request", FirmwareUpdateRequestEvent {
+        port_name: port_name.to_string(),
+        current_version_string: current_version.version_string(),
+        current_version,
+        available_version: release.as_ref().map(|r| r.version_string.clone()),
+        action: if release.is_some() { "update" } else { "no-update" }.to_string(),
+    });
+
+    let started = release.map(|release| {
+        log::info!(target: "gcp::listener", "Updating device on {} to {} from {}",
+                                            port_name, release.version_string, release.path.display());
+        load_firmware_image(&release.path, FIRMWARE_DEFAULT_PAD_BYTE).and_then(|image| {
+            // A device that lost power mid-update asks again; the transfer starts over
+            let options = FirmwareJobOptions {
+                image_path: Some(release.path.to_string_lossy().to_string()),
+                resume: true,
+                checkpoint_dir: checkpoint_dir().ok(),
+            };
+            firmware_job::start_job(app.clone(), port_name, image, options)
+        })
+    });
+
+    let (success, message) = match started {
+        Some(Ok(job)) => (true, format!("Started firmware update job {}", job.job_id)),
+        Some(Err(e)) => {
+            log::warn!(target: "gcp::listener", "Failed to start firmware update on {}: {}", port_name, e);
+            match send_no_update_available(handler) {
+                Ok(()) => (false, format!("Failed to start firmware update ({}), sent FW_NO_UPDATE_AVAILABLE", e)),
+                Err(no_update) => (false, format!("Failed to start firmware update ({}) or send FW_NO_UPDATE_AVAILABLE ({})", e, no_update)),
+            }
+        }
+        None => send_no_update_available(handler)
+            .map(|()| (true, "Device is up to date, sent FW_NO_UPDATE_AVAILABLE".to_string()))
+            .unwrap_or_else(|e| (false, e.to_string())),
+    };
+
+    let _ = app.emit("firmware-update-request-handled", FirmwareUpdateRequestOutcome {
+        port_name: port_name.to_string(),
+        success,
+        message,
+    });
+}
+
+fn send_no_update_available(handler: &Mutex<GcpUartHandler>) -> Result<(), GcpCommError> {
+    handler.lock()
+        .map_err(|_| GcpCommError::internal("Failed to lock handler"))?
+        .send_no_update_available()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app_store::tests::test_dir;
+    use crate::firmware_job::{FirmwareJobState, FirmwareJobStatus};
+    use crate::firmware_repository::{set_firmware_repository, FirmwareRepository};
+    use crate::firmware_signing::tests::trusted_test_container;
+    use crate::gcp::{connect_with_transport, disconnect_from_port};
+    use crate::simulator::{spawn_memory_device, SimulatorConfig};
+    use std::sync::mpsc;
+    use tauri::Listener;
+
+    // Both tests replace the global firmware repository
+    static REPOSITORY_LOCK: Mutex<()> = Mutex::new(());
+
+    #[test]
+    fn test_device_request_starts_update_job() {
+        let _repository = REPOSITORY_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let port_name = "memory://test_fw_update_listener".to_string();
+        let dir = test_dir("fw_repository");
+        std::fs::create_dir_all(&dir).unwrap();
+        let image: Vec<u8> = (0..5000u32).map(|i| (i * 13) as u8).collect();
+        std::fs::write(dir.join("glitchi_v0.1.5a.fw"), trusted_test_container(&image)).unwrap();
+        set_firmware_repository(Some(FirmwareRepository::new(&dir).unwrap())).unwrap();
+
+        // The simulated device runs 0.1.4a and asks for an update once it is up
+        let config = SimulatorConfig { request_update: true, ..SimulatorConfig::default() };
+        connect_with_transport(port_name.clone(), Box::new(spawn_memory_device(config))).unwrap();
+
+        let app = tauri::test::mock_app();
+        let (outcome_tx, outcomes) = mpsc::channel();
+        app.handle().listen_any("firmware-update-request-handled", move |event| {
+            let _ = outcome_tx.send(serde_json::from_str::<FirmwareUpdateRequestOutcome>(event.payload()).unwrap());
+        });
+        let (job_tx, jobs) = mpsc::channel();
+        app.handle().listen_any("firmware-job-updated", move |event| {
+            let _ = job_tx.send(serde_json::from_str::<FirmwareJobStatus>(event.payload()).unwrap());
+        });
+        start_listener(app.handle().clone(), port_name.clone()).unwrap();
+
+        // The job is started while the listener holds the handler
+        let outcome = outcomes.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(outcome.success, "{}", outcome.message);
+        let finished = loop {
+            let status = jobs.recv_timeout(Duration::from_secs(10)).unwrap();
+            if status.state.is_finished() {
+                break status;
+            }
+        };
+        assert_eq!(finished.state, FirmwareJobState::Completed, "{}", finished.message);
+        assert_eq!(finished.bytes_acked, image.len() as u32);
+
+        stop_listener(&port_name);
+        disconnect_from_port(port_name).unwrap();
+        set_firmware_repository(None).unwrap();
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_unloadable_image_answers_no_update() {
+        let _repository = REPOSITORY_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let port_name = "memory://test_fw_update_listener_broken".to_string();
+        let dir = test_dir("fw_repository_broken");
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("glitchi_v0.1.5a.fw"), b"not a firmware container").unwrap();
+        set_firmware_repository(Some(FirmwareRepository::new(&dir).unwrap())).unwrap();
+
+        let config = SimulatorConfig { request_update: true, ..SimulatorConfig::default() };
+        connect_with_transport(port_name.clone(), Box::new(spawn_memory_device(config))).unwrap();
+
+        let app = tauri::test::mock_app();
+        let (outcome_tx, outcomes) = mpsc::channel();
+        app.handle().listen_any("firmware-update-request-handled", move |event| {
+            let _ = outcome_tx.send(serde_json::from_str::<FirmwareUpdateRequestOutcome>(event.payload()).unwrap());
+        });
+        start_listener(app.handle().clone(), port_name.clone()).unwrap();
+
+        // The device is released from update mode instead of waiting for FW_UPDATE_START
+        let outcome = outcomes.recv_timeout(Duration::from_secs(5)).unwrap();
+        assert!(!outcome.success);
+        assert!(outcome.message.ends_with("sent FW_NO_UPDATE_AVAILABLE"), "{}", outcome.message);
+        assert!(firmware_job::active_job_for_port(&port_name).is_none());
+
+        stop_listener(&port_name);
+        disconnect_from_port(port_name).unwrap();
+        set_firmware_repository(None).unwrap();
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -4,10 +4,15 @@
 //! over UART as specified in gcp_spec_v2.md
 
 use serde::{Deserialize, Serialize};
-use std::io::{Read, Write};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 use std::sync::{Arc, Mutex};
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+
+use crate::gcp_decoder::{DecodedFrame, GcpDecoder};
+use crate::capture::{CaptureSummary, ProtocolCapture};
+use crate::link_config::{self, LinkConfig};
+use crate::protocol_log::{self, LogLevel, ProtocolEvent, TARGET_FW, TARGET_LINK, TARGET_RX, TARGET_TX};
+use crate::transport::{open_transport, GcpTransport};
 
 // Protocol Constants
 pub const GCP_PREAMBLE: [u8; 2] = [0xAA, 0x55];
@@ -16,9 +21,19 @@ pub const GCP_TIMEOUT_MS: u64 = 1000;
 pub const GCP_MAX_RETRIES: u32 = 3;
 pub const GCP_RECOMMENDED_CHUNK_SIZE: usize = 2036;
 
+// Payload sizes (GCP v2.2)
+pub const GCP_HARDWARE_DATA_LEN: usize = 8;
+pub const GCP_STATUS_DATA_LEN: usize = 15;
+pub const GCP_FW_VERSION_DATA_LEN: usize = 6;
+pub const GCP_DIAGNOSTICS_DATA_LEN: usize = 32;
+pub const GCP_FW_UPDATE_RESULT_LEN: usize = 4;
+// Stale ACK/NACK frames skipped while waiting for the response to a request
+pub const GCP_MAX_STALE_FRAMES: u32 = 4;
+
 // Command Definitions
 #[repr(u16)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum GcpCommand {
     // Basic Control Commands (0x00xx)
     Hello = 0x0001,
@@ -43,33 +58,51 @@ pub enum GcpCommand {
     GetFwVersion = 0x2005,
 }
 
-impl From<u16> for GcpCommand {
-    fn from(value: u16) -> Self {
+impl TryFrom<u16> for GcpCommand {
+    type Error = u16;
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
         match value {
-            0x0001 => GcpCommand::Hello,
-            0x0002 => GcpCommand::Ack,
-            0x0003 => GcpCommand::Nack,
-            0x0004 => GcpCommand::Reset,
-            0x0005 => GcpCommand::Ping,
-            0x1001 => GcpCommand::FwUpdateStart,
-            0x1002 => GcpCommand::FwUpdateData,
-            0x1003 => GcpCommand::FwUpdateEnd,
-            0x1004 => GcpCommand::FwUpdateAbort,
-            0x1005 => GcpCommand::FwUpdateRequest,
-            0x1006 => GcpCommand::FwNoUpdateAvailable,
-            0x2001 => GcpCommand::GetStatus,
-            0x2002 => GcpCommand::SetConfig,
-            0x2003 => GcpCommand::GetInfo,
-            0x2004 => GcpCommand::GetDiagnostics,
-            0x2005 => GcpCommand::GetFwVersion,
-            _ => GcpCommand::Hello, // Default fallback
+            0x0001 => Ok(GcpCommand::Hello),
+            0x0002 => Ok(GcpCommand::Ack),
+            0x0003 => Ok(GcpCommand::Nack),
+            0x0004 => Ok(GcpCommand::Reset),
+            0x0005 => Ok(GcpCommand::Ping),
+            0x1001 => Ok(GcpCommand::FwUpdateStart),
+            0x1002 => Ok(GcpCommand::FwUpdateData),
+            0x1003 => Ok(GcpCommand::FwUpdateEnd),
+            0x1004 => Ok(GcpCommand::FwUpdateAbort),
+            0x1005 => Ok(GcpCommand::FwUpdateRequest),
+            0x1006 => Ok(GcpCommand::FwNoUpdateAvailable),
+            0x2001 => Ok(GcpCommand::GetStatus),
+            0x2002 => Ok(GcpCommand::SetConfig),
+            0x2003 => Ok(GcpCommand::GetInfo),
+            0x2004 => Ok(GcpCommand::GetDiagnostics),
+            0x2005 => Ok(GcpCommand::GetFwVersion),
+            other => Err(other),
+        }
+    }
+}
+
+impl GcpCommand {
+    // Leading payload bytes that are Parameters rather than Data (§4)
+    pub fn parameter_len(self) -> usize {
+        match self {
+            GcpCommand::FwUpdateStart => 12,    // Size(4) + CRC32(4) + Chunk(2) + Reserved(2)
+            GcpCommand::FwUpdateData => 4,      // SeqNo(4)
+            GcpCommand::SetConfig => 4,         // SubCmd(2) + Reserved(2)
+            // MsgType/SeqNo echoes and version data are all Data
+            GcpCommand::Ack | GcpCommand::Nack | GcpCommand::FwUpdateRequest => 0,
+            // RESET's Type(2), or the reserved 00 00 of every other request
+            _ => 2,
         }
     }
 }
 
 // Error Codes
 #[repr(u16)]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum GcpError {
     Crc = 0x0001,
     Seq = 0x0002,
@@ -81,6 +114,159 @@ pub enum GcpError {
     Busy = 0x0008,
 }
 
+impl TryFrom<u16> for GcpError {
+    type Error = u16;
+
+    fn try_from(value: u16) -> Result<Self, Self::Error> {
+        match value {
+            0x0001 => Ok(GcpError::Crc),
+            0x0002 => Ok(GcpError::Seq),
+            0x0003 => Ok(GcpError::Size),
+            0x0004 => Ok(GcpError::Timeout),
+            0x0005 => Ok(GcpError::Mram),
+            0x0006 => Ok(GcpError::UnknownCmd),
+            0x0007 => Ok(GcpError::InvalidParam),
+            0x0008 => Ok(GcpError::Busy),
+            other => Err(other),
+        }
+    }
+}
+
+// Host-side error type, serialized across the Tauri boundary as {"kind": ..., ...}
+// so the frontend can branch on the failure instead of parsing messages.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum GcpCommError {
+    // Serial port could not be opened, configured, read or written
+    Transport { op: TransportOp, message: String },
+    // Received bytes do not form a valid GCP frame
+    Framing { reason: FramingError },
+    // No (complete) response within the timeout
+    Timeout { timeout_ms: u64 },
+    // Device rejected a request
+    Nack { msg_type: u16, seq_no: u32, error_code: u16, error: Option<GcpError> },
+    // Valid frame, but not the response the request expects
+    UnexpectedResponse { msg_type: u16, message: String },
+    // Response payload shorter than the structure it should carry
+    TruncatedPayload { payload: String, expected: usize, received: usize },
+    // Response payload could not be decoded
+    InvalidResponse { message: String },
+    // Request rejected on the host before anything was sent
+    InvalidParameter { message: String },
+    NotConnected { port_name: String },
+    File { path: String, message: String },
+    // Firmware image failed signature or target checks
+    UntrustedFirmware { reason: String },
+    Internal { message: String },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TransportOp {
+    Open,
+    Configure,
+    Read,
+    Write,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FramingError {
+    Preamble,
+    Crc16 { calculated: u16, received: u16 },
+    Length { length: u16 },
+    Truncated { expected: usize, received: usize },
+}
+
+impl GcpCommError {
+    pub fn transport(op: TransportOp, message: impl std::fmt::Display) -> Self {
+        GcpCommError::Transport { op, message: message.to_string() }
+    }
+
+    pub fn invalid_response(message: impl Into<String>) -> Self {
+        GcpCommError::InvalidResponse { message: message.into() }
+    }
+
+    pub fn invalid_parameter(message: impl Into<String>) -> Self {
+        GcpCommError::InvalidParameter { message: message.into() }
+    }
+
+    pub fn truncated(payload: &str, expected: usize, received: usize) -> Self {
+        GcpCommError::TruncatedPayload { payload: payload.to_string(), expected, received }
+    }
+
+    pub fn internal(message: impl Into<String>) -> Self {
+        GcpCommError::Internal { message: message.into() }
+    }
+
+    pub fn file(path: impl AsRef<std::path::Path>, message: impl std::fmt::Display) -> Self {
+        GcpCommError::File { path: path.as_ref().display().to_string(), message: message.to_string() }
+    }
+
+    pub fn untrusted_firmware(reason: impl Into<String>) -> Self {
+        GcpCommError::UntrustedFirmware { reason: reason.into() }
+    }
+
+    // Decode a NACK frame into the rejected MsgType, SeqNo and error code (GCP v2.2 §4.2)
+    pub fn from_nack(frame: &GcpFrame) -> Self {
+        match NackFrame::decode(frame) {
+            Ok(nack) => nack.into(),
+            Err(e) => e,
+        }
+    }
+
+    // Retrying the request may succeed; NACKs and host-side errors are final
+    pub fn is_retryable(&self) -> bool {
+        matches!(self,
+            GcpCommError::Transport { .. }
+                | GcpCommError::Framing { .. }
+                | GcpCommError::Timeout { .. }
+                | GcpCommError::UnexpectedResponse { .. })
+    }
+
+    pub fn unexpected_response(msg_type: GcpCommand, request: &str) -> Self {
+        GcpCommError::UnexpectedResponse {
+            msg_type: msg_type as u16,
+            message: format!("Unexpected response to {}: {:?}", request, msg_type),
+        }
+    }
+}
+
+impl std::fmt::Display for GcpCommError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GcpCommError::Transport { op, message } => write!(f, "Port {:?} error: {}", op, message),
+            GcpCommError::Framing { reason } => match reason {
+                FramingError::Preamble => write!(f, "Invalid preamble"),
+                FramingError::Crc16 { calculated, received } => {
+                    write!(f, "CRC mismatch: calculated={:04X}, received={:04X}", calculated, received)
+                }
+                FramingError::Length { length } => write!(f, "Invalid frame length {}", length),
+                FramingError::Truncated { expected, received } => {
+                    write!(f, "Incomplete frame: expected {} bytes, got {}", expected, received)
+                }
+            },
+            GcpCommError::Timeout { timeout_ms } => write!(f, "Timeout waiting for response ({} ms)", timeout_ms),
+            GcpCommError::Nack { msg_type, seq_no, error_code, error } => match error {
+                Some(error) => write!(f, "Device rejected 0x{:04X} (seq {}): {:?} (0x{:04X})", msg_type, seq_no, error, error_code),
+                None => write!(f, "Device rejected 0x{:04X} (seq {}): error code 0x{:04X}", msg_type, seq_no, error_code),
+            },
+            GcpCommError::UnexpectedResponse { message, .. } => write!(f, "{}", message),
+            GcpCommError::TruncatedPayload { payload, expected, received } => {
+                write!(f, "Truncated {} payload: got {} bytes, need {}", payload, received, expected)
+            }
+            GcpCommError::InvalidResponse { message } => write!(f, "Invalid response: {}", message),
+            GcpCommError::InvalidParameter { message } => write!(f, "{}", message),
+            GcpCommError::NotConnected { port_name } => write!(f, "No connection found for {}. Please connect first.", port_name),
+            GcpCommError::File { path, message } => write!(f, "{}: {}", path, message),
+            GcpCommError::UntrustedFirmware { reason } => write!(f, "Firmware image rejected: {}", reason),
+            GcpCommError::Internal { message } => write!(f, "{}", message),
+        }
+    }
+}
+
+impl std::error::Error for GcpCommError {}
+
 // Data Structures
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GcpStatusData {
@@ -92,6 +278,18 @@ pub struct GcpStatusData {
     pub rtc_time: [u8; 8],      // [year, month, day, hour, min, sec, weekday, hundredths]
 }
 
+impl GcpStatusData {
+    // gcp_status_data_t wire layout (15 bytes), the inverse of parse_status_data
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = vec![self.battery_level, self.system_state];
+        data.extend_from_slice(&self.led_color.to_le_bytes());
+        data.push(self.led_brightness);
+        data.extend_from_slice(&self.current_game_idx.to_le_bytes());
+        data.extend_from_slice(&self.rtc_time);
+        data
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GcpDiagnosticsData {
     pub step_counter: u32,      // counters.StepCounter
@@ -104,7 +302,18 @@ pub struct GcpDiagnosticsData {
     pub fram_write: u32,        // counters.FRAMWrite
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl GcpDiagnosticsData {
+    // gcp_diagnostics_data_t wire layout (32 bytes)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [self.step_counter, self.full_power_time, self.silent_time, self.charging_time,
+         self.btn_counter_l, self.btn_counter_r, self.fram_read, self.fram_write]
+            .iter()
+            .flat_map(|counter| counter.to_le_bytes())
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct GcpFwVersionData {
     pub fw_version_major: u8,    // FW_VERSION_MAJOR
     pub fw_version_minor: u8,    // FW_VERSION_MINOR  
@@ -112,6 +321,38 @@ pub struct GcpFwVersionData {
     pub fw_version_suffix: [u8; 3], // FW_VERSION_SUFFIX (3 chars)
 }
 
+impl GcpFwVersionData {
+    // gcp_fw_version_data_t wire layout (6 bytes)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = vec![self.fw_version_major, self.fw_version_minor, self.fw_version_patch];
+        data.extend_from_slice(&self.fw_version_suffix);
+        data
+    }
+
+    // Suffix as text, without the NUL padding used for suffixes shorter than 3 chars
+    pub fn suffix(&self) -> String {
+        self.fw_version_suffix
+            .iter()
+            .take_while(|&&c| c != 0)
+            .map(|&c| c as char)
+            .collect()
+    }
+
+    // Version in the "0.1.4a" form used by the spec
+    pub fn version_string(&self) -> String {
+        format!("{}.{}.{}{}", self.fw_version_major, self.fw_version_minor,
+                self.fw_version_patch, self.suffix())
+    }
+
+    // Major/minor/patch are compared numerically; on a tie the suffixes are compared
+    // as strings, so 0.1.4b is newer than 0.1.4a
+    pub fn is_newer_than(&self, other: &GcpFwVersionData) -> bool {
+        let this = (self.fw_version_major, self.fw_version_minor, self.fw_version_patch);
+        let that = (other.fw_version_major, other.fw_version_minor, other.fw_version_patch);
+        this > that || (this == that && self.suffix() > other.suffix())
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GcpHardwareData {
     pub manufacture_date: u16,   // Manufacturing date (e.g., 0x0719 = January 25, 2025)
@@ -122,6 +363,231 @@ pub struct GcpHardwareData {
     pub features: u8,            // Feature flags (bit0:USB, bit1:BLE...)
 }
 
+impl GcpHardwareData {
+    // HWVersion_t wire layout (8 bytes)
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(GCP_HARDWARE_DATA_LEN);
+        data.extend_from_slice(&self.manufacture_date.to_le_bytes());
+        data.extend_from_slice(&self.serial_number.to_le_bytes());
+        data.extend_from_slice(&[self.board_type, self.hw_revision, self.chip_model, self.features]);
+        data
+    }
+
+    // Names from the HWVersion_t tables (§4.1); None for values the spec doesn't define
+    pub fn board_type_name(&self) -> Option<&'static str> {
+        match self.board_type {
+            0x01 => Some("DEV"),
+            0x10 => Some("REV0"),
+            0x11 => Some("REV1"),
+            _ => None,
+        }
+    }
+
+    pub fn chip_model_name(&self) -> Option<&'static str> {
+        match self.chip_model {
+            0x40 => Some("Apollo4Lite"),
+            0x41 => Some("Apollo4Plus"),
+            _ => None,
+        }
+    }
+
+    // Defined feature flags that are set; reserved bits are left out
+    pub fn feature_names(&self) -> Vec<&'static str> {
+        [(0x01, "NATIVE_USB"), (0x02, "BLE"), (0x04, "EXT_MRAM_A"), (0x08, "EXT_MRAM_B")]
+            .into_iter()
+            .filter(|(flag, _)| self.features & flag != 0)
+            .map(|(_, name)| name)
+            .collect()
+    }
+}
+
+// Protocol revision the device firmware implements. Only v2.2 is auto-assumed;
+// older payload layouts must be selected per connection.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GcpSpecRevision {
+    #[serde(rename = "2.0")]
+    V2_0,
+    #[serde(rename = "2.1")]
+    V2_1,
+    #[default]
+    #[serde(rename = "2.2")]
+    V2_2,
+}
+
+impl GcpSpecRevision {
+    // v2.0 documented 21 bytes of status data; the first 15 match gcp_status_data_t
+    pub fn status_data_len(self) -> usize {
+        match self {
+            GcpSpecRevision::V2_0 => 21,
+            GcpSpecRevision::V2_1 | GcpSpecRevision::V2_2 => GCP_STATUS_DATA_LEN,
+        }
+    }
+
+    // Before v2.2, HELLO answered with status data instead of HWVersion_t
+    pub fn hello_returns_status(self) -> bool {
+        self != GcpSpecRevision::V2_2
+    }
+}
+
+// HELLO response, serialized as the bare hardware or status structure
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum GcpHelloResponse {
+    Hardware(GcpHardwareData),
+    Status(GcpStatusData),
+}
+
+// Response to a single request, as handled by `GcpUartHandler::exchange`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", content = "data", rename_all = "snake_case")]
+pub enum GcpResponse {
+    None,                        // FW_UPDATE_ABORT and FW_NO_UPDATE_AVAILABLE (§4.7) are not answered
+    Ack,
+    Hello(GcpHelloResponse),
+    Status(GcpStatusData),
+    FwVersion(GcpFwVersionData),
+    Diagnostics(GcpDiagnosticsData),
+    FwUpdateEnd { verified: bool },
+}
+
+// RTC time as written by SET_CONFIG(TIME): [year, month, day, hour, min, sec, weekday]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GcpRtcTime {
+    pub year: u8,      // Years since 2000 (0-99), same encoding as GcpStatusData::rtc_time
+    pub month: u8,     // 1-12
+    pub day: u8,       // 1-31 (checked against the month length)
+    pub hour: u8,      // 0-23
+    pub minute: u8,    // 0-59
+    pub second: u8,    // 0-59
+    pub weekday: u8,   // 0-6, Monday = 0
+}
+
+impl GcpRtcTime {
+    pub fn from_datetime<Tz: chrono::TimeZone>(time: &chrono::DateTime<Tz>) -> Result<Self, GcpCommError> {
+        use chrono::{Datelike, Timelike};
+
+        let year = time.year() - 2000;
+        if !(0..=99).contains(&year) {
+            return Err(GcpCommError::invalid_parameter(format!("Year {} cannot be represented by the device RTC (2000-2099)", time.year())));
+        }
+
+        Ok(Self {
+            year: year as u8,
+            month: time.month() as u8,
+            day: time.day() as u8,
+            hour: time.hour() as u8,
+            minute: time.minute() as u8,
+            // Leap seconds are reported as second 59 with an extra nanosecond count
+            second: time.second().min(59) as u8,
+            weekday: time.weekday().num_days_from_monday() as u8,
+        })
+    }
+
+    // Current host wall-clock time in the local timezone
+    pub fn now_local() -> Result<Self, GcpCommError> {
+        Self::from_datetime(&chrono::Local::now())
+    }
+
+    pub fn validate(&self) -> Result<(), GcpCommError> {
+        if self.year > 99 {
+            return Err(GcpCommError::invalid_parameter(format!("Invalid RTC year {} (expected 0-99, years since 2000)", self.year)));
+        }
+        if !(1..=12).contains(&self.month) {
+            return Err(GcpCommError::invalid_parameter(format!("Invalid RTC month {} (expected 1-12)", self.month)));
+        }
+        let days_in_month = match self.month {
+            2 if (2000 + self.year as u32) % 4 == 0 => 29, // 2000-2099: every 4th year is a leap year
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        };
+        if self.day < 1 || self.day > days_in_month {
+            return Err(GcpCommError::invalid_parameter(format!("Invalid RTC day {} for month {} (expected 1-{})", self.day, self.month, days_in_month)));
+        }
+        if self.hour > 23 {
+            return Err(GcpCommError::invalid_parameter(format!("Invalid RTC hour {} (expected 0-23)", self.hour)));
+        }
+        if self.minute > 59 {
+            return Err(GcpCommError::invalid_parameter(format!("Invalid RTC minute {} (expected 0-59)", self.minute)));
+        }
+        if self.second > 59 {
+            return Err(GcpCommError::invalid_parameter(format!("Invalid RTC second {} (expected 0-59)", self.second)));
+        }
+        if self.weekday > 6 {
+            return Err(GcpCommError::invalid_parameter(format!("Invalid RTC weekday {} (expected 0-6)", self.weekday)));
+        }
+        Ok(())
+    }
+
+    pub fn to_bytes(self) -> [u8; 7] {
+        [self.year, self.month, self.day, self.hour, self.minute, self.second, self.weekday]
+    }
+}
+
+// SET_CONFIG sub-commands (GCP v2.2 §4.12)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum GcpConfig {
+    Time(GcpRtcTime),   // SubCmd 0x0001, 7 bytes
+    Brightness(u8),     // SubCmd 0x0002, 1 byte, 0-100%
+    Sound(bool),        // SubCmd 0x0003, 1 byte, 0=OFF 1=ON
+}
+
+impl GcpConfig {
+    pub fn sub_cmd(&self) -> u16 {
+        match self {
+            GcpConfig::Time(_) => 0x0001,
+            GcpConfig::Brightness(_) => 0x0002,
+            GcpConfig::Sound(_) => 0x0003,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), GcpCommError> {
+        match self {
+            GcpConfig::Time(time) => time.validate(),
+            GcpConfig::Brightness(level) if *level > 100 => {
+                Err(GcpCommError::invalid_parameter(format!("Invalid brightness {} (expected 0-100)", level)))
+            }
+            GcpConfig::Brightness(_) | GcpConfig::Sound(_) => Ok(()),
+        }
+    }
+
+    pub fn config_data(&self) -> Vec<u8> {
+        match self {
+            GcpConfig::Time(time) => time.to_bytes().to_vec(),
+            GcpConfig::Brightness(level) => vec![*level],
+            GcpConfig::Sound(enabled) => vec![*enabled as u8],
+        }
+    }
+
+    // Inverse of sub_cmd() and config_data(); the data must be exactly the sub-command's size
+    pub fn from_bytes(sub_cmd: u16, data: &[u8]) -> Result<Self, GcpCommError> {
+        match sub_cmd {
+            0x0001 => {
+                let data = exact_len("SET_CONFIG TIME", data, 7)?;
+                Ok(GcpConfig::Time(GcpRtcTime {
+                    year: data[0], month: data[1], day: data[2],
+                    hour: data[3], minute: data[4], second: data[5], weekday: data[6],
+                }))
+            }
+            0x0002 => Ok(GcpConfig::Brightness(exact_len("SET_CONFIG BRIGHTNESS", data, 1)?[0])),
+            0x0003 => match exact_len("SET_CONFIG SOUND", data, 1)?[0] {
+                0 => Ok(GcpConfig::Sound(false)),
+                1 => Ok(GcpConfig::Sound(true)),
+                other => Err(GcpCommError::invalid_response(format!("Invalid SET_CONFIG SOUND value {} (expected 0 or 1)", other))),
+            },
+            other => Err(GcpCommError::invalid_response(format!("Unknown SET_CONFIG sub-command 0x{:04X}", other))),
+        }
+    }
+
+    // Build the SET_CONFIG frame: SubCmd(2) + Reserved(2) + ConfigData
+    pub fn to_frame(self) -> GcpFrame {
+        let mut parameters = Vec::new();
+        parameters.extend_from_slice(&self.sub_cmd().to_le_bytes());
+        parameters.extend_from_slice(&[0u8, 0u8]);
+        GcpFrame::with_data(GcpCommand::SetConfig, parameters, self.config_data())
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct GcpFrame {
     pub length: u16,
@@ -189,77 +655,342 @@ impl GcpFrame {
         frame
     }
 
-    pub fn deserialize(data: &[u8]) -> Result<Self, String> {
-        if data.len() < 10 {
-            return Err("Frame too short".to_string());
-        }
+    pub fn deserialize(data: &[u8]) -> Result<Self, GcpCommError> {
+        let (length, raw_type, payload) = unframe(data)?;
+        let msg_type = GcpCommand::try_from(raw_type).map_err(|raw_type| GcpCommError::UnexpectedResponse {
+            msg_type: raw_type,
+            message: format!("Unknown message type 0x{:04X}", raw_type),
+        })?;
 
-        // Check preamble
-        if data[0] != GCP_PREAMBLE[0] || data[1] != GCP_PREAMBLE[1] {
-            return Err("Invalid preamble".to_string());
-        }
+        // Each command has its own Parameters size; whatever follows is Data
+        let (parameters, data) = payload.split_at(msg_type.parameter_len().min(payload.len()));
 
-        // Extract length
-        let length = u16::from_le_bytes([data[2], data[3]]);
-        
-        // Verify frame length
-        if data.len() < (length + 4) as usize { // +4 for preamble + CRC
-            return Err("Incomplete frame".to_string());
+        Ok(Self {
+            length,
+            msg_type,
+            parameters: parameters.to_vec(),
+            data: data.to_vec(),
+        })
+    }
+
+    // Everything after MsgType: Parameters followed by Data
+    pub fn payload(&self) -> Vec<u8> {
+        [self.parameters.as_slice(), self.data.as_slice()].concat()
+    }
+}
+
+// SeqNo carried by FW_UPDATE_DATA, its ACK, and NACKs, for protocol logging
+fn frame_seq_no(frame: &GcpFrame) -> Option<u32> {
+    match frame.msg_type {
+        GcpCommand::FwUpdateData => frame.parameters.get(..4).map(|seq| u32::from_le_bytes([seq[0], seq[1], seq[2], seq[3]])),
+        GcpCommand::Ack => AckFrame::decode(frame)
+            .ok()
+            .filter(|ack| ack.acknowledges(GcpCommand::FwUpdateData))
+            .map(|ack| ack.seq_no),
+        GcpCommand::Nack => NackFrame::decode(frame).ok().map(|nack| nack.seq_no),
+        _ => None,
+    }
+}
+
+// Decoded GCP_MSG_ACK (GCP v2.2 §4.2): MsgType(2) + SeqNo(4) + response data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AckFrame {
+    pub msg_type: u16,
+    pub seq_no: u32,
+    pub data: Vec<u8>,
+}
+
+impl AckFrame {
+    pub fn decode(frame: &GcpFrame) -> Result<Self, GcpCommError> {
+        let payload = ack_payload(frame, GcpCommand::Ack, 6)?;
+        Ok(Self {
+            msg_type: u16::from_le_bytes([payload[0], payload[1]]),
+            seq_no: u32::from_le_bytes([payload[2], payload[3], payload[4], payload[5]]),
+            data: payload[6..].to_vec(),
+        })
+    }
+
+    // GET_STATUS and HELLO responses echo the MsgType but carry no SeqNo (§4.1, §4.9)
+    pub fn decode_without_seq(frame: &GcpFrame) -> Result<Self, GcpCommError> {
+        let payload = ack_payload(frame, GcpCommand::Ack, 2)?;
+        Ok(Self {
+            msg_type: u16::from_le_bytes([payload[0], payload[1]]),
+            seq_no: 0,
+            data: payload[2..].to_vec(),
+        })
+    }
+
+    pub fn acknowledges(&self, request: GcpCommand) -> bool {
+        self.msg_type == request as u16
+    }
+}
+
+// Decoded GCP_MSG_NACK (GCP v2.2 §4.2): MsgType(2) + SeqNo(4) + Error(2)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NackFrame {
+    pub msg_type: u16,
+    pub seq_no: u32,
+    pub error_code: u16,
+}
+
+impl NackFrame {
+    pub fn decode(frame: &GcpFrame) -> Result<Self, GcpCommError> {
+        let payload = ack_payload(frame, GcpCommand::Nack, 8)?;
+        Ok(Self {
+            msg_type: u16::from_le_bytes([payload[0], payload[1]]),
+            seq_no: u32::from_le_bytes([payload[2], payload[3], payload[4], payload[5]]),
+            error_code: u16::from_le_bytes([payload[6], payload[7]]),
+        })
+    }
+
+    pub fn error(&self) -> Option<GcpError> {
+        GcpError::try_from(self.error_code).ok()
+    }
+
+    pub fn rejects(&self, request: GcpCommand) -> bool {
+        self.msg_type == request as u16
+    }
+}
+
+impl From<NackFrame> for GcpCommError {
+    fn from(nack: NackFrame) -> Self {
+        GcpCommError::Nack {
+            msg_type: nack.msg_type,
+            seq_no: nack.seq_no,
+            error: nack.error(),
+            error_code: nack.error_code,
         }
+    }
+}
+
+fn ack_payload(frame: &GcpFrame, expected: GcpCommand, min_len: usize) -> Result<Vec<u8>, GcpCommError> {
+    if frame.msg_type != expected {
+        return Err(GcpCommError::UnexpectedResponse {
+            msg_type: frame.msg_type as u16,
+            message: format!("Expected {:?}, got {:?}", expected, frame.msg_type),
+        });
+    }
+
+    let payload = frame.payload();
+    if payload.len() < min_len {
+        return Err(GcpCommError::invalid_response(format!(
+            "{:?} payload too short (got {} bytes, need {})", expected, payload.len(), min_len)));
+    }
+    Ok(payload)
+}
+
+// Check a received frame's preamble, length and CRC16, returning Length, the raw
+// MsgType and the payload after it
+fn unframe(data: &[u8]) -> Result<(u16, u16, &[u8]), GcpCommError> {
+    if data.len() < 10 {
+        return Err(GcpCommError::Framing {
+            reason: FramingError::Truncated { expected: 10, received: data.len() },
+        });
+    }
+
+    if data[0] != GCP_PREAMBLE[0] || data[1] != GCP_PREAMBLE[1] {
+        return Err(GcpCommError::Framing { reason: FramingError::Preamble });
+    }
+
+    // Length covers at least its own field and the message type
+    let length = u16::from_le_bytes([data[2], data[3]]);
+    if length < 4 {
+        return Err(GcpCommError::Framing { reason: FramingError::Length { length } });
+    }
+
+    // +4 for preamble + CRC
+    let frame_len = length as usize + 4;
+    if data.len() < frame_len {
+        return Err(GcpCommError::Framing {
+            reason: FramingError::Truncated { expected: frame_len, received: data.len() },
+        });
+    }
+
+    // The CRC follows the Length bytes counted from the Length field (§1), not the end of `data`
+    let calculated_crc = gcp_crc16(&data[2..frame_len - 2]);
+    let received_crc = u16::from_le_bytes([data[frame_len - 2], data[frame_len - 1]]);
+    if calculated_crc != received_crc {
+        return Err(GcpCommError::Framing {
+            reason: FramingError::Crc16 { calculated: calculated_crc, received: received_crc },
+        });
+    }
+
+    Ok((length, u16::from_le_bytes([data[4], data[5]]), &data[6..frame_len - 2]))
+}
+
+// A GCP message with its payload decoded per command (§4), in either direction.
+// Payloads must match the command's layout exactly, reserved bytes included, so
+// decoding a frame and encoding the message again gives back the same bytes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GcpMessage {
+    // Host -> device
+    Hello,
+    Reset { reset_type: u16 },
+    Ping,
+    FwUpdateStart { size: u32, crc32: u32, chunk_size: u16 },
+    FwUpdateData { offset: u32, data: Vec<u8> },
+    FwUpdateEnd,
+    FwUpdateAbort,
+    FwNoUpdateAvailable,
+    GetStatus,
+    SetConfig(GcpConfig),
+    GetInfo,
+    GetDiagnostics,
+    GetFwVersion,
+
+    // Device -> host. An ACK's layout depends on the request it answers (§4.1, §4.2,
+    // §4.9) and the frame does not say which, so its payload is kept for AckFrame.
+    Ack(Vec<u8>),
+    Nack(NackFrame),
+    FwUpdateRequest(GcpFwVersionData),
+
+    // A MsgType outside the spec, kept as received
+    Unknown(u16, Vec<u8>),
+}
 
-        // Extract message type
-        let msg_type = GcpCommand::from(u16::from_le_bytes([data[4], data[5]]));
+impl GcpMessage {
+    // Decode a whole frame as received, preamble and CRC16 included
+    pub fn decode(data: &[u8]) -> Result<Self, GcpCommError> {
+        let (_, msg_type, payload) = unframe(data)?;
+        Self::from_payload(msg_type, payload)
+    }
 
-        // Calculate expected CRC
-        let crc_data = &data[2..(2 + length) as usize];
-        let calculated_crc = gcp_crc16(crc_data);
-        // CRC is at the end of the frame (last 2 bytes)
-        let crc_pos = data.len() - 2;
-        let received_crc = u16::from_le_bytes([data[crc_pos], data[crc_pos + 1]]);
+    pub fn from_frame(frame: &GcpFrame) -> Result<Self, GcpCommError> {
+        Self::from_payload(frame.msg_type as u16, &frame.payload())
+    }
 
-        if calculated_crc != received_crc {
-            return Err(format!("CRC mismatch: calculated={:04X}, received={:04X}", calculated_crc, received_crc));
+    pub fn from_payload(msg_type: u16, payload: &[u8]) -> Result<Self, GcpCommError> {
+        let Ok(command) = GcpCommand::try_from(msg_type) else {
+            return Ok(GcpMessage::Unknown(msg_type, payload.to_vec()));
+        };
+        let name = format!("{:?}", command);
+        let u32_at = |data: &[u8], offset: usize| {
+            u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+        };
+        let reserved = |data: &[u8]| {
+            if data.iter().any(|&byte| byte != 0) {
+                return Err(GcpCommError::invalid_response(format!("{} reserved bytes must be 0, got {:02X?}", name, data)));
+            }
+            Ok(())
+        };
+        // Requests without parameters carry only the reserved 00 00
+        let bare = |message: GcpMessage| reserved(exact_len(&name, payload, 2)?).map(|()| message);
+
+        match command {
+            GcpCommand::Hello => bare(GcpMessage::Hello),
+            GcpCommand::Ping => bare(GcpMessage::Ping),
+            GcpCommand::FwUpdateEnd => bare(GcpMessage::FwUpdateEnd),
+            GcpCommand::FwUpdateAbort => bare(GcpMessage::FwUpdateAbort),
+            GcpCommand::FwNoUpdateAvailable => bare(GcpMessage::FwNoUpdateAvailable),
+            GcpCommand::GetStatus => bare(GcpMessage::GetStatus),
+            GcpCommand::GetInfo => bare(GcpMessage::GetInfo),
+            GcpCommand::GetDiagnostics => bare(GcpMessage::GetDiagnostics),
+            GcpCommand::GetFwVersion => bare(GcpMessage::GetFwVersion),
+            GcpCommand::Reset => {
+                let data = exact_len(&name, payload, 2)?;
+                Ok(GcpMessage::Reset { reset_type: u16::from_le_bytes([data[0], data[1]]) })
+            }
+            GcpCommand::FwUpdateStart => {
+                let data = exact_len(&name, payload, 12)?;
+                reserved(&data[10..12])?;
+                Ok(GcpMessage::FwUpdateStart {
+                    size: u32_at(data, 0),
+                    crc32: u32_at(data, 4),
+                    chunk_size: u16::from_le_bytes([data[8], data[9]]),
+                })
+            }
+            GcpCommand::FwUpdateData => {
+                let offset = u32_at(require_len(&name, payload, 4)?, 0);
+                Ok(GcpMessage::FwUpdateData { offset, data: payload[4..].to_vec() })
+            }
+            GcpCommand::SetConfig => {
+                let data = require_len(&name, payload, 4)?;
+                reserved(&data[2..4])?;
+                let config = GcpConfig::from_bytes(u16::from_le_bytes([data[0], data[1]]), &payload[4..])?;
+                Ok(GcpMessage::SetConfig(config))
+            }
+            GcpCommand::Ack => Ok(GcpMessage::Ack(payload.to_vec())),
+            GcpCommand::Nack => {
+                let data = exact_len(&name, payload, 8)?;
+                Ok(GcpMessage::Nack(NackFrame {
+                    msg_type: u16::from_le_bytes([data[0], data[1]]),
+                    seq_no: u32_at(data, 2),
+                    error_code: u16::from_le_bytes([data[6], data[7]]),
+                }))
+            }
+            GcpCommand::FwUpdateRequest => {
+                let data = exact_len(&name, payload, GCP_FW_VERSION_DATA_LEN)?;
+                Ok(GcpMessage::FwUpdateRequest(parse_fw_version_data(data)?))
+            }
         }
+    }
 
-        // Extract parameters and data based on message type
-        let param_data_len = length as usize - 4; // Subtract length(2) + msg_type(2)
-        
-        let mut parameters = Vec::new();
-        let mut data_payload = Vec::new();
-        
-        if param_data_len > 0 {
-            let payload_start = 6; // After length + msg_type
-            let payload_end = payload_start + param_data_len;
-            
-            // For ACK responses with status data, treat everything as data
-            match msg_type {
-                GcpCommand::Ack => {
-                    // For ACK frames, the payload structure is: MsgType(2) + SeqNo(4) + Data
-                    // All payload goes to data field for easier processing
-                    data_payload = data[payload_start..payload_end].to_vec();
-                }
-                _ => {
-                    // For other commands, use the original logic
-                    if param_data_len >= 2 {
-                        // First 2 bytes are parameters (or reserved)
-                        parameters = data[payload_start..payload_start + 2].to_vec();
-                        if param_data_len > 2 {
-                            data_payload = data[payload_start + 2..payload_end].to_vec();
-                        }
-                    } else {
-                        // All payload is data
-                        data_payload = data[payload_start..payload_end].to_vec();
-                    }
-                }
+    pub fn msg_type(&self) -> u16 {
+        let command = match self {
+            GcpMessage::Hello => GcpCommand::Hello,
+            GcpMessage::Reset { .. } => GcpCommand::Reset,
+            GcpMessage::Ping => GcpCommand::Ping,
+            GcpMessage::FwUpdateStart { .. } => GcpCommand::FwUpdateStart,
+            GcpMessage::FwUpdateData { .. } => GcpCommand::FwUpdateData,
+            GcpMessage::FwUpdateEnd => GcpCommand::FwUpdateEnd,
+            GcpMessage::FwUpdateAbort => GcpCommand::FwUpdateAbort,
+            GcpMessage::FwNoUpdateAvailable => GcpCommand::FwNoUpdateAvailable,
+            GcpMessage::GetStatus => GcpCommand::GetStatus,
+            GcpMessage::SetConfig(_) => GcpCommand::SetConfig,
+            GcpMessage::GetInfo => GcpCommand::GetInfo,
+            GcpMessage::GetDiagnostics => GcpCommand::GetDiagnostics,
+            GcpMessage::GetFwVersion => GcpCommand::GetFwVersion,
+            GcpMessage::Ack(_) => GcpCommand::Ack,
+            GcpMessage::Nack(_) => GcpCommand::Nack,
+            GcpMessage::FwUpdateRequest(_) => GcpCommand::FwUpdateRequest,
+            GcpMessage::Unknown(msg_type, _) => return *msg_type,
+        };
+        command as u16
+    }
+
+    // None for an Unknown MsgType
+    pub fn command(&self) -> Option<GcpCommand> {
+        GcpCommand::try_from(self.msg_type()).ok()
+    }
+
+    // Everything after MsgType, the inverse of from_payload
+    pub fn payload(&self) -> Vec<u8> {
+        match self {
+            GcpMessage::Hello
+            | GcpMessage::Ping
+            | GcpMessage::FwUpdateEnd
+            | GcpMessage::FwUpdateAbort
+            | GcpMessage::FwNoUpdateAvailable
+            | GcpMessage::GetStatus
+            | GcpMessage::GetInfo
+            | GcpMessage::GetDiagnostics
+            | GcpMessage::GetFwVersion => vec![0, 0],
+            GcpMessage::Reset { reset_type } => reset_type.to_le_bytes().to_vec(),
+            GcpMessage::FwUpdateStart { size, crc32, chunk_size } => {
+                [&size.to_le_bytes()[..], &crc32.to_le_bytes(), &chunk_size.to_le_bytes(), &[0, 0]].concat()
             }
+            GcpMessage::FwUpdateData { offset, data } => [&offset.to_le_bytes()[..], data].concat(),
+            GcpMessage::SetConfig(config) => config.to_frame().payload(),
+            GcpMessage::Nack(nack) => {
+                [&nack.msg_type.to_le_bytes()[..], &nack.seq_no.to_le_bytes(), &nack.error_code.to_le_bytes()].concat()
+            }
+            GcpMessage::FwUpdateRequest(version) => version.to_bytes(),
+            GcpMessage::Ack(payload) | GcpMessage::Unknown(_, payload) => payload.clone(),
         }
+    }
 
-        Ok(Self {
-            length,
-            msg_type,
-            parameters,
-            data: data_payload,
-        })
+    // The whole frame as sent on the wire
+    pub fn encode(&self) -> Vec<u8> {
+        let payload = self.payload();
+        let length = (4 + payload.len()) as u16;
+
+        let mut frame = GCP_PREAMBLE.to_vec();
+        frame.extend_from_slice(&length.to_le_bytes());
+        frame.extend_from_slice(&self.msg_type().to_le_bytes());
+        frame.extend_from_slice(&payload);
+        let crc = gcp_crc16(&frame[2..]);
+        frame.extend_from_slice(&crc.to_le_bytes());
+        frame
     }
 }
 
@@ -284,8 +1015,12 @@ pub fn gcp_crc16(data: &[u8]) -> u16 {
 
 // CRC-32 Implementation for firmware verification
 pub fn gcp_crc32(data: &[u8]) -> u32 {
-    let mut crc: u32 = 0xFFFFFFFF;
-    
+    gcp_crc32_update(0xFFFFFFFF, data) ^ 0xFFFFFFFF
+}
+
+// Fold `data` into a running CRC-32 register (start at 0xFFFFFFFF, invert at the end),
+// the way the MCU checks FW_UPDATE_DATA chunks as they arrive
+pub fn gcp_crc32_update(mut crc: u32, data: &[u8]) -> u32 {
     for &byte in data {
         crc ^= byte as u32;
         
@@ -297,12 +1032,13 @@ pub fn gcp_crc32(data: &[u8]) -> u32 {
             }
         }
     }
-    
-    crc ^ 0xFFFFFFFF
+
+    crc
 }
 
-// Connection State
-#[derive(Debug, Clone, PartialEq, Eq)]
+// Connection State, serialized as {"state": "error", "message": ...}
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "state", content = "message", rename_all = "snake_case")]
 pub enum ConnectionState {
     Disconnected,
     Connected,
@@ -314,204 +1050,413 @@ type ConnectionMap = Arc<Mutex<HashMap<String, Arc<Mutex<GcpUartHandler>>>>>;
 
 lazy_static::lazy_static! {
     static ref CONNECTION_POOL: ConnectionMap = Arc::new(Mutex::new(HashMap::new()));
+    // Health reported by the connection monitor; pooled ports without an entry are Connected
+    static ref CONNECTION_STATES: Mutex<HashMap<String, ConnectionState>> = Mutex::new(HashMap::new());
 }
 
-// UART Communication Handler
+// GCP Communication Handler (runs over any transport, see transport.rs)
 pub struct GcpUartHandler {
-    port: Box<dyn serialport::SerialPort>,
+    port: Box<dyn GcpTransport>,
+    port_name: String,           // Empty for transports opened outside the pool
+    decoder: GcpDecoder,
+    spec_revision: GcpSpecRevision,
+    last_sent: Instant,
+    last_received: Instant,
+    hardware: Option<GcpHardwareData>,
+    link: LinkConfig,
+    capture: Option<ProtocolCapture>,
+    unsolicited: VecDeque<DecodedFrame>, // Device requests received while a command waited
 }
 
 impl GcpUartHandler {
-    pub fn new(port_name: &str) -> Result<Self, String> {
-        let port = serialport::new(port_name, GCP_UART_BAUD)
-            .timeout(Duration::from_millis(GCP_TIMEOUT_MS))
-            .data_bits(serialport::DataBits::Eight)
-            .flow_control(serialport::FlowControl::Hardware)
-            .parity(serialport::Parity::None)
-            .stop_bits(serialport::StopBits::One)
-            .open()
-            .map_err(|e| format!("Failed to open port {}: {}", port_name, e))?;
+    // `port_name` is a serial port or `tcp://host:port`, opened with its saved link profile
+    pub fn new(port_name: &str) -> Result<Self, GcpCommError> {
+        Self::open(port_name, link_config::profile_for(port_name))
+    }
+
+    pub fn open(port_name: &str, link: LinkConfig) -> Result<Self, GcpCommError> {
+        link.validate()?;
+        let port = open_transport(port_name, &link)?;
+        Ok(Self { port_name: port_name.to_string(), link, ..Self::with_transport(port) })
+    }
+
+    pub fn with_transport(port: Box<dyn GcpTransport>) -> Self {
+        Self {
+            port,
+            port_name: String::new(),
+            decoder: GcpDecoder::default(),
+            spec_revision: GcpSpecRevision::default(),
+            last_sent: Instant::now(),
+            last_received: Instant::now(),
+            hardware: None,
+            link: LinkConfig::default(),
+            capture: None,
+            unsolicited: VecDeque::new(),
+        }
+    }
+
+    pub fn link_config(&self) -> &LinkConfig {
+        &self.link
+    }
+
+    pub fn spec_revision(&self) -> GcpSpecRevision {
+        self.spec_revision
+    }
 
-        Ok(Self { port })
+    // Parse responses using an older protocol revision's payload layout
+    pub fn set_spec_revision(&mut self, spec_revision: GcpSpecRevision) {
+        self.spec_revision = spec_revision;
+    }
+
+    // HWVersion from the last HELLO answered with hardware data
+    pub fn hardware(&self) -> Option<GcpHardwareData> {
+        self.hardware.clone()
+    }
+
+    // Check for a frame the device sent on its own (e.g. FW_UPDATE_REQUEST), including
+    // ones with a MsgType outside the spec. Returns immediately with None when nothing
+    // is waiting in the RX buffer.
+    pub fn poll_unsolicited(&mut self) -> Result<Option<DecodedFrame>, GcpCommError> {
+        if self.unsolicited.is_empty() {
+            if let Some(result) = self.next_frame() {
+                self.unsolicited.push_back(DecodedFrame::Frame(result?));
+            }
+        }
+        if self.unsolicited.is_empty() {
+            let pending = self.port.bytes_to_read()
+                .map_err(|e| GcpCommError::transport(TransportOp::Read, format!("Failed to query RX buffer: {}", e)))?;
+            if pending > 0 {
+                let frame = self.receive_frame()?;
+                self.unsolicited.push_back(DecodedFrame::Frame(frame));
+            }
+        }
+        Ok(self.unsolicited.pop_front())
+    }
+
+    // Time since the device last sent a frame
+    pub fn idle_time(&self) -> Duration {
+        self.last_received.elapsed()
+    }
+
+    // Query the port without sending anything; fails once the device or link is gone
+    pub fn check_link(&mut self) -> Result<(), GcpCommError> {
+        self.port.bytes_to_read()
+            .map(|_| ())
+            .map_err(|e| GcpCommError::transport(TransportOp::Read, format!("Port is no longer usable: {}", e)))
     }
 
-    // Test connection health  
     pub fn is_connected(&mut self) -> bool {
-        // Don't send ping during active operations to avoid buffer corruption
-        // Just check if port is still open
-        true
+        self.check_link().is_ok()
+    }
+
+    // Record every TX and RX chunk to a capture file until `stop_capture`
+    pub fn start_capture(&mut self, path: &std::path::Path) -> Result<(), GcpCommError> {
+        if self.capture.is_some() {
+            return Err(GcpCommError::invalid_parameter(format!("{} is already being captured", self.port_name)));
+        }
+        let header = ProtocolCapture::header(&self.port_name, &self.link, self.spec_revision);
+        self.capture = Some(ProtocolCapture::create(path, &header)?);
+        ProtocolEvent::new(LogLevel::Info, TARGET_LINK, &self.port_name).log(format!("Capturing to {}", path.display()));
+        Ok(())
+    }
+
+    pub fn stop_capture(&mut self) -> Option<CaptureSummary> {
+        let summary = self.capture.take()?.finish();
+        ProtocolEvent::new(LogLevel::Info, TARGET_LINK, &self.port_name)
+            .log(format!("Capture {} finished: {} records", summary.path, summary.records));
+        Some(summary)
+    }
+
+    // A capture that can't be written is stopped; the connection itself carries on
+    fn capture(&mut self, record: impl FnOnce(&mut ProtocolCapture) -> std::io::Result<()>) {
+        let Some(capture) = &mut self.capture else {
+            return;
+        };
+        if let Err(e) = record(capture) {
+            ProtocolEvent::new(LogLevel::Warn, TARGET_LINK, &self.port_name).log(format!("Capture stopped: {}", e));
+            self.capture = None;
+        }
+    }
+
+    // Keep-alive (§2.1). An unsolicited frame that arrives ahead of the ACK is queued
+    // for `poll_unsolicited`.
+    pub fn ping(&mut self) -> Result<(), GcpCommError> {
+        self.send_frame_simple(&GcpFrame::new(GcpCommand::Ping))?;
+        self.receive_ack(GcpCommand::Ping, None, self.link.timeout_ms).map(|_| ())
+    }
+
+    // Send an already built request once and handle its response the way the command
+    // it carries does, without retries. Used to replay captured traffic.
+    pub fn exchange(&mut self, request: &GcpFrame) -> Result<GcpResponse, GcpCommError> {
+        self.send_frame(request)?;
+        let timeout_ms = self.link.timeout_ms;
+
+        match request.msg_type {
+            GcpCommand::FwUpdateAbort | GcpCommand::FwNoUpdateAvailable => Ok(GcpResponse::None),
+            GcpCommand::Hello => {
+                let hello = self.receive_hello_response(timeout_ms)?;
+                if let GcpHelloResponse::Hardware(hardware) = &hello {
+                    self.hardware = Some(hardware.clone());
+                }
+                Ok(GcpResponse::Hello(hello))
+            }
+            GcpCommand::GetStatus => {
+                let ack = self.receive_ack_with(GcpCommand::GetStatus, None, timeout_ms, AckFrame::decode_without_seq)?;
+                let status_data = require_len("GET_STATUS", &ack.data, self.spec_revision.status_data_len())?;
+                Ok(GcpResponse::Status(parse_status_data(status_data)?))
+            }
+            GcpCommand::GetFwVersion => {
+                let ack = self.receive_ack(GcpCommand::GetFwVersion, None, timeout_ms)?;
+                Ok(GcpResponse::FwVersion(parse_fw_version_data(&ack.data)?))
+            }
+            GcpCommand::GetDiagnostics => {
+                let ack = self.receive_ack(GcpCommand::GetDiagnostics, None, timeout_ms)?;
+                Ok(GcpResponse::Diagnostics(parse_diagnostics_data(&ack.data)?))
+            }
+            GcpCommand::FwUpdateEnd => {
+                let ack = self.receive_ack(GcpCommand::FwUpdateEnd, None, timeout_ms)?;
+                let verified = parse_fw_update_result(&ack.data)? == 0x00000000;
+                Ok(GcpResponse::FwUpdateEnd { verified })
+            }
+            msg_type => self.receive_ack(msg_type, frame_seq_no(request), timeout_ms).map(|_| GcpResponse::Ack),
+        }
     }
 }
 
 // Connection Pool Management Functions
-pub fn connect_to_port(port_name: String) -> Result<String, String> {
-    let mut pool = CONNECTION_POOL.lock()
-        .map_err(|_| "Failed to lock connection pool".to_string())?;
-    
+pub fn connect_to_port(port_name: String, link: LinkConfig) -> Result<String, GcpCommError> {
     // Check if connection already exists
+    if get_connection_handle(&port_name).is_some() {
+        return Ok(format!("Already connected to {}", port_name));
+    }
+
+    // Open the port without holding the pool, so many ports can be opened at once
+    let handler = GcpUartHandler::open(&port_name, link)?;
+
+    let mut pool = CONNECTION_POOL.lock()
+        .map_err(|_| GcpCommError::internal("Failed to lock connection pool"))?;
     if pool.contains_key(&port_name) {
         return Ok(format!("Already connected to {}", port_name));
     }
+    pool.insert(port_name.clone(), Arc::new(Mutex::new(handler)));
 
-    // Create new connection
-    let handler = GcpUartHandler::new(&port_name)?;
-    let handler_arc = Arc::new(Mutex::new(handler));
-    
-    pool.insert(port_name.clone(), handler_arc);
-    
+    ProtocolEvent::new(LogLevel::Info, TARGET_LINK, &port_name).log("Connected");
     Ok(format!("Connected to {}", port_name))
 }
 
-pub fn disconnect_from_port(port_name: String) -> Result<String, String> {
+// Pool a connection over an already open transport (memory pipes, simulators)
+pub fn connect_with_transport(port_name: String, transport: Box<dyn GcpTransport>) -> Result<String, GcpCommError> {
     let mut pool = CONNECTION_POOL.lock()
-        .map_err(|_| "Failed to lock connection pool".to_string())?;
-    
-    match pool.remove(&port_name) {
-        Some(_) => Ok(format!("Disconnected from {}", port_name)),
-        None => Err(format!("No connection found for {}", port_name)),
+        .map_err(|_| GcpCommError::internal("Failed to lock connection pool"))?;
+
+    if pool.contains_key(&port_name) {
+        return Ok(format!("Already connected to {}", port_name));
     }
+
+    let handler = GcpUartHandler { port_name: port_name.clone(), ..GcpUartHandler::with_transport(transport) };
+    pool.insert(port_name.clone(), Arc::new(Mutex::new(handler)));
+
+    ProtocolEvent::new(LogLevel::Info, TARGET_LINK, &port_name).log("Connected");
+    Ok(format!("Connected to {}", port_name))
 }
 
-pub fn get_connection_status(port_name: String) -> Result<ConnectionState, String> {
-    let pool = CONNECTION_POOL.lock()
-        .map_err(|_| "Failed to lock connection pool".to_string())?;
+pub fn disconnect_from_port(port_name: String) -> Result<String, GcpCommError> {
+    let mut pool = CONNECTION_POOL.lock()
+        .map_err(|_| GcpCommError::internal("Failed to lock connection pool"))?;
     
-    match pool.get(&port_name) {
-        Some(handler_arc) => {
-            match handler_arc.lock() {
-                Ok(mut handler) => {
-                    if handler.is_connected() {
-                        Ok(ConnectionState::Connected)
-                    } else {
-                        Ok(ConnectionState::Error("Connection lost".to_string()))
-                    }
-                }
-                Err(_) => Ok(ConnectionState::Error("Handler lock failed".to_string())),
-            }
+    match pool.remove(&port_name) {
+        Some(_) => {
+            set_connection_state(&port_name, ConnectionState::Disconnected);
+            ProtocolEvent::new(LogLevel::Info, TARGET_LINK, &port_name).log("Disconnected");
+            Ok(format!("Disconnected from {}", port_name))
         }
-        None => Ok(ConnectionState::Disconnected),
+        None => Err(GcpCommError::NotConnected { port_name }),
+    }
+}
+
+// Put a reopened connection in place of a dead one, possibly under a new port name
+// (a rebooted USB device can enumerate as a different port)
+pub fn replace_connection(old_port_name: &str, port_name: String, handler: GcpUartHandler) -> Result<(), GcpCommError> {
+    let mut pool = CONNECTION_POOL.lock()
+        .map_err(|_| GcpCommError::internal("Failed to lock connection pool"))?;
+
+    if !pool.contains_key(old_port_name) {
+        return Err(GcpCommError::NotConnected { port_name: old_port_name.to_string() });
+    }
+    if port_name != old_port_name && pool.contains_key(&port_name) {
+        return Err(GcpCommError::invalid_parameter(format!("{} is already connected", port_name)));
+    }
+
+    pool.remove(old_port_name);
+    if port_name != old_port_name {
+        set_connection_state(old_port_name, ConnectionState::Disconnected);
+    }
+    ProtocolEvent::new(LogLevel::Info, TARGET_LINK, &port_name).log(format!("Reconnected (was {})", old_port_name));
+    pool.insert(port_name, Arc::new(Mutex::new(handler)));
+    Ok(())
+}
+
+pub fn get_connection_status(port_name: String) -> Result<ConnectionState, GcpCommError> {
+    let Some(handler_arc) = get_connection_handle(&port_name) else {
+        return Ok(ConnectionState::Disconnected);
+    };
+
+    let reported = CONNECTION_STATES.lock()
+        .map_err(|_| GcpCommError::internal("Failed to lock connection states"))?
+        .get(&port_name)
+        .cloned();
+    if let Some(state @ ConnectionState::Error(_)) = reported {
+        return Ok(state);
     }
+
+    // A command in progress owns the port; don't wait for it to finish
+    let state = match handler_arc.try_lock() {
+        Ok(mut handler) => match handler.check_link() {
+            Ok(()) => ConnectionState::Connected,
+            Err(e) => ConnectionState::Error(e.to_string()),
+        },
+        Err(std::sync::TryLockError::WouldBlock) => ConnectionState::Connected,
+        Err(std::sync::TryLockError::Poisoned(_)) => ConnectionState::Error("Handler lock failed".to_string()),
+    };
+    Ok(state)
+}
+
+// Record a connection's health as last observed, returning the previous state.
+// Disconnected clears the record.
+pub fn set_connection_state(port_name: &str, state: ConnectionState) -> Option<ConnectionState> {
+    let mut states = CONNECTION_STATES.lock().ok()?;
+    match state {
+        ConnectionState::Disconnected => states.remove(port_name),
+        state => states.insert(port_name.to_string(), state),
+    }
+}
+
+// Shared handle to a pooled connection, for background tasks that must not
+// hold the pool lock while they wait for the handler
+pub fn get_connection_handle(port_name: &str) -> Option<Arc<Mutex<GcpUartHandler>>> {
+    CONNECTION_POOL.lock().ok()?.get(port_name).cloned()
 }
 
-pub fn execute_with_connection<F, T>(port_name: &str, operation: F) -> Result<T, String>
+pub fn execute_with_connection<F, T>(port_name: &str, operation: F) -> Result<T, GcpCommError>
 where
-    F: FnOnce(&mut GcpUartHandler) -> Result<T, String>,
+    F: FnOnce(&mut GcpUartHandler) -> Result<T, GcpCommError>,
 {
-    let pool = CONNECTION_POOL.lock()
-        .map_err(|_| "Failed to lock connection pool".to_string())?;
-    
-    match pool.get(port_name) {
-        Some(handler_arc) => {
-            match handler_arc.lock() {
-                Ok(mut handler) => operation(&mut *handler),
-                Err(_) => Err("Failed to lock handler".to_string()),
-            }
-        }
-        None => Err(format!("No connection found for {}. Please connect first.", port_name)),
-    }
+    // Release the pool before waiting on the handler, so a long operation on one
+    // port does not block connect/disconnect or commands on other ports
+    let handler_arc = {
+        let pool = CONNECTION_POOL.lock()
+            .map_err(|_| GcpCommError::internal("Failed to lock connection pool"))?;
+        pool.get(port_name)
+            .cloned()
+            .ok_or_else(|| GcpCommError::NotConnected { port_name: port_name.to_string() })?
+    };
+
+    let mut handler = handler_arc.lock()
+        .map_err(|_| GcpCommError::internal("Failed to lock handler"))?;
+    operation(&mut handler)
 }
 
 impl GcpUartHandler {
-    pub fn send_frame_simple(&mut self, frame: &GcpFrame) -> Result<(), String> {
+    pub fn send_frame_simple(&mut self, frame: &GcpFrame) -> Result<(), GcpCommError> {
         let data = frame.serialize();
-        
-        println!("TX Simple Frame ({} bytes): {:02X?}", data.len(), data);
-        
-        // Simple transmission without aggressive buffer clearing for firmware operations
-        let _ = self.port.flush();
-        std::thread::sleep(std::time::Duration::from_millis(50));
-        
-        self.port.write_all(&data)
-            .map_err(|e| format!("Failed to send frame: {}", e))?;
+
+        // Simple transmission without buffer clearing for firmware operations
+        self.write_frame(frame, &data)
+    }
+
+    fn write_frame(&mut self, frame: &GcpFrame, data: &[u8]) -> Result<(), GcpCommError> {
+        self.inter_frame_delay();
+        self.port.write_all(data)
+            .map_err(|e| GcpCommError::transport(TransportOp::Write, format!("Failed to send frame: {}", e)))?;
         self.port.flush()
-            .map_err(|e| format!("Failed to flush port: {}", e))?;
-        
-        std::thread::sleep(std::time::Duration::from_millis(100));
-        println!("TX Simple Frame sent successfully, {} bytes transmitted", data.len());
+            .map_err(|e| GcpCommError::transport(TransportOp::Write, format!("Failed to flush port: {}", e)))?;
+        self.last_sent = Instant::now();
+        self.capture(|capture| capture.record_tx(data));
+
+        ProtocolEvent::new(LogLevel::Debug, TARGET_TX, &self.port_name)
+            .msg_type(frame.msg_type as u16)
+            .seq(frame_seq_no(frame))
+            .len(data.len())
+            .log(format!("TX {:?}", frame.msg_type));
+        if protocol_log::enabled(LogLevel::Trace, TARGET_TX) {
+            ProtocolEvent::new(LogLevel::Trace, TARGET_TX, &self.port_name)
+                .len(data.len())
+                .log(format!("TX {:02X?}", data));
+        }
         Ok(())
     }
 
-    pub fn send_frame(&mut self, frame: &GcpFrame) -> Result<(), String> {
+    // Only adapters configured with a delay need one; responses are waited for, not timed
+    fn inter_frame_delay(&self) {
+        if self.link.inter_frame_delay_ms > 0 {
+            std::thread::sleep(Duration::from_millis(self.link.inter_frame_delay_ms));
+        }
+    }
+
+    pub fn send_frame(&mut self, frame: &GcpFrame) -> Result<(), GcpCommError> {
         let data = frame.serialize();
         
         // Validate frame before sending
         // Total frame = Preamble(2) + Length content + CRC(2) = Length + 4  
         if data.len() != (frame.length + 4) as usize { 
-            return Err(format!("Frame size mismatch: data_len={}, expected={}", 
-                             data.len(), frame.length + 4));
+            return Err(GcpCommError::internal(format!("Frame size mismatch: data_len={}, expected={}", 
+                             data.len(), frame.length + 4)));
         }
         
-        // Debug logging for frame transmission
-        println!("TX Frame ({} bytes): {:02X?}", data.len(), data);
-        println!("TX Frame Details: Type={:?}, Length={}, Params={} bytes, Data={} bytes", 
-               frame.msg_type, frame.length, frame.parameters.len(), frame.data.len());
-        
-        // Validate frame structure
-        println!("TX Frame Structure Validation:");
-        println!("  Preamble: {:02X?} (should be [AA, 55])", &data[0..2]);
-        println!("  Length: {:02X?} (should be [{:02X}, 00])", &data[2..4], frame.length as u8);
-        println!("  MsgType: {:02X?}", &data[4..6]);
-        println!("  Expected total size: {} bytes", frame.length + 4);
-        println!("  Actual total size: {} bytes", data.len());
-        
-        // Calculate and log CRC
-        let crc_data = &data[2..(2 + frame.length) as usize]; // Skip preamble for CRC calc
-        let calculated_crc = gcp_crc16(crc_data);
-        let frame_crc = u16::from_le_bytes([data[data.len()-2], data[data.len()-1]]);
-        println!("TX CRC: calculated=0x{:04X}, in_frame=0x{:04X}, match={}", 
-               calculated_crc, frame_crc, calculated_crc == frame_crc);
-        
-        // Aggressive buffer management to prevent frame contamination
+        // Drop stale responses so the new one isn't mistaken for an earlier one, but keep
+        // requests the device sent on its own. The receive side waits for the response
+        // itself, so no settle delays are needed.
         let _ = self.port.flush();
-        
-        // Multiple rounds of buffer clearing with increasing delays
-        for round in 1..=3 {
-            let mut discard_buffer = [0u8; 1024];
-            let mut total_discarded = 0;
-            
-            // Clear buffer aggressively
-            self.port.set_timeout(Duration::from_millis(10)).ok();
-            while let Ok(bytes_read) = self.port.read(&mut discard_buffer) {
-                if bytes_read == 0 {
-                    break;
-                }
-                total_discarded += bytes_read;
-            }
-            
-            if total_discarded > 0 {
-                println!("Round {}: Discarded {} stale bytes from RX buffer", round, total_discarded);
-            }
-            
-            // Exponential delay between clearing rounds
-            std::thread::sleep(std::time::Duration::from_millis(20 * round));
+        let discarded = self.drain_input(frame.msg_type);
+        if discarded > 0 {
+            ProtocolEvent::new(LogLevel::Debug, TARGET_LINK, &self.port_name)
+                .len(discarded)
+                .log(format!("Discarded {} stale bytes before {:?}", discarded, frame.msg_type));
         }
-        
-        // Reset timeout for normal operation
-        self.port.set_timeout(Duration::from_millis(GCP_TIMEOUT_MS)).ok();
-        
-        // Long delay before transmission to ensure clean channel
-        std::thread::sleep(std::time::Duration::from_millis(100));
-        
-        self.port.write_all(&data)
-            .map_err(|e| format!("Failed to send frame: {}", e))?;
-        self.port.flush()
-            .map_err(|e| format!("Failed to flush port: {}", e))?;
-        
-        // Extended delay after transmission for device processing
-        std::thread::sleep(std::time::Duration::from_millis(75));
-        
-        println!("TX Frame sent successfully, {} bytes transmitted", data.len());
-        Ok(())
+
+        self.write_frame(frame, &data)
+    }
+
+    // Read whatever is already waiting, queue unsolicited frames and drop the rest.
+    // Returns the number of bytes dropped.
+    fn drain_input(&mut self, request: GcpCommand) -> usize {
+        let mut buffer = [0u8; 4096];
+        while let Ok(pending) = self.port.bytes_to_read() {
+            if pending == 0 {
+                break;
+            }
+            let len = pending.min(buffer.len());
+            match self.port.read(&mut buffer[..len]) {
+                Ok(0) | Err(_) => break,
+                Ok(bytes_read) => {
+                    self.capture(|capture| capture.record_rx(&buffer[..bytes_read]));
+                    self.decoder.push(&buffer[..bytes_read]);
+                }
+            }
+        }
+
+        let mut discarded = 0;
+        while let Some(result) = self.next_frame() {
+            match result {
+                Ok(frame) if !matches!(frame.msg_type, GcpCommand::Ack | GcpCommand::Nack) => {
+                    self.queue_unsolicited(DecodedFrame::Frame(frame), request);
+                }
+                Ok(frame) => discarded += frame.length as usize + 4,
+                Err(_) => {}
+            }
+        }
+        let partial = self.decoder.buffered();
+        self.decoder.clear();
+        discarded + partial
     }
 
-    pub fn start_firmware_update(&mut self, fw_data: &[u8], chunk_size: u16) -> Result<(), String> {
+    pub fn start_firmware_update(&mut self, fw_data: &[u8], chunk_size: u16) -> Result<(), GcpCommError> {
         let fw_size = fw_data.len() as u32;
         let fw_crc32 = gcp_crc32(fw_data);
-        
-        println!("===== FIRMWARE UPDATE START DEBUG =====");
-        println!("FW Size: {} bytes", fw_size);
-        println!("FW CRC32: 0x{:08X}", fw_crc32);
-        println!("Chunk Size: {} bytes", chunk_size);
+
+        ProtocolEvent::new(LogLevel::Info, TARGET_FW, &self.port_name)
+            .len(fw_data.len())
+            .log(format!("FW_UPDATE_START: {} bytes, CRC32 0x{:08X}, {}-byte chunks", fw_size, fw_crc32, chunk_size));
 
         // Create FW_UPDATE_START frame - following GCP v2.2 spec exactly
         let mut parameters = Vec::new();
@@ -520,134 +1465,78 @@ impl GcpUartHandler {
         parameters.extend_from_slice(&chunk_size.to_le_bytes());     // Chunk size (2 bytes)
         parameters.extend_from_slice(&[0u8, 0u8]);                   // Reserved (2 bytes)
 
-        println!("Parameters ({} bytes): {:02X?}", parameters.len(), parameters);
-        println!("Corrected frame structure (20 bytes total):");
-        println!("  Preamble: AA 55");
-        println!("  Length: 10 00 (16 bytes)");
-        println!("  MsgType: 01 10");
-        println!("  Size: {:02X?}", &fw_size.to_le_bytes());
-        println!("  CRC32: {:02X?}", &fw_crc32.to_le_bytes());
-        println!("  Chunk: {:02X?}", &chunk_size.to_le_bytes());
-        println!("  Reserved: 00 00");
-        println!("  CRC16: [calculated]");
-
         // Create frame with correct length calculation
         let start_frame = GcpFrame::with_parameters(GcpCommand::FwUpdateStart, parameters);
-        println!("Constructed frame length: {} (correct per Length field definition)", start_frame.length);
-        
+
         // Verify frame structure is consistent
         if start_frame.length != 16 {
-            return Err(format!("Frame length error: calculated={}, should be 16", start_frame.length));
+            return Err(GcpCommError::internal(format!("Frame length error: calculated={}, should be 16", start_frame.length)));
         }
 
-        // Wait for device to be completely ready
-        std::thread::sleep(std::time::Duration::from_millis(1000));
-
-        for attempt in 1..=GCP_MAX_RETRIES {
-            println!("=== Attempt {} ===", attempt);
-            
+        for attempt in 1..=self.link.max_retries {
             // Use simplified transmission method to avoid buffer clearing corruption
             match self.send_frame_simple(&start_frame) {
                 Ok(()) => {
-                    println!("Frame sent successfully, waiting for response...");
-                    match self.receive_frame_with_timeout(5000) { // Extended timeout for FW operations
-                        Ok(response) => {
-                            println!("Response received: {:?}", response.msg_type);
-                            if response.msg_type == GcpCommand::Ack {
-                                println!("Firmware update start acknowledged");
-                                return Ok(());
-                            } else if response.msg_type == GcpCommand::Nack {
-                                let error_msg = if response.data.len() >= 2 {
-                                    let error_code = u16::from_le_bytes([response.data[0], response.data[1]]);
-                                    format!("Device rejected firmware update start: error code 0x{:04X}", error_code)
-                                } else {
-                                    "Device rejected firmware update start".to_string()
-                                };
-                                return Err(error_msg);
-                            } else {
-                                return Err(format!("Unexpected response to firmware update start: {:?}", response.msg_type));
-                            }
+                    match self.receive_ack(GcpCommand::FwUpdateStart, None, self.link.timeout_ms) {
+                        Ok(_) => {
+                            ProtocolEvent::new(LogLevel::Info, TARGET_FW, &self.port_name)
+                                .elapsed(self.last_sent.elapsed())
+                                .log("FW_UPDATE_START acknowledged");
+                            return Ok(());
                         }
+                        Err(e) if !e.is_retryable() => return Err(e),
                         Err(e) => {
-                            println!("Failed to receive response: {}", e);
-                            if attempt == GCP_MAX_RETRIES {
-                                return Err(format!("Failed to receive response to firmware update start after {} attempts: {}", GCP_MAX_RETRIES, e));
+                            ProtocolEvent::new(LogLevel::Warn, TARGET_FW, &self.port_name)
+                                .log(format!("FW_UPDATE_START attempt {} failed: {}", attempt, e));
+                            if attempt == self.link.max_retries {
+                                return Err(e);
                             }
-                            // Longer delay between retry attempts for complete device recovery
-                            std::thread::sleep(std::time::Duration::from_millis(1000));
                         }
                     }
                 }
                 Err(e) => {
-                    println!("Failed to send frame: {}", e);
-                    if attempt == GCP_MAX_RETRIES {
-                        return Err(format!("Failed to send firmware update start after {} attempts: {}", GCP_MAX_RETRIES, e));
+                    ProtocolEvent::new(LogLevel::Warn, TARGET_FW, &self.port_name)
+                        .log(format!("FW_UPDATE_START attempt {} not sent: {}", attempt, e));
+                    if attempt == self.link.max_retries {
+                        return Err(e);
                     }
-                    std::thread::sleep(std::time::Duration::from_millis(500));
                 }
             }
         }
 
-        Err("Firmware update start failed".to_string())
+        Err(GcpCommError::internal("Firmware update start failed"))
     }
 
-    pub fn send_firmware_chunk(&mut self, chunk_data: &[u8], seq_no: u32) -> Result<(), String> {
-        // Create FW_UPDATE_DATA frame
-        let mut parameters = Vec::new();
-        parameters.extend_from_slice(&seq_no.to_le_bytes());  // Sequence number (4 bytes)
-
-        let data_frame = GcpFrame::with_data(GcpCommand::FwUpdateData, parameters, chunk_data.to_vec());
-
-        for attempt in 1..=GCP_MAX_RETRIES {
-            match self.send_frame(&data_frame) {
-                Ok(()) => {
-                    match self.receive_frame() {
-                        Ok(response) => {
-                            if response.msg_type == GcpCommand::Ack {
-                                // Verify ACK contains correct sequence number
-                                let all_data = [response.parameters.as_slice(), response.data.as_slice()].concat();
-                                if all_data.len() >= 10 {  // MsgType(2) + SeqNo(4) + minimal payload
-                                    let ack_seq = u32::from_le_bytes([all_data[2], all_data[3], all_data[4], all_data[5]]);
-                                    if ack_seq == seq_no {
-                                        return Ok(());
-                                    } else {
-                                        return Err(format!("Sequence number mismatch: sent {}, acked {}", seq_no, ack_seq));
-                                    }
-                                } else {
-                                    // Simple ACK without sequence check - assume success
-                                    return Ok(());
-                                }
-                            } else if response.msg_type == GcpCommand::Nack {
-                                let error_msg = if response.data.len() >= 2 {
-                                    let error_code = u16::from_le_bytes([response.data[0], response.data[1]]);
-                                    format!("Device rejected chunk {}: error code 0x{:04X}", seq_no, error_code)
-                                } else {
-                                    format!("Device rejected chunk {}", seq_no)
-                                };
-                                return Err(error_msg);
-                            } else {
-                                return Err(format!("Unexpected response to firmware chunk {}: {:?}", seq_no, response.msg_type));
-                            }
-                        }
-                        Err(e) => {
-                            if attempt == GCP_MAX_RETRIES {
-                                return Err(format!("Failed to receive response to chunk {} after {} attempts: {}", seq_no, GCP_MAX_RETRIES, e));
-                            }
-                        }
-                    }
-                }
+    pub fn send_firmware_chunk(&mut self, chunk_data: &[u8], seq_no: u32) -> Result<(), GcpCommError> {
+        for attempt in 1..=self.link.max_retries {
+            match self.send_firmware_chunk_once(chunk_data, seq_no) {
+                Ok(()) => return Ok(()),
+                Err(e) if !e.is_retryable() => return Err(e),
                 Err(e) => {
-                    if attempt == GCP_MAX_RETRIES {
-                        return Err(format!("Failed to send chunk {} after {} attempts: {}", seq_no, GCP_MAX_RETRIES, e));
+                    if attempt == self.link.max_retries {
+                        return Err(e);
                     }
                 }
             }
         }
 
-        Err(format!("Firmware chunk {} send failed", seq_no))
+        Err(GcpCommError::internal(format!("Firmware chunk {} send failed", seq_no)))
+    }
+
+    // One FW_UPDATE_DATA and its ACK, no retries; the caller paces the transfer
+    pub fn send_firmware_chunk_once(&mut self, chunk_data: &[u8], seq_no: u32) -> Result<(), GcpCommError> {
+        // Create FW_UPDATE_DATA frame
+        let mut parameters = Vec::new();
+        parameters.extend_from_slice(&seq_no.to_le_bytes());  // Sequence number (4 bytes)
+
+        let data_frame = GcpFrame::with_data(GcpCommand::FwUpdateData, parameters, chunk_data.to_vec());
+        self.send_frame(&data_frame)?;
+
+        // Only an ACK echoing this chunk's SeqNo confirms it was written
+        self.receive_ack(GcpCommand::FwUpdateData, Some(seq_no), self.link.timeout_ms).map(|_| ())
     }
 
-    pub fn send_firmware_chunk_single_try(&mut self, chunk_data: &[u8], seq_no: u32) -> Result<(), String> {
+    pub fn send_firmware_chunk_single_try(&mut self, chunk_data: &[u8], seq_no: u32) -> Result<(), GcpCommError> {
         // Create FW_UPDATE_DATA frame
         let mut parameters = Vec::new();
         parameters.extend_from_slice(&seq_no.to_le_bytes());  // Sequence number (4 bytes)
@@ -655,277 +1544,264 @@ impl GcpUartHandler {
         let data_frame = GcpFrame::with_data(GcpCommand::FwUpdateData, parameters, chunk_data.to_vec());
 
         // Single attempt only for robustness testing
-        println!("Robustness test: Sending {}-byte packet (seq: {})", chunk_data.len(), seq_no);
-        
-        match self.send_frame_simple(&data_frame) {
-            Ok(()) => {
-                println!("Robustness test: Frame sent successfully, waiting for response...");
-                match self.receive_frame_with_timeout(2000) {
-                    Ok(response) => {
-                        println!("Robustness test: Response received: {:?}", response.msg_type);
-                        if response.msg_type == GcpCommand::Ack {
-                            println!("Robustness test: Packet acknowledged");
-                            return Ok(());
-                        } else if response.msg_type == GcpCommand::Nack {
-                            let error_msg = if response.data.len() >= 2 {
-                                let error_code = u16::from_le_bytes([response.data[0], response.data[1]]);
-                                format!("Device rejected robustness test packet: error code 0x{:04X}", error_code)
-                            } else {
-                                "Device rejected robustness test packet".to_string()
-                            };
-                            return Err(error_msg);
-                        } else {
-                            return Err(format!("Unexpected response to robustness test: {:?}", response.msg_type));
-                        }
-                    }
-                    Err(e) => {
-                        return Err(format!("Robustness test receive failed: {}", e));
-                    }
-                }
-            }
-            Err(e) => {
-                return Err(format!("Robustness test send failed: {}", e));
-            }
-        }
+        self.send_frame_simple(&data_frame)?;
+        let result = self.receive_ack(GcpCommand::FwUpdateData, Some(seq_no), self.link.timeout_ms).map(|_| ());
+
+        let (level, outcome) = match &result {
+            Ok(()) => (LogLevel::Info, "acknowledged".to_string()),
+            Err(e) => (LogLevel::Warn, e.to_string()),
+        };
+        ProtocolEvent::new(level, TARGET_FW, &self.port_name)
+            .msg_type(GcpCommand::FwUpdateData as u16)
+            .seq(Some(seq_no))
+            .len(chunk_data.len())
+            .log(format!("Robustness test packet: {}", outcome));
+        result
     }
 
-    pub fn end_firmware_update(&mut self) -> Result<bool, String> {
+    pub fn end_firmware_update(&mut self) -> Result<bool, GcpCommError> {
         let end_frame = GcpFrame::new(GcpCommand::FwUpdateEnd);
 
-        for attempt in 1..=GCP_MAX_RETRIES {
+        for attempt in 1..=self.link.max_retries {
             match self.send_frame(&end_frame) {
                 Ok(()) => {
-                    match self.receive_frame() {
-                        Ok(response) => {
-                            if response.msg_type == GcpCommand::Ack {
-                                // Parse verification result
-                                let all_data = [response.parameters.as_slice(), response.data.as_slice()].concat();
-                                if all_data.len() >= 10 {  // MsgType(2) + SeqNo(4) + Result(4)
-                                    let result = u32::from_le_bytes([all_data[6], all_data[7], all_data[8], all_data[9]]);
-                                    if result == 0x00000000 {
-                                        println!("Firmware update verification successful");
-                                        return Ok(true);
-                                    } else {
-                                        println!("Firmware update verification failed: result=0x{:08X}", result);
-                                        return Ok(false);
-                                    }
-                                } else {
-                                    // Simple ACK - assume success
-                                    return Ok(true);
-                                }
-                            } else if response.msg_type == GcpCommand::Nack {
-                                let error_msg = if response.data.len() >= 2 {
-                                    let error_code = u16::from_le_bytes([response.data[0], response.data[1]]);
-                                    format!("Device rejected firmware update end: error code 0x{:04X}", error_code)
-                                } else {
-                                    "Device rejected firmware update end".to_string()
-                                };
-                                return Err(error_msg);
+                    match self.receive_ack(GcpCommand::FwUpdateEnd, None, self.link.timeout_ms) {
+                        Ok(ack) => {
+                            // Parse verification result: MsgType(2) + SeqNo(4) + Result(4)
+                            let result = parse_fw_update_result(&ack.data)?;
+                            if result == 0x00000000 {
+                                ProtocolEvent::new(LogLevel::Info, TARGET_FW, &self.port_name)
+                                    .crc_ok(true)
+                                    .log("FW_UPDATE_END: image verified");
+                                return Ok(true);
                             } else {
-                                return Err(format!("Unexpected response to firmware update end: {:?}", response.msg_type));
+                                ProtocolEvent::new(LogLevel::Warn, TARGET_FW, &self.port_name)
+                                    .crc_ok(false)
+                                    .log(format!("FW_UPDATE_END: verification failed, result=0x{:08X}", result));
+                                return Ok(false);
                             }
                         }
+                        Err(e) if !e.is_retryable() => return Err(e),
                         Err(e) => {
-                            if attempt == GCP_MAX_RETRIES {
-                                return Err(format!("Failed to receive response to firmware update end after {} attempts: {}", GCP_MAX_RETRIES, e));
+                            if attempt == self.link.max_retries {
+                                return Err(e);
                             }
                         }
                     }
                 }
                 Err(e) => {
-                    if attempt == GCP_MAX_RETRIES {
-                        return Err(format!("Failed to send firmware update end after {} attempts: {}", GCP_MAX_RETRIES, e));
+                    if attempt == self.link.max_retries {
+                        return Err(e);
                     }
                 }
             }
         }
 
-        Err("Firmware update end failed".to_string())
+        Err(GcpCommError::internal("Firmware update end failed"))
     }
 
-    pub fn abort_firmware_update(&mut self) -> Result<(), String> {
+    pub fn abort_firmware_update(&mut self) -> Result<(), GcpCommError> {
         let abort_frame = GcpFrame::new(GcpCommand::FwUpdateAbort);
 
         match self.send_frame(&abort_frame) {
             Ok(()) => {
                 // Don't wait for response, just send abort
-                println!("Firmware update abort sent");
+                ProtocolEvent::new(LogLevel::Info, TARGET_FW, &self.port_name).log("FW_UPDATE_ABORT sent");
                 Ok(())
             }
-            Err(e) => Err(format!("Failed to send firmware update abort: {}", e))
+            Err(e) => Err(e)
         }
     }
 
-    pub fn reset_device(&mut self, reset_type: u16) -> Result<(), String> {
+    pub fn send_no_update_available(&mut self) -> Result<(), GcpCommError> {
+        let no_update_frame = GcpFrame::new(GcpCommand::FwNoUpdateAvailable);
+
+        // The device exits firmware update mode on receipt, no response is defined
+        self.send_frame(&no_update_frame)
+    }
+
+    pub fn reset_device(&mut self, reset_type: u16) -> Result<(), GcpCommError> {
         let parameters = reset_type.to_le_bytes().to_vec();
         let reset_frame = GcpFrame::with_parameters(GcpCommand::Reset, parameters);
 
-        match self.send_frame(&reset_frame) {
-            Ok(()) => {
-                // Wait briefly for ACK, but don't fail if device reboots immediately
-                match self.receive_frame() {
-                    Ok(response) => {
-                        if response.msg_type == GcpCommand::Ack {
-                            println!("Reset acknowledged, device will reboot");
-                        }
+        self.send_frame(&reset_frame)?;
+
+        // Wait briefly for ACK, but don't fail if device reboots immediately
+        match self.receive_ack(GcpCommand::Reset, None, self.link.timeout_ms) {
+            Ok(_) => ProtocolEvent::new(LogLevel::Info, TARGET_FW, &self.port_name)
+                .log(format!("RESET 0x{:04X} acknowledged, device will reboot", reset_type)),
+            Err(e @ GcpCommError::Nack { .. }) => return Err(e),
+            Err(_) => {
+                // Device may have rebooted immediately
+                ProtocolEvent::new(LogLevel::Info, TARGET_FW, &self.port_name)
+                    .log(format!("RESET 0x{:04X} sent, device may have rebooted immediately", reset_type));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn receive_frame(&mut self) -> Result<GcpFrame, GcpCommError> {
+        self.receive_frame_with_timeout(self.link.timeout_ms)
+    }
+
+    // Wait for the ACK to `request` (and `seq_no`, if given), skipping stale ACK/NACK
+    // frames answering earlier requests. A matching NACK becomes GcpCommError::Nack.
+    pub fn receive_ack(&mut self, request: GcpCommand, seq_no: Option<u32>, timeout_ms: u64) -> Result<AckFrame, GcpCommError> {
+        self.receive_ack_with(request, seq_no, timeout_ms, AckFrame::decode)
+    }
+
+    fn receive_ack_with(
+        &mut self,
+        request: GcpCommand,
+        seq_no: Option<u32>,
+        timeout_ms: u64,
+        decode: fn(&GcpFrame) -> Result<AckFrame, GcpCommError>,
+    ) -> Result<AckFrame, GcpCommError> {
+        for _ in 0..=GCP_MAX_STALE_FRAMES {
+            let response = self.receive_frame_with_timeout(timeout_ms)?;
+            match response.msg_type {
+                GcpCommand::Ack => {
+                    let ack = decode(&response)?;
+                    if !ack.acknowledges(request) {
+                        self.log_stale(format!("Discarding stale ACK for 0x{:04X} while waiting for {:?}", ack.msg_type, request));
+                    } else if seq_no.is_some_and(|seq_no| seq_no != ack.seq_no) {
+                        self.log_stale(format!("Discarding stale ACK for {:?} seq {} (expected {:?})", request, ack.seq_no, seq_no));
+                    } else {
+                        return Ok(ack);
                     }
-                    Err(_) => {
-                        // Device may have rebooted immediately
-                        println!("Reset sent, device may have rebooted immediately");
+                }
+                GcpCommand::Nack => {
+                    let nack = NackFrame::decode(&response)?;
+                    if nack.rejects(request) {
+                        return Err(nack.into());
                     }
+                    self.log_stale(format!("Discarding stale NACK for 0x{:04X} while waiting for {:?}", nack.msg_type, request));
                 }
-                Ok(())
+                _ => self.queue_unsolicited(DecodedFrame::Frame(response), request),
             }
-            Err(e) => Err(format!("Failed to send reset command: {}", e))
         }
-    }
 
-    pub fn receive_frame(&mut self) -> Result<GcpFrame, String> {
-        self.receive_frame_with_timeout(GCP_TIMEOUT_MS)
+        Err(GcpCommError::UnexpectedResponse {
+            msg_type: request as u16,
+            message: format!("No matching response to {:?} after {} stale frames", request, GCP_MAX_STALE_FRAMES + 1),
+        })
     }
 
-    pub fn receive_frame_with_timeout(&mut self, timeout_ms: u64) -> Result<GcpFrame, String> {
-        // Set custom timeout for this operation
-        self.port.set_timeout(Duration::from_millis(timeout_ms))
-            .map_err(|e| format!("Failed to set timeout: {}", e))?;
+    pub fn receive_frame_with_timeout(&mut self, timeout_ms: u64) -> Result<GcpFrame, GcpCommError> {
+        // A frame that arrived together with an earlier one is already buffered
+        if let Some(result) = self.next_frame() {
+            self.log_received(&result);
+            return result;
+        }
 
+        let deadline = Instant::now() + Duration::from_millis(timeout_ms);
         let mut buffer = [0u8; 4096];
-        let mut frame_buffer = Vec::new();
-        let mut found_preamble = false;
-        let mut expected_length = 0u16;
+        let result = loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                break Err(GcpCommError::Timeout { timeout_ms });
+            }
+            if let Err(e) = self.port.set_timeout(remaining) {
+                break Err(GcpCommError::transport(TransportOp::Configure, format!("Failed to set timeout: {}", e)));
+            }
 
-        // Read until we have a complete frame
-        loop {
             match self.port.read(&mut buffer) {
+                Ok(0) => break Err(GcpCommError::transport(TransportOp::Read, "No data received")),
                 Ok(bytes_read) => {
-                    if bytes_read == 0 {
-                        return Err("No data received".to_string());
+                    self.capture(|capture| capture.record_rx(&buffer[..bytes_read]));
+                    if protocol_log::enabled(LogLevel::Trace, TARGET_RX) {
+                        ProtocolEvent::new(LogLevel::Trace, TARGET_RX, &self.port_name)
+                            .len(bytes_read)
+                            .log(format!("RX {:02X?}", &buffer[..bytes_read]));
+                    }
+                    self.decoder.push(&buffer[..bytes_read]);
+                    if let Some(result) = self.next_frame() {
+                        break result;
                     }
+                }
+                Err(ref e) if e.kind() == std::io::ErrorKind::TimedOut => break Err(GcpCommError::Timeout { timeout_ms }),
+                Err(e) => break Err(GcpCommError::transport(TransportOp::Read, format!("Failed to read from port: {}", e))),
+            }
+        };
 
-                    // Log raw received data
-                    println!("RX Raw ({} bytes): {:02X?}", bytes_read, &buffer[..bytes_read]);
-                    frame_buffer.extend_from_slice(&buffer[..bytes_read]);
+        // Restore the default timeout before returning
+        let _ = self.port.set_timeout(Duration::from_millis(self.link.timeout_ms));
+        self.log_received(&result);
+        result
+    }
 
-                    // Look for preamble if we haven't found it yet
-                    if !found_preamble {
-                        if let Some(pos) = find_preamble(&frame_buffer) {
-                            if pos > 0 {
-                                println!("RX: Skipped {} bytes to find preamble", pos);
-                            }
-                            frame_buffer = frame_buffer[pos..].to_vec();
-                            found_preamble = true;
-                            println!("RX: Found preamble, buffer now: {:02X?}", frame_buffer);
-                        } else {
-                            // Keep looking, but don't let buffer grow too large
-                            if frame_buffer.len() > 1000 {
-                                println!("RX: Buffer too large, clearing");
-                                frame_buffer.clear();
-                            }
-                            continue;
-                        }
-                    }
+    fn log_received(&mut self, result: &Result<GcpFrame, GcpCommError>) {
+        let elapsed = self.last_sent.elapsed();
+        match result {
+            Ok(frame) => {
+                self.last_received = Instant::now();
+                ProtocolEvent::new(LogLevel::Debug, TARGET_RX, &self.port_name)
+                    .elapsed(elapsed)
+                    .msg_type(frame.msg_type as u16)
+                    .seq(frame_seq_no(frame))
+                    .len(frame.length as usize + 4)
+                    .crc_ok(true)
+                    .log(format!("RX {:?}", frame.msg_type));
+            }
+            Err(GcpCommError::Framing { reason }) => ProtocolEvent::new(LogLevel::Warn, TARGET_RX, &self.port_name)
+                .elapsed(elapsed)
+                .crc_ok(!matches!(reason, FramingError::Crc16 { .. }))
+                .log(format!("RX framing error: {:?}", reason)),
+            Err(_) => {}
+        }
+    }
 
-                    // If we have preamble, check if we can read length
-                    if found_preamble && expected_length == 0 && frame_buffer.len() >= 4 {
-                        expected_length = u16::from_le_bytes([frame_buffer[2], frame_buffer[3]]);
-                        println!("RX: Expected frame length: {}", expected_length);
-                    }
+    // A frame the device sent on its own while a command waited for its response is
+    // kept for `poll_unsolicited` rather than mistaken for the response
+    fn queue_unsolicited(&mut self, frame: DecodedFrame, request: GcpCommand) {
+        ProtocolEvent::new(LogLevel::Debug, TARGET_RX, &self.port_name)
+            .msg_type(frame.msg_type())
+            .log(format!("Queued unsolicited 0x{:04X} received while waiting for {:?}", frame.msg_type(), request));
+        self.unsolicited.push_back(frame);
+    }
 
-                    // Check if we have a complete frame
-                    if expected_length > 0 && frame_buffer.len() >= (expected_length + 4) as usize {
-                        let frame_data = &frame_buffer[..(expected_length + 4) as usize];
-                        println!("RX Complete Frame ({} bytes): {:02X?}", frame_data.len(), frame_data);
-                        
-                        // Log CRC validation details before deserializing
-                        if frame_data.len() >= 10 {
-                            let crc_data = &frame_data[2..(2 + expected_length) as usize];
-                            let calculated_crc = gcp_crc16(crc_data);
-                            let received_crc = u16::from_le_bytes([frame_data[frame_data.len()-2], frame_data[frame_data.len()-1]]);
-                            println!("RX CRC: calculated=0x{:04X}, received=0x{:04X}, match={}", 
-                                   calculated_crc, received_crc, calculated_crc == received_crc);
-                        }
-                        
-                        // Reset timeout before returning
-                        let _ = self.port.set_timeout(Duration::from_millis(GCP_TIMEOUT_MS));
-                        return GcpFrame::deserialize(frame_data);
-                    }
-                }
-                Err(ref e) if e.kind() == std::io::ErrorKind::TimedOut => {
-                    // Reset timeout before returning
-                    let _ = self.port.set_timeout(Duration::from_millis(GCP_TIMEOUT_MS));
-                    return Err("Timeout waiting for response".to_string());
-                }
-                Err(e) => {
-                    // Reset timeout before returning
-                    let _ = self.port.set_timeout(Duration::from_millis(GCP_TIMEOUT_MS));
-                    return Err(format!("Failed to read from port: {}", e));
+    // Next complete frame from the decoder. A MsgType outside the spec is logged and
+    // queued for `poll_unsolicited`, since no request can be waiting for it.
+    fn next_frame(&mut self) -> Option<Result<GcpFrame, GcpCommError>> {
+        loop {
+            match self.decoder.next_frame()? {
+                Ok(DecodedFrame::Frame(frame)) => return Some(Ok(frame)),
+                Ok(unknown) => {
+                    ProtocolEvent::new(LogLevel::Warn, TARGET_RX, &self.port_name)
+                        .elapsed(self.last_sent.elapsed())
+                        .msg_type(unknown.msg_type())
+                        .crc_ok(true)
+                        .log(format!("RX unknown MsgType 0x{:04X}", unknown.msg_type()));
+                    self.last_received = Instant::now();
+                    self.unsolicited.push_back(unknown);
                 }
+                Err(e) => return Some(Err(e)),
             }
         }
     }
 
-    pub fn send_hello(&mut self) -> Result<GcpHardwareData, String> {
+    // Responses to earlier requests that arrived late, e.g. after a timeout and resend
+    fn log_stale(&self, message: String) {
+        ProtocolEvent::new(LogLevel::Warn, TARGET_RX, &self.port_name).log(message);
+    }
+
+    pub fn send_hello(&mut self) -> Result<GcpHelloResponse, GcpCommError> {
         let hello_frame = GcpFrame::new(GcpCommand::Hello);
         
-        for attempt in 1..=GCP_MAX_RETRIES {
+        for attempt in 1..=self.link.max_retries {
             match self.send_frame(&hello_frame) {
                 Ok(()) => {
-                    match self.receive_frame() {
-                        Ok(response) => {
-                            println!("HELLO Response - Type: {:?}, Data len: {}, Parameters len: {}", 
-                                   response.msg_type, response.data.len(), response.parameters.len());
-                            
-                            // Handle different response types for HELLO
-                            match response.msg_type {
-                                GcpCommand::Ack => {
-                                    // Device sent ACK with hardware data - combine parameters and data for parsing
-                                    let all_data = [response.parameters.as_slice(), response.data.as_slice()].concat();
-                                    println!("ACK response with {} bytes total data (params: {}, data: {})", 
-                                           all_data.len(), response.parameters.len(), response.data.len());
-                                    
-                                    // For HELLO ACK: expect 8 bytes of hardware data (GCP v2.2)
-                                    if all_data.len() >= 8 {
-                                        // Hardware data may have command acknowledgment prefix, skip if present
-                                        let hw_start = if all_data.len() >= 10 && all_data[0] == 0x01 && all_data[1] == 0x00 {
-                                            2  // Skip HELLO command bytes (01 00)
-                                        } else {
-                                            0  // No command prefix, start from beginning
-                                        };
-                                        let hw_data = &all_data[hw_start..];
-                                        if hw_data.len() >= 8 {
-                                            return Ok(parse_hardware_data(hw_data));
-                                        }
-                                    }
-                                    
-                                    // Fallback: check if we got old status data format (temporary compatibility)
-                                    if all_data.len() >= 15 {
-                                        println!("Warning: Device returned status data instead of hardware data - using fallback");
-                                        return Ok(GcpHardwareData {
-                                            manufacture_date: 0x0A17,  // October 23rd as fallback
-                                            serial_number: 1000,       // Default serial
-                                            board_type: 0x01,          // DEV board
-                                            hw_revision: 0,
-                                            chip_model: 0x40,          // Apollo4Lite
-                                            features: 0x03,            // USB + BLE
-                                        });
-                                    }
-                                    
-                                    return Err("HELLO ACK response has insufficient data".to_string());
-                                }
-                                _ => {
-                                    // Direct hardware response - combine parameters and data
-                                    let all_data = [response.parameters.as_slice(), response.data.as_slice()].concat();
-                                    if all_data.len() >= 8 {
-                                        return Ok(parse_hardware_data(&all_data));
-                                    } else {
-                                        return Err(format!("Invalid HELLO response: insufficient data (got {} bytes, need 8)", all_data.len()));
-                                    }
-                                }
+                    match self.receive_hello_response(self.link.timeout_ms) {
+                        Ok(hello) => {
+                            if let GcpHelloResponse::Hardware(hardware) = &hello {
+                                self.hardware = Some(hardware.clone());
                             }
+                            return Ok(hello);
                         }
+                        Err(e) if !e.is_retryable() => return Err(e),
                         Err(e) => {
-                            if attempt == GCP_MAX_RETRIES {
-                                return Err(format!("HELLO failed after {} attempts: {}", GCP_MAX_RETRIES, e));
+                            if attempt == self.link.max_retries {
+                                return Err(e);
                             }
                             // Try again
                             continue;
@@ -933,233 +1809,306 @@ impl GcpUartHandler {
                     }
                 }
                 Err(e) => {
-                    if attempt == GCP_MAX_RETRIES {
-                        return Err(format!("Failed to send HELLO after {} attempts: {}", GCP_MAX_RETRIES, e));
+                    if attempt == self.link.max_retries {
+                        return Err(e);
+                    }
+                }
+            }
+        }
+
+        Err(GcpCommError::internal("HELLO command failed"))
+    }
+
+    // Single HELLO with a short timeout, for probing ports that may not be a device at all
+    pub fn probe_hello(&mut self, timeout_ms: u64) -> Result<GcpHelloResponse, GcpCommError> {
+        self.send_frame_simple(&GcpFrame::new(GcpCommand::Hello))?;
+        let hello = self.receive_hello_response(timeout_ms)?;
+        if let GcpHelloResponse::Hardware(hardware) = &hello {
+            self.hardware = Some(hardware.clone());
+        }
+        Ok(hello)
+    }
+
+    // Bytes received that were not part of a valid frame
+    pub fn discarded_bytes(&self) -> usize {
+        self.decoder.discarded()
+    }
+
+    fn receive_hello_response(&mut self, timeout_ms: u64) -> Result<GcpHelloResponse, GcpCommError> {
+        let response = self.receive_frame_with_timeout(timeout_ms)?;
+
+        let all_data = match response.msg_type {
+            GcpCommand::Ack => {
+                let all_data = response.payload();
+
+                // §4.1 form: bare response data, nothing to correlate
+                if all_data.len() == self.hello_data_len() {
+                    all_data
+                } else {
+                    // Otherwise the ACK must echo HELLO (01 00) before the response data
+                    let ack = AckFrame::decode_without_seq(&response)?;
+                    if !ack.acknowledges(GcpCommand::Hello) {
+                        return Err(GcpCommError::UnexpectedResponse {
+                            msg_type: ack.msg_type,
+                            message: format!("Stale ACK for 0x{:04X} received in response to HELLO", ack.msg_type),
+                        });
                     }
+                    ack.data
                 }
             }
+            GcpCommand::Nack => return Err(GcpCommError::from_nack(&response)),
+            // Direct hardware response
+            GcpCommand::Hello => response.payload(),
+            other => return Err(GcpCommError::unexpected_response(other, "HELLO")),
+        };
+
+        if self.spec_revision.hello_returns_status() {
+            let status_data = require_len("HELLO status", &all_data, self.spec_revision.status_data_len())?;
+            Ok(GcpHelloResponse::Status(parse_status_data(status_data)?))
+        } else {
+            Ok(GcpHelloResponse::Hardware(parse_hardware_data(&all_data)?))
         }
+    }
 
-        Err("HELLO command failed".to_string())
+    fn hello_data_len(&self) -> usize {
+        if self.spec_revision.hello_returns_status() {
+            self.spec_revision.status_data_len()
+        } else {
+            GCP_HARDWARE_DATA_LEN
+        }
     }
 
-    pub fn get_status(&mut self) -> Result<GcpStatusData, String> {
+    pub fn get_status(&mut self) -> Result<GcpStatusData, GcpCommError> {
         let status_frame = GcpFrame::new(GcpCommand::GetStatus);
         
-        for attempt in 1..=GCP_MAX_RETRIES {
+        for attempt in 1..=self.link.max_retries {
             match self.send_frame(&status_frame) {
                 Ok(()) => {
-                    match self.receive_frame() {
-                        Ok(response) => {
-                            // Combine parameters and data for status parsing (frame parser splits them incorrectly)
-                            let all_data = [response.parameters.as_slice(), response.data.as_slice()].concat();
-                            println!("GET_STATUS Response - Type: {:?}, Total data: {} bytes (params: {}, data: {})", 
-                                   response.msg_type, all_data.len(), response.parameters.len(), response.data.len());
-                            
-                            if response.msg_type == GcpCommand::Ack {
-                                // For ACK responses, skip the first 2 bytes (original command) if present
-                                let status_start = if all_data.len() >= 17 && all_data[0] == 0x01 && all_data[1] == 0x20 {
-                                    2  // Skip GET_STATUS command bytes  
-                                } else {
-                                    0  // No command prefix
-                                };
-                                let status_data = &all_data[status_start..];
-                                if status_data.len() >= 15 {
-                                    return Ok(parse_status_data(status_data));
-                                }
-                            } else if all_data.len() >= 15 {
-                                return Ok(parse_status_data(&all_data));
-                            }
-                            
-                            return Err(format!("Invalid status response: insufficient data (got {} bytes, need 15)", all_data.len()));
+                    // ACK payload structure: MsgType(2) + STATUS_DATA(15), no SeqNo (§4.9)
+                    match self.receive_ack_with(GcpCommand::GetStatus, None, self.link.timeout_ms, AckFrame::decode_without_seq) {
+                        Ok(ack) => {
+                            let status_data = require_len("GET_STATUS", &ack.data, self.spec_revision.status_data_len())?;
+                            return parse_status_data(status_data);
                         }
+                        Err(e) if !e.is_retryable() => return Err(e),
                         Err(e) => {
-                            if attempt == GCP_MAX_RETRIES {
-                                return Err(format!("Get status failed after {} attempts: {}", GCP_MAX_RETRIES, e));
+                            if attempt == self.link.max_retries {
+                                return Err(e);
                             }
                             continue;
                         }
                     }
                 }
                 Err(e) => {
-                    if attempt == GCP_MAX_RETRIES {
-                        return Err(format!("Failed to send get status after {} attempts: {}", GCP_MAX_RETRIES, e));
+                    if attempt == self.link.max_retries {
+                        return Err(e);
                     }
                 }
             }
         }
 
-        Err("Get status command failed".to_string())
+        Err(GcpCommError::internal("Get status command failed"))
     }
 
-    pub fn get_fw_version(&mut self) -> Result<GcpFwVersionData, String> {
+    pub fn get_fw_version(&mut self) -> Result<GcpFwVersionData, GcpCommError> {
         let fw_version_frame = GcpFrame::new(GcpCommand::GetFwVersion);
         
-        for attempt in 1..=GCP_MAX_RETRIES {
+        for attempt in 1..=self.link.max_retries {
             match self.send_frame(&fw_version_frame) {
                 Ok(()) => {
-                    match self.receive_frame() {
-                        Ok(response) => {
-                            // Combine parameters and data for fw version parsing
-                            let all_data = [response.parameters.as_slice(), response.data.as_slice()].concat();
-                            println!("GET_FW_VERSION Response - Type: {:?}, Total data: {} bytes (params: {}, data: {})", 
-                                   response.msg_type, all_data.len(), response.parameters.len(), response.data.len());
-                            
-                            if response.msg_type == GcpCommand::Ack {
-                                // ACK payload structure: MsgType(2) + SeqNo(4) + FW_DATA(6)
-                                // FW version data starts at offset 6 within the ACK payload
-                                println!("ACK payload: {:02X?}", all_data);
-                                if all_data.len() >= 12 { // MsgType(2) + SeqNo(4) + FW_DATA(6) = 12
-                                    let version_data = &all_data[6..12]; // Skip MsgType(2) + SeqNo(4), take 6 bytes
-                                    println!("Firmware version data: {:02X?}", version_data);
-                                    return Ok(parse_fw_version_data(version_data));
-                                }
-                            } else if all_data.len() >= 6 {
-                                return Ok(parse_fw_version_data(&all_data));
-                            }
-                            
-                            return Err(format!("Invalid fw version response: insufficient data (got {} bytes, need 6)", all_data.len()));
+                    // ACK payload structure: MsgType(2) + SeqNo(4) + FW_DATA(6)
+                    match self.receive_ack(GcpCommand::GetFwVersion, None, self.link.timeout_ms) {
+                        Ok(ack) => {
+                            return parse_fw_version_data(&ack.data);
                         }
+                        Err(e) if !e.is_retryable() => return Err(e),
                         Err(e) => {
-                            if attempt == GCP_MAX_RETRIES {
-                                return Err(format!("Get fw version failed after {} attempts: {}", GCP_MAX_RETRIES, e));
+                            if attempt == self.link.max_retries {
+                                return Err(e);
                             }
                             continue;
                         }
                     }
                 }
                 Err(e) => {
-                    if attempt == GCP_MAX_RETRIES {
-                        return Err(format!("Failed to send get fw version after {} attempts: {}", GCP_MAX_RETRIES, e));
+                    if attempt == self.link.max_retries {
+                        return Err(e);
                     }
                 }
             }
         }
 
-        Err("Get fw version command failed".to_string())
+        Err(GcpCommError::internal("Get fw version command failed"))
     }
-}
 
-// Helper function to find preamble in buffer
-fn find_preamble(buffer: &[u8]) -> Option<usize> {
-    if buffer.len() < 2 {
-        return None;
+    pub fn get_diagnostics(&mut self) -> Result<GcpDiagnosticsData, GcpCommError> {
+        let diagnostics_frame = GcpFrame::new(GcpCommand::GetDiagnostics);
+
+        for attempt in 1..=self.link.max_retries {
+            match self.send_frame(&diagnostics_frame) {
+                Ok(()) => {
+                    // ACK payload structure: MsgType(2) + SeqNo(4) + DIAG_DATA(32)
+                    match self.receive_ack(GcpCommand::GetDiagnostics, None, self.link.timeout_ms) {
+                        Ok(ack) => return parse_diagnostics_data(&ack.data),
+                        Err(e) if !e.is_retryable() => return Err(e),
+                        Err(e) => {
+                            if attempt == self.link.max_retries {
+                                return Err(e);
+                            }
+                            continue;
+                        }
+                    }
+                }
+                Err(e) => {
+                    if attempt == self.link.max_retries {
+                        return Err(e);
+                    }
+                }
+            }
+        }
+
+        Err(GcpCommError::internal("Get diagnostics command failed"))
     }
 
-    for i in 0..=buffer.len() - 2 {
-        if buffer[i] == GCP_PREAMBLE[0] && buffer[i + 1] == GCP_PREAMBLE[1] {
-            return Some(i);
+    pub fn set_config(&mut self, config: GcpConfig) -> Result<(), GcpCommError> {
+        // Reject out-of-range values on the host instead of relying on a device NACK
+        config.validate()?;
+
+        let config_frame = config.to_frame();
+
+        for attempt in 1..=self.link.max_retries {
+            match self.send_frame(&config_frame) {
+                Ok(()) => {
+                    match self.receive_ack(GcpCommand::SetConfig, None, self.link.timeout_ms) {
+                        Ok(_) => {
+                            ProtocolEvent::new(LogLevel::Info, TARGET_LINK, &self.port_name)
+                                .log(format!("SET_CONFIG {:?} acknowledged", config));
+                            return Ok(());
+                        }
+                        Err(e) if !e.is_retryable() => return Err(e),
+                        Err(e) => {
+                            if attempt == self.link.max_retries {
+                                return Err(e);
+                            }
+                        }
+                    }
+                }
+                Err(e) => {
+                    if attempt == self.link.max_retries {
+                        return Err(e);
+                    }
+                }
+            }
         }
+
+        Err(GcpCommError::internal("Set config command failed"))
     }
 
-    None
 }
 
-// Helper function to parse status data from response (GCP v2.1: 15 bytes)
-fn parse_status_data(data: &[u8]) -> GcpStatusData {
-    if data.len() < 15 {
-        // Return default data if insufficient
-        return GcpStatusData {
-            battery_level: 0,
-            system_state: 0,
-            led_color: 0,
-            led_brightness: 0,
-            current_game_idx: 0,
-            rtc_time: [0; 8],
-        };
+// Payloads are parsed strictly: a short payload is an error, never filled in with
+// made-up values. Trailing bytes beyond the structure are ignored.
+fn require_len<'a>(payload: &str, data: &'a [u8], len: usize) -> Result<&'a [u8], GcpCommError> {
+    if data.len() < len {
+        return Err(GcpCommError::truncated(payload, len, data.len()));
+    }
+    Ok(&data[..len])
+}
+
+// FW_UPDATE_END result (§4.5): 0x00000000 when the image CRC32 matched. An ACK
+// without it says nothing about the image, so it is not taken as a match.
+fn parse_fw_update_result(data: &[u8]) -> Result<u32, GcpCommError> {
+    let data = require_len("FW_UPDATE_END result", data, GCP_FW_UPDATE_RESULT_LEN)?;
+    Ok(u32::from_le_bytes([data[0], data[1], data[2], data[3]]))
+}
+
+// GcpMessage payloads must match their layout exactly, or re-encoding would change them
+fn exact_len<'a>(payload: &str, data: &'a [u8], len: usize) -> Result<&'a [u8], GcpCommError> {
+    if data.len() > len {
+        return Err(GcpCommError::invalid_response(format!(
+            "{} payload too long: got {} bytes, need {}", payload, data.len(), len)));
     }
+    require_len(payload, data, len)
+}
+
+// Helper function to parse status data from response (GCP v2.1: 15 bytes)
+fn parse_status_data(data: &[u8]) -> Result<GcpStatusData, GcpCommError> {
+    let data = require_len("status", data, GCP_STATUS_DATA_LEN)?;
 
-    GcpStatusData {
+    Ok(GcpStatusData {
         battery_level: data[0],
         system_state: data[1],
         led_color: u16::from_le_bytes([data[2], data[3]]),
         led_brightness: data[4],
         current_game_idx: u16::from_le_bytes([data[5], data[6]]),
         rtc_time: [data[7], data[8], data[9], data[10], data[11], data[12], data[13], data[14]],
-    }
-}
-
-// Helper function to parse status data flexibly with whatever data we have
-fn parse_status_data_flexible(data: &[u8]) -> GcpStatusData {
-    let mut status = GcpStatusData {
-        battery_level: 50,  // Default values
-        system_state: 1,
-        led_color: 0x07E0,  // Green
-        led_brightness: 255,
-        current_game_idx: 0,
-        rtc_time: [25, 10, 22, 2, 17, 0, 2, 0], // Current approx time
-    };
-
-    // Parse whatever fields we have available
-    if data.len() >= 1 {
-        status.battery_level = data[0];
-    }
-    if data.len() >= 2 {
-        status.system_state = data[1];
-    }
-    if data.len() >= 4 {
-        status.led_color = u16::from_le_bytes([data[2], data[3]]);
-    }
-    if data.len() >= 5 {
-        status.led_brightness = data[4];
-    }
-    if data.len() >= 7 {
-        status.current_game_idx = u16::from_le_bytes([data[5], data[6]]);
-    }
-    if data.len() >= 15 {
-        status.rtc_time = [data[7], data[8], data[9], data[10], data[11], data[12], data[13], data[14]];
-    }
-
-    status
+    })
 }
 
 // Helper function to parse firmware version data from response (GCP v2.1: 6 bytes)
-fn parse_fw_version_data(data: &[u8]) -> GcpFwVersionData {
-    if data.len() < 6 {
-        // Return default data if insufficient
-        return GcpFwVersionData {
-            fw_version_major: 0,
-            fw_version_minor: 0,
-            fw_version_patch: 0,
-            fw_version_suffix: [0; 3],
-        };
-    }
+fn parse_fw_version_data(data: &[u8]) -> Result<GcpFwVersionData, GcpCommError> {
+    let data = require_len("firmware version", data, GCP_FW_VERSION_DATA_LEN)?;
 
-    GcpFwVersionData {
+    Ok(GcpFwVersionData {
         fw_version_major: data[0],
         fw_version_minor: data[1],
         fw_version_patch: data[2],
         fw_version_suffix: [data[3], data[4], data[5]],
+    })
+}
+
+// Extract the current firmware version from a device-initiated FW_UPDATE_REQUEST
+pub fn parse_fw_update_request(frame: &GcpFrame) -> Result<GcpFwVersionData, GcpCommError> {
+    if frame.msg_type != GcpCommand::FwUpdateRequest {
+        return Err(GcpCommError::UnexpectedResponse {
+            msg_type: frame.msg_type as u16,
+            message: format!("Expected FW_UPDATE_REQUEST, got {:?}", frame.msg_type),
+        });
     }
+
+    parse_fw_version_data(&frame.payload())
+}
+
+// Helper function to parse diagnostics counters from response (GCP v2.2: 32 bytes)
+// All counters are little-endian u32.
+fn parse_diagnostics_data(data: &[u8]) -> Result<GcpDiagnosticsData, GcpCommError> {
+    let data = require_len("diagnostics", data, GCP_DIAGNOSTICS_DATA_LEN)?;
+
+    let counter = |index: usize| {
+        let offset = index * 4;
+        u32::from_le_bytes([data[offset], data[offset + 1], data[offset + 2], data[offset + 3]])
+    };
+
+    Ok(GcpDiagnosticsData {
+        step_counter: counter(0),
+        full_power_time: counter(1),
+        silent_time: counter(2),
+        charging_time: counter(3),
+        btn_counter_l: counter(4),
+        btn_counter_r: counter(5),
+        fram_read: counter(6),
+        fram_write: counter(7),
+    })
 }
 
 // Helper function to parse hardware data from response (GCP v2.2: 8 bytes)
-fn parse_hardware_data(data: &[u8]) -> GcpHardwareData {
-    if data.len() < 8 {
-        // Return default data if insufficient
-        return GcpHardwareData {
-            manufacture_date: 0x0A17,  // October 23rd as default
-            serial_number: 1000,       // Default serial
-            board_type: 0x01,          // DEV board
-            hw_revision: 0,
-            chip_model: 0x40,          // Apollo4Lite
-            features: 0x03,            // USB + BLE
-        };
-    }
+fn parse_hardware_data(data: &[u8]) -> Result<GcpHardwareData, GcpCommError> {
+    let data = require_len("hardware", data, GCP_HARDWARE_DATA_LEN)?;
 
-    GcpHardwareData {
+    Ok(GcpHardwareData {
         manufacture_date: u16::from_le_bytes([data[0], data[1]]),
         serial_number: u16::from_le_bytes([data[2], data[3]]),
         board_type: data[4],
         hw_revision: data[5],
         chip_model: data[6],
         features: data[7],
-    }
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
 
     #[test]
     fn test_crc16() {
@@ -1196,4 +2145,383 @@ mod tests {
         assert_eq!(deserialized.msg_type as u16, GcpCommand::Hello as u16);
         assert_eq!(deserialized.length, 6);
     }
+
+    #[test]
+    fn test_parse_diagnostics_data() {
+        let mut data = Vec::new();
+        for value in [1u32, 2, 3, 4, 5, 6, 7, 0xDEADBEEF] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+
+        let diagnostics = parse_diagnostics_data(&data).unwrap();
+        assert_eq!(diagnostics.step_counter, 1);
+        assert_eq!(diagnostics.full_power_time, 2);
+        assert_eq!(diagnostics.silent_time, 3);
+        assert_eq!(diagnostics.charging_time, 4);
+        assert_eq!(diagnostics.btn_counter_l, 5);
+        assert_eq!(diagnostics.btn_counter_r, 6);
+        assert_eq!(diagnostics.fram_read, 7);
+        assert_eq!(diagnostics.fram_write, 0xDEADBEEF);
+    }
+
+    #[test]
+    fn test_fw_version_ordering() {
+        let version = |major, minor, patch, suffix: &[u8; 3]| GcpFwVersionData {
+            fw_version_major: major,
+            fw_version_minor: minor,
+            fw_version_patch: patch,
+            fw_version_suffix: *suffix,
+        };
+
+        let current = version(0, 1, 4, b"a\0\0");
+        assert_eq!(current.version_string(), "0.1.4a");
+        assert!(version(0, 1, 5, b"a\0\0").is_newer_than(&current));
+        assert!(version(0, 1, 4, b"b\0\0").is_newer_than(&current));
+        assert!(!version(0, 1, 4, b"a\0\0").is_newer_than(&current));
+        assert!(!version(0, 0, 9, b"rc1").is_newer_than(&current));
+    }
+
+    #[test]
+    fn test_parse_fw_update_request() {
+        let frame = GcpFrame::with_parameters(GcpCommand::FwUpdateRequest, vec![0, 1, 4, b'a', 0, 0]);
+        let received = GcpFrame::deserialize(&frame.serialize()).unwrap();
+
+        let version = parse_fw_update_request(&received).unwrap();
+        assert_eq!(version.version_string(), "0.1.4a");
+    }
+
+    #[test]
+    fn test_set_config_frame_layout() {
+        let time = GcpRtcTime { year: 25, month: 10, day: 23, hour: 14, minute: 5, second: 30, weekday: 3 };
+        let frame = GcpConfig::Time(time).to_frame();
+        let serialized = frame.serialize();
+
+        // Length(2) + MsgType(2) + SubCmd(2) + Reserved(2) + TIME(7)
+        assert_eq!(frame.length, 15);
+        assert_eq!(&serialized[4..6], &[0x02, 0x20]);
+        assert_eq!(&serialized[6..10], &[0x01, 0x00, 0x00, 0x00]);
+        assert_eq!(&serialized[10..17], &[25, 10, 23, 14, 5, 30, 3]);
+
+        let frame = GcpConfig::Sound(true).to_frame();
+        assert_eq!(frame.parameters, vec![0x03, 0x00, 0x00, 0x00]);
+        assert_eq!(frame.data, vec![1]);
+    }
+
+    #[test]
+    fn test_set_config_validation() {
+        assert!(GcpConfig::Brightness(100).validate().is_ok());
+        assert!(GcpConfig::Brightness(101).validate().is_err());
+
+        let valid = GcpRtcTime { year: 24, month: 2, day: 29, hour: 23, minute: 59, second: 59, weekday: 3 };
+        assert!(GcpConfig::Time(valid).validate().is_ok());
+        assert!(GcpConfig::Time(GcpRtcTime { year: 25, ..valid }).validate().is_err());
+        assert!(GcpConfig::Time(GcpRtcTime { month: 13, ..valid }).validate().is_err());
+        assert!(GcpConfig::Time(GcpRtcTime { hour: 24, ..valid }).validate().is_err());
+        assert!(GcpConfig::Time(GcpRtcTime { weekday: 7, ..valid }).validate().is_err());
+    }
+
+    #[test]
+    fn test_nack_error_decoding() {
+        // NACK for FW_UPDATE_DATA (0x1002), SeqNo 7, error SEQ (0x0002)
+        let mut nack = GcpFrame::with_data(GcpCommand::Nack, Vec::new(),
+                                           vec![0x02, 0x10, 0x07, 0x00, 0x00, 0x00, 0x02, 0x00]);
+
+        let error = GcpCommError::from_nack(&nack);
+        assert_eq!(error, GcpCommError::Nack {
+            msg_type: 0x1002,
+            seq_no: 7,
+            error_code: 0x0002,
+            error: Some(GcpError::Seq),
+        });
+
+        let json = serde_json::to_value(&error).unwrap();
+        assert_eq!(json["kind"], "nack");
+        assert_eq!(json["error"], "SEQ");
+
+        nack.data.truncate(6);
+        assert!(matches!(GcpCommError::from_nack(&nack), GcpCommError::InvalidResponse { .. }));
+
+        let json = serde_json::to_value(GcpCommError::Framing { reason: FramingError::Preamble }).unwrap();
+        assert_eq!(json, serde_json::json!({ "kind": "framing", "reason": { "type": "preamble" } }));
+    }
+
+    #[test]
+    fn test_ack_frame_decoding() {
+        // ACK for FW_UPDATE_DATA at offset 0x0800, as received on the wire
+        let mut wire = vec![0xAA, 0x55, 0x0A, 0x00, 0x02, 0x00, 0x02, 0x10, 0x00, 0x08, 0x00, 0x00];
+        let crc = gcp_crc16(&wire[2..]);
+        wire.extend_from_slice(&crc.to_le_bytes());
+
+        let frame = GcpFrame::deserialize(&wire).unwrap();
+        let ack = AckFrame::decode(&frame).unwrap();
+        assert!(ack.acknowledges(GcpCommand::FwUpdateData));
+        assert!(!ack.acknowledges(GcpCommand::FwUpdateStart));
+        assert_eq!(ack.seq_no, 0x0800);
+        assert!(ack.data.is_empty());
+
+        // GET_STATUS echoes its MsgType without a SeqNo
+        let status = GcpFrame::with_data(GcpCommand::Ack, Vec::new(), vec![0x01, 0x20, 80, 2]);
+        let ack = AckFrame::decode_without_seq(&status).unwrap();
+        assert!(ack.acknowledges(GcpCommand::GetStatus));
+        assert_eq!(ack.data, vec![80, 2]);
+
+        // A NACK is not an ACK, and a bare ACK is too short to correlate
+        let nack = GcpFrame::with_data(GcpCommand::Nack, Vec::new(), vec![0x02, 0x10, 0, 0, 0, 0, 0x02, 0x00]);
+        assert!(matches!(AckFrame::decode(&nack), Err(GcpCommError::UnexpectedResponse { .. })));
+        assert!(NackFrame::decode(&nack).unwrap().rejects(GcpCommand::FwUpdateData));
+        let short = GcpFrame::with_data(GcpCommand::Ack, Vec::new(), vec![0x02, 0x10]);
+        assert!(matches!(AckFrame::decode(&short), Err(GcpCommError::InvalidResponse { .. })));
+    }
+
+    #[test]
+    fn test_frame_parameters_follow_the_command() {
+        let split = |frame: GcpFrame| {
+            let received = GcpFrame::deserialize(&frame.serialize()).unwrap();
+            (received.parameters.len(), received.data.len())
+        };
+
+        let chunk = GcpMessage::FwUpdateData { offset: 0x0800, data: vec![0x5A; 10] };
+        assert_eq!(split(GcpFrame::deserialize(&chunk.encode()).unwrap()), (4, 10));
+        assert_eq!(split(GcpConfig::Brightness(40).to_frame()), (4, 1));
+        assert_eq!(split(GcpFrame::with_data(GcpCommand::FwUpdateRequest, Vec::new(), vec![0, 1, 4, b'a', 0, 0])), (0, 6));
+        assert_eq!(split(GcpFrame::new(GcpCommand::GetStatus)), (2, 0));
+
+        // Unknown message types are reported, not disguised as HELLO
+        let unknown = GcpMessage::Unknown(0x3001, vec![1, 2, 3]).encode();
+        assert!(matches!(GcpFrame::deserialize(&unknown), Err(GcpCommError::UnexpectedResponse { msg_type: 0x3001, .. })));
+        assert_eq!(GcpCommand::try_from(0x3001), Err(0x3001));
+    }
+
+    #[test]
+    fn test_message_round_trip() {
+        let time = GcpRtcTime { year: 25, month: 10, day: 23, hour: 14, minute: 5, second: 30, weekday: 3 };
+        let messages = vec![
+            GcpMessage::Hello,
+            GcpMessage::Reset { reset_type: 2 },
+            GcpMessage::Ping,
+            GcpMessage::FwUpdateStart { size: 6000, crc32: 0xDEADBEEF, chunk_size: 2036 },
+            GcpMessage::FwUpdateData { offset: 2036, data: vec![1, 2, 3] },
+            GcpMessage::FwUpdateEnd,
+            GcpMessage::FwUpdateAbort,
+            GcpMessage::FwNoUpdateAvailable,
+            GcpMessage::GetStatus,
+            GcpMessage::SetConfig(GcpConfig::Time(time)),
+            GcpMessage::SetConfig(GcpConfig::Sound(false)),
+            GcpMessage::GetInfo,
+            GcpMessage::GetDiagnostics,
+            GcpMessage::GetFwVersion,
+            GcpMessage::Ack(vec![0x03, 0x10, 0, 0, 0, 0, 0xFF, 0xFF, 0xFF, 0xFF]),
+            GcpMessage::Nack(NackFrame { msg_type: 0x1002, seq_no: 7, error_code: 0x0002 }),
+            GcpMessage::FwUpdateRequest(GcpFwVersionData {
+                fw_version_major: 0,
+                fw_version_minor: 1,
+                fw_version_patch: 4,
+                fw_version_suffix: *b"rc1",
+            }),
+            GcpMessage::Unknown(0x3001, vec![0xAA, 0x55]),
+        ];
+
+        for message in messages {
+            let wire = message.encode();
+            assert_eq!(GcpMessage::decode(&wire).unwrap(), message);
+            if let Some(command) = message.command() {
+                let frame = GcpFrame::deserialize(&wire).unwrap();
+                assert_eq!(frame.msg_type, command);
+                assert_eq!(frame.serialize(), wire);
+                assert_eq!(GcpMessage::from_frame(&frame).unwrap(), message);
+            }
+        }
+
+        // The typed fields sit where the spec puts them
+        assert_eq!(GcpMessage::Hello.encode(), GcpFrame::new(GcpCommand::Hello).serialize());
+        assert_eq!(GcpMessage::SetConfig(GcpConfig::Time(time)).encode(), GcpConfig::Time(time).to_frame().serialize());
+
+        // Anything re-encoding could not reproduce is rejected
+        let with_reserved = GcpMessage::Unknown(GcpCommand::GetStatus as u16, vec![0, 1]).encode();
+        assert!(GcpMessage::decode(&with_reserved).is_err());
+        let short_start = GcpMessage::Unknown(GcpCommand::FwUpdateStart as u16, vec![0; 10]).encode();
+        assert!(matches!(GcpMessage::decode(&short_start), Err(GcpCommError::TruncatedPayload { .. })));
+        let bad_sound = GcpMessage::Unknown(GcpCommand::SetConfig as u16, vec![3, 0, 0, 0, 2]).encode();
+        assert!(GcpMessage::decode(&bad_sound).is_err());
+    }
+
+    proptest! {
+        #[test]
+        fn prop_decoded_messages_reencode_losslessly(
+            msg_type in prop_oneof![any::<u16>(), 0x0001u16..=0x0005, 0x1001u16..=0x1006, 0x2001u16..=0x2005],
+            payload in prop::collection::vec(any::<u8>(), 0..40),
+        ) {
+            // Any frame that decodes, known MsgType or not, encodes back to the same bytes
+            let wire = GcpMessage::Unknown(msg_type, payload).encode();
+            if let Ok(message) = GcpMessage::decode(&wire) {
+                prop_assert_eq!(message.msg_type(), msg_type);
+                prop_assert_eq!(message.encode(), wire);
+            }
+        }
+    }
+
+    #[test]
+    fn test_short_payloads_are_rejected() {
+        let status = [80, 2, 0xE0, 0x07, 128, 3, 0, 25, 10, 23, 14, 5, 30, 3, 0];
+        assert_eq!(parse_status_data(&status).unwrap().battery_level, 80);
+        assert_eq!(parse_status_data(&status[..7]).unwrap_err(), GcpCommError::TruncatedPayload {
+            payload: "status".to_string(),
+            expected: 15,
+            received: 7,
+        });
+
+        assert!(parse_hardware_data(&[0x17, 0x0A, 0xE8, 0x03, 0x01, 0x00, 0x40, 0x03]).is_ok());
+        assert!(matches!(parse_hardware_data(&[0x17, 0x0A, 0xE8, 0x03]),
+                         Err(GcpCommError::TruncatedPayload { expected: 8, received: 4, .. })));
+        assert!(parse_fw_version_data(&[0, 1]).is_err());
+        assert!(parse_diagnostics_data(&[0; 31]).is_err());
+    }
+
+    #[test]
+    fn test_spec_revision_payload_sizes() {
+        assert_eq!(GcpSpecRevision::default(), GcpSpecRevision::V2_2);
+        assert!(!GcpSpecRevision::V2_2.hello_returns_status());
+        assert!(GcpSpecRevision::V2_1.hello_returns_status());
+        assert_eq!(GcpSpecRevision::V2_1.status_data_len(), 15);
+        assert_eq!(GcpSpecRevision::V2_0.status_data_len(), 21);
+
+        let revision: GcpSpecRevision = serde_json::from_str("\"2.0\"").unwrap();
+        assert_eq!(revision, GcpSpecRevision::V2_0);
+    }
+
+    #[test]
+    fn test_hello_over_memory_transport() {
+        let (host, device) = crate::transport::MemoryTransport::pair();
+
+        // Minimal device: answer one HELLO with HWVersion_t
+        let device_thread = std::thread::spawn(move || {
+            let mut device = GcpUartHandler::with_transport(Box::new(device));
+            let request = device.receive_frame().unwrap();
+            assert_eq!(request.msg_type, GcpCommand::Hello);
+            let reply = GcpFrame::with_data(GcpCommand::Hello, Vec::new(), vec![0x19, 0x07, 0xE8, 0x03, 0x10, 0x01, 0x40, 0x03]);
+            device.send_frame_simple(&reply).unwrap();
+        });
+
+        let port_name = "memory://test_hello".to_string();
+        connect_with_transport(port_name.clone(), Box::new(host)).unwrap();
+        let hello = execute_with_connection(&port_name, |handler| handler.send_hello()).unwrap();
+        match hello {
+            GcpHelloResponse::Hardware(hardware) => {
+                assert_eq!(hardware.serial_number, 1000);
+                assert_eq!(hardware.chip_model, 0x40);
+            }
+            other => panic!("Expected hardware data, got {:?}", other),
+        }
+
+        device_thread.join().unwrap();
+        disconnect_from_port(port_name).unwrap();
+    }
+
+    #[test]
+    fn test_fw_update_end_requires_result() {
+        let (host, device) = crate::transport::MemoryTransport::pair();
+
+        // Device that ACKs FW_UPDATE_END twice without the 4-byte Result
+        let device_thread = std::thread::spawn(move || {
+            let mut device = GcpUartHandler::with_transport(Box::new(device));
+            for _ in 0..2 {
+                let request = device.receive_frame().unwrap();
+                assert_eq!(request.msg_type, GcpCommand::FwUpdateEnd);
+                let mut payload = (GcpCommand::FwUpdateEnd as u16).to_le_bytes().to_vec();
+                payload.extend_from_slice(&0u32.to_le_bytes());
+                device.send_frame_simple(&GcpFrame::with_data(GcpCommand::Ack, Vec::new(), payload)).unwrap();
+            }
+        });
+
+        let mut handler = GcpUartHandler::with_transport(Box::new(host));
+        let truncated = GcpCommError::TruncatedPayload {
+            payload: "FW_UPDATE_END result".to_string(),
+            expected: GCP_FW_UPDATE_RESULT_LEN,
+            received: 0,
+        };
+        assert_eq!(handler.end_firmware_update().unwrap_err(), truncated);
+        assert_eq!(handler.exchange(&GcpFrame::new(GcpCommand::FwUpdateEnd)).unwrap_err(), truncated);
+        device_thread.join().unwrap();
+    }
+
+    #[test]
+    fn test_unsolicited_frames_are_kept_for_polling() {
+        let (host, device) = crate::transport::MemoryTransport::pair();
+        let version = crate::simulator::SimulatorConfig::default().fw_version;
+        let request = GcpMessage::FwUpdateRequest(version.clone()).encode();
+        let ack = |msg_type: GcpCommand, data: &[u8]| {
+            let mut payload = (msg_type as u16).to_le_bytes().to_vec();
+            payload.extend_from_slice(&0u32.to_le_bytes());
+            payload.extend_from_slice(data);
+            GcpFrame::with_data(GcpCommand::Ack, Vec::new(), payload)
+        };
+
+        let unknown = GcpMessage::Unknown(0x3001, vec![0, 0]).encode();
+
+        // A frame outside the spec and FW_UPDATE_REQUEST ahead of the PING ACK, and
+        // another request left waiting after it
+        let device_thread = std::thread::spawn(move || {
+            let mut device = GcpUartHandler::with_transport(Box::new(device));
+            assert_eq!(device.receive_frame().unwrap().msg_type, GcpCommand::Ping);
+            device.port.write_all(&unknown).unwrap();
+            device.port.write_all(&request).unwrap();
+            device.send_frame_simple(&ack(GcpCommand::Ping, &[])).unwrap();
+            device.port.write_all(&request).unwrap();
+            assert_eq!(device.receive_frame().unwrap().msg_type, GcpCommand::GetFwVersion);
+            device.send_frame_simple(&ack(GcpCommand::GetFwVersion, &version.to_bytes())).unwrap();
+            device
+        });
+
+        let mut handler = GcpUartHandler::with_transport(Box::new(host));
+        handler.ping().unwrap();
+        // Sending the next command drains the RX buffer without losing the request
+        std::thread::sleep(Duration::from_millis(50));
+        handler.get_fw_version().unwrap();
+        let _device = device_thread.join().unwrap();
+
+        for msg_type in [0x3001, GcpCommand::FwUpdateRequest as u16, GcpCommand::FwUpdateRequest as u16] {
+            assert_eq!(handler.poll_unsolicited().unwrap().unwrap().msg_type(), msg_type);
+        }
+        assert!(handler.poll_unsolicited().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_ping_and_link_loss() {
+        let host = crate::simulator::spawn_memory_device(crate::simulator::SimulatorConfig::default());
+        let mut handler = GcpUartHandler::with_transport(Box::new(host));
+        handler.ping().unwrap();
+        assert!(handler.idle_time() < Duration::from_millis(GCP_TIMEOUT_MS));
+        assert!(handler.is_connected());
+
+        // Device side gone: detected without sending, and PING fails as a transport error
+        let (host, device) = crate::transport::MemoryTransport::pair();
+        drop(device);
+        let mut handler = GcpUartHandler::with_transport(Box::new(host));
+        assert!(!handler.is_connected());
+        assert!(matches!(handler.ping(), Err(GcpCommError::Transport { .. })));
+    }
+
+    #[test]
+    fn test_replace_connection_after_reboot() {
+        let config = crate::simulator::SimulatorConfig::default();
+        let serial_number = config.hardware.serial_number;
+        let old_port = "memory://test_replace_old".to_string();
+        let new_port = "memory://test_replace_new".to_string();
+        connect_with_transport(old_port.clone(), Box::new(crate::simulator::spawn_memory_device(config.clone()))).unwrap();
+
+        // The device reappears under another name; HELLO on the new link is remembered
+        let mut handler = GcpUartHandler::with_transport(Box::new(crate::simulator::spawn_memory_device(config)));
+        assert!(handler.hardware().is_none());
+        handler.send_hello().unwrap();
+        assert_eq!(handler.hardware().map(|hardware| hardware.serial_number), Some(serial_number));
+
+        replace_connection(&old_port, new_port.clone(), handler).unwrap();
+        assert!(get_connection_handle(&old_port).is_none());
+        let hardware = execute_with_connection(&new_port, |handler| Ok(handler.hardware())).unwrap();
+        assert_eq!(hardware.map(|hardware| hardware.serial_number), Some(serial_number));
+
+        let orphan = GcpUartHandler::with_transport(Box::new(crate::transport::MemoryTransport::pair().0));
+        assert!(matches!(replace_connection(&old_port, new_port.clone(), orphan), Err(GcpCommError::NotConnected { .. })));
+        disconnect_from_port(new_port).unwrap();
+    }
 }
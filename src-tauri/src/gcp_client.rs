@@ -0,0 +1,169 @@
+//! Async GCP client
+//!
+//! Serial I/O is blocking, so each connection gets a dedicated I/O worker thread with
+//! a request queue. Callers queue a request and await its response future; the worker
+//! runs requests one at a time against the pooled handler and completes each future
+//! with the result. Commands built on this never block the Tauri runtime or the main
+//! thread while a device is answering. Background tasks (firmware jobs, listeners,
+//! the health monitor) keep sharing the handler directly, so requests interleave
+//! with them exactly as before.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Mutex;
+use tokio::sync::oneshot;
+
+use crate::gcp::{get_connection_handle, GcpCommError, GcpUartHandler};
+
+type Request = Box<dyn FnOnce(&mut GcpUartHandler) + Send>;
+
+#[derive(Clone)]
+pub struct GcpClient {
+    id: u64,                     // Tells apart clients for the same port name over reconnects
+    port_name: String,
+    queue: Sender<Request>,
+}
+
+lazy_static::lazy_static! {
+    static ref CLIENTS: Mutex<HashMap<String, GcpClient>> = Mutex::new(HashMap::new());
+}
+
+static NEXT_CLIENT_ID: AtomicU64 = AtomicU64::new(1);
+
+// Client for a pooled connection, starting its I/O worker on first use
+pub fn client(port_name: &str) -> Result<GcpClient, GcpCommError> {
+    let mut clients = CLIENTS.lock()
+        .map_err(|_| GcpCommError::internal("Failed to lock client registry"))?;
+
+    // The connection may have been closed or moved to another port name since
+    if get_connection_handle(port_name).is_none() {
+        clients.remove(port_name);
+        return Err(GcpCommError::NotConnected { port_name: port_name.to_string() });
+    }
+    if let Some(client) = clients.get(port_name) {
+        return Ok(client.clone());
+    }
+
+    let (queue, requests) = channel();
+    let id = NEXT_CLIENT_ID.fetch_add(1, Ordering::Relaxed);
+    let worker_port = port_name.to_string();
+    std::thread::Builder::new()
+        .name(format!("gcp-io-{}", port_name))
+        .spawn(move || serve(worker_port, id, requests))
+        .map_err(|e| GcpCommError::internal(format!("Failed to start I/O worker: {}", e)))?;
+
+    let client = GcpClient { id, port_name: port_name.to_string(), queue };
+    clients.insert(port_name.to_string(), client.clone());
+    Ok(client)
+}
+
+// Stop a connection's worker once the requests already queued have run
+pub fn close_client(port_name: &str) {
+    if let Ok(mut clients) = CLIENTS.lock() {
+        clients.remove(port_name);
+    }
+}
+
+// Queue `operation` on the port's I/O worker and await its result
+pub async fn request<F, T>(port_name: &str, operation: F) -> Result<T, GcpCommError>
+where
+    F: FnOnce(&mut GcpUartHandler) -> Result<T, GcpCommError> + Send + 'static,
+    T: Send + 'static,
+{
+    client(port_name)?.request(operation).await
+}
+
+impl GcpClient {
+    pub async fn request<F, T>(&self, operation: F) -> Result<T, GcpCommError>
+    where
+        F: FnOnce(&mut GcpUartHandler) -> Result<T, GcpCommError> + Send + 'static,
+        T: Send + 'static,
+    {
+        let not_connected = || GcpCommError::NotConnected { port_name: self.port_name.clone() };
+
+        let (reply, response) = oneshot::channel();
+        self.queue
+            .send(Box::new(move |handler| {
+                let _ = reply.send(operation(handler));
+            }))
+            .map_err(|_| not_connected())?;
+
+        // A request dropped unanswered means the connection closed before it ran
+        response.await.map_err(|_| not_connected())?
+    }
+}
+
+fn serve(port_name: String, id: u64, requests: Receiver<Request>) {
+    log::debug!(target: "gcp::client", "I/O worker started for {}", port_name);
+
+    while let Ok(request) = requests.recv() {
+        // Looked up per request: a reconnect may have replaced the handler
+        let Some(handler_arc) = get_connection_handle(&port_name) else {
+            break;
+        };
+        let Ok(mut handler) = handler_arc.lock() else {
+            break;
+        };
+        request(&mut handler);
+    }
+
+    // A newer client may have been registered for the same port name meanwhile
+    if let Ok(mut clients) = CLIENTS.lock() {
+        if clients.get(&port_name).is_some_and(|client| client.id == id) {
+            clients.remove(&port_name);
+        }
+    }
+    log::debug!(target: "gcp::client", "I/O worker stopped for {}", port_name);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gcp::{connect_with_transport, disconnect_from_port, GcpHelloResponse};
+    use crate::simulator::{spawn_memory_device, SimulatorConfig};
+
+    #[tokio::test]
+    async fn test_requests_await_the_worker() {
+        let port_name = "memory://test_async_client".to_string();
+        let config = SimulatorConfig::default();
+        connect_with_transport(port_name.clone(), Box::new(spawn_memory_device(config.clone()))).unwrap();
+
+        // Queued back to back, answered in order
+        let client = client(&port_name).unwrap();
+        let (hello, fw_version) = tokio::join!(
+            client.request(|handler| handler.send_hello()),
+            client.request(|handler| handler.get_fw_version()),
+        );
+        match hello.unwrap() {
+            GcpHelloResponse::Hardware(hardware) => assert_eq!(hardware.serial_number, config.hardware.serial_number),
+            other => panic!("Expected hardware data, got {:?}", other),
+        }
+        assert_eq!(fw_version.unwrap(), config.fw_version);
+
+        disconnect_from_port(port_name.clone()).unwrap();
+        assert!(matches!(request(&port_name, |handler| handler.ping()).await, Err(GcpCommError::NotConnected { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_old_worker_leaves_newer_client_registered() {
+        let port_name = "memory://test_async_client_reconnect".to_string();
+        let device = || Box::new(spawn_memory_device(SimulatorConfig::default()));
+        connect_with_transport(port_name.clone(), device()).unwrap();
+        let old_client = client(&port_name).unwrap();
+
+        // Reconnected under the same name while the old client is still around
+        disconnect_from_port(port_name.clone()).unwrap();
+        assert!(client(&port_name).is_err());
+        connect_with_transport(port_name.clone(), device()).unwrap();
+        let new_client = client(&port_name).unwrap();
+
+        // The old worker stops once its last client is gone
+        drop(old_client);
+        std::thread::sleep(std::time::Duration::from_millis(100));
+        assert_eq!(CLIENTS.lock().unwrap().get(&port_name).map(|client| client.id), Some(new_client.id));
+        new_client.request(|handler| handler.ping()).await.unwrap();
+
+        disconnect_from_port(port_name).unwrap();
+    }
+}
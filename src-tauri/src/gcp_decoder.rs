@@ -0,0 +1,271 @@
+//! Incremental GCP frame decoder
+//!
+//! Bytes go in as the transport returns them, in whatever pieces; complete frames come
+//! out. Bytes after a frame stay buffered for the next call, so back-to-back frames
+//! are never lost. Resynchronisation:
+//!
+//! - Bytes before `AA 55` are line noise and are dropped
+//! - A length no frame can have marks a false preamble: the decoder skips one byte and
+//!   rescans instead of waiting for a body that will never come
+//! - A frame whose CRC16 fails is reported once, then scanning resumes one byte past
+//!   its preamble, so a real frame behind a false one is still found
+//! - An intact frame with a MsgType outside the spec is returned as
+//!   `DecodedFrame::Unknown`; the caller decides whether it matters
+
+use std::collections::VecDeque;
+
+use crate::gcp::{GcpCommError, GcpCommand, GcpFrame, GcpMessage, GCP_PREAMBLE};
+
+// Largest frame the host accepts: its DMA RX buffer (§8)
+pub const GCP_MAX_FRAME_LEN: usize = 4095;
+// Length(2) + MsgType(2) + at least 2 bytes of parameters; nothing in the spec is shorter
+const GCP_MIN_LENGTH_FIELD: usize = 6;
+// Preamble(2) + CRC(2), which the Length field does not count
+const GCP_FRAME_OVERHEAD: usize = 4;
+
+#[derive(Debug, Clone)]
+pub enum DecodedFrame {
+    Frame(GcpFrame),
+    // A MsgType outside the spec, kept as GcpMessage::Unknown
+    Unknown(GcpMessage),
+}
+
+impl DecodedFrame {
+    pub fn msg_type(&self) -> u16 {
+        match self {
+            DecodedFrame::Frame(frame) => frame.msg_type as u16,
+            DecodedFrame::Unknown(message) => message.msg_type(),
+        }
+    }
+}
+
+pub struct GcpDecoder {
+    buffer: VecDeque<u8>,
+    max_frame_len: usize,
+    discarded: usize,
+}
+
+impl Default for GcpDecoder {
+    fn default() -> Self {
+        Self::new(GCP_MAX_FRAME_LEN)
+    }
+}
+
+impl GcpDecoder {
+    pub fn new(max_frame_len: usize) -> Self {
+        Self {
+            buffer: VecDeque::with_capacity(2 * max_frame_len),
+            max_frame_len,
+            discarded: 0,
+        }
+    }
+
+    pub fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend(bytes);
+    }
+
+    // Bytes received but not yet returned as a frame
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    // Bytes dropped as noise, false preambles or by clear()
+    pub fn discarded(&self) -> usize {
+        self.discarded
+    }
+
+    pub fn clear(&mut self) {
+        self.discarded += self.buffer.len();
+        self.buffer.clear();
+    }
+
+    // Next complete frame, or the error for a corrupt one; None until more bytes arrive
+    pub fn next_frame(&mut self) -> Option<Result<DecodedFrame, GcpCommError>> {
+        let bytes = self.peek_frame()?;
+        let raw_type = u16::from_le_bytes([bytes[4], bytes[5]]);
+        let decoded = match GcpCommand::try_from(raw_type) {
+            Ok(_) => GcpFrame::deserialize(&bytes).map(DecodedFrame::Frame),
+            Err(_) => GcpMessage::decode(&bytes).map(DecodedFrame::Unknown),
+        };
+        match decoded {
+            Ok(frame) => {
+                self.buffer.drain(..bytes.len());
+                Some(Ok(frame))
+            }
+            Err(e @ GcpCommError::Framing { .. }) => {
+                self.skip(1);
+                Some(Err(e))
+            }
+            Err(e) => {
+                self.skip(bytes.len());
+                Some(Err(e))
+            }
+        }
+    }
+
+    // Next complete frame as raw bytes, without checking its CRC16
+    pub fn next_raw_frame(&mut self) -> Option<Vec<u8>> {
+        let bytes = self.peek_frame()?;
+        self.buffer.drain(..bytes.len());
+        Some(bytes)
+    }
+
+    // Align on a plausible frame start and copy the frame out once all of it is buffered
+    fn peek_frame(&mut self) -> Option<Vec<u8>> {
+        loop {
+            self.sync();
+            if self.buffer.len() < 4 {
+                return None;
+            }
+
+            let length = u16::from_le_bytes([self.buffer[2], self.buffer[3]]) as usize;
+            if length < GCP_MIN_LENGTH_FIELD || length + GCP_FRAME_OVERHEAD > self.max_frame_len {
+                self.skip(1);
+                continue;
+            }
+
+            let frame_len = length + GCP_FRAME_OVERHEAD;
+            if self.buffer.len() < frame_len {
+                return None;
+            }
+            return Some(self.buffer.range(..frame_len).copied().collect());
+        }
+    }
+
+    // Drop everything before the next preamble
+    fn sync(&mut self) {
+        let found = (0..self.buffer.len().saturating_sub(1))
+            .find(|&i| self.buffer[i] == GCP_PREAMBLE[0] && self.buffer[i + 1] == GCP_PREAMBLE[1]);
+        let start = match found {
+            Some(start) => start,
+            // A trailing AA may be the first half of the next preamble
+            None if self.buffer.back() == Some(&GCP_PREAMBLE[0]) => self.buffer.len() - 1,
+            None => self.buffer.len(),
+        };
+        self.skip(start);
+    }
+
+    fn skip(&mut self, count: usize) {
+        self.buffer.drain(..count);
+        self.discarded += count;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gcp::GcpCommand;
+    use proptest::prelude::*;
+
+    const COMMANDS: [GcpCommand; 6] = [
+        GcpCommand::Hello,
+        GcpCommand::Ack,
+        GcpCommand::Nack,
+        GcpCommand::FwUpdateData,
+        GcpCommand::FwUpdateRequest,
+        GcpCommand::GetStatus,
+    ];
+
+    fn frame(msg_type: GcpCommand, payload: &[u8]) -> Vec<u8> {
+        GcpFrame::with_data(msg_type, Vec::new(), payload.to_vec()).serialize()
+    }
+
+    fn decode_all(decoder: &mut GcpDecoder) -> Vec<Result<Vec<u8>, GcpCommError>> {
+        std::iter::from_fn(|| decoder.next_frame())
+            .map(|result| result.map(|frame| match frame {
+                DecodedFrame::Frame(frame) => frame.serialize(),
+                DecodedFrame::Unknown(message) => message.encode(),
+            }))
+            .collect()
+    }
+
+    #[test]
+    fn test_back_to_back_frames_and_leftovers() {
+        let first = frame(GcpCommand::Ack, &[0x02, 0x10, 0, 0, 0, 0]);
+        let second = frame(GcpCommand::Ack, &[0x01, 0x20, 1, 2, 3]);
+
+        let mut decoder = GcpDecoder::default();
+        decoder.push(&[&first[..], &second[..], &second[..3]].concat());
+        assert_eq!(decode_all(&mut decoder), vec![Ok(first), Ok(second.clone())]);
+        assert_eq!(decoder.buffered(), 3);
+
+        decoder.push(&second[3..]);
+        assert_eq!(decode_all(&mut decoder), vec![Ok(second)]);
+        assert_eq!(decoder.discarded(), 0);
+    }
+
+    #[test]
+    fn test_resync_after_false_preamble() {
+        let real = frame(GcpCommand::Hello, &[1, 2, 3, 4, 5, 6, 7, 8]);
+
+        // Impossible length: rejected at once instead of waiting for 65535 bytes
+        let mut decoder = GcpDecoder::default();
+        decoder.push(&[&[0x00, 0xAA, 0x55, 0xFF, 0xFF][..], &real[..]].concat());
+        assert_eq!(decode_all(&mut decoder), vec![Ok(real.clone())]);
+        assert_eq!(decoder.discarded(), 5);
+
+        // Plausible length but bad CRC16: reported, then the real frame inside it is found
+        let mut false_frame = vec![0xAA, 0x55, 0x08, 0x00, 0x02];
+        false_frame.extend_from_slice(&real);
+        let mut decoder = GcpDecoder::default();
+        decoder.push(&false_frame);
+        let results = decode_all(&mut decoder);
+        assert_eq!(results.len(), 2);
+        assert!(matches!(results[0], Err(GcpCommError::Framing { .. })));
+        assert_eq!(results[1], Ok(real));
+    }
+
+    #[test]
+    fn test_unknown_msg_type_is_returned_raw() {
+        let unknown = GcpMessage::Unknown(0x3001, vec![0xDE, 0xAD]).encode();
+        let next = frame(GcpCommand::Ack, &[0x01, 0x20, 0, 0, 0, 0]);
+
+        let mut decoder = GcpDecoder::default();
+        decoder.push(&[&unknown[..], &next[..]].concat());
+        match decoder.next_frame() {
+            Some(Ok(DecodedFrame::Unknown(message))) => assert_eq!(message, GcpMessage::Unknown(0x3001, vec![0xDE, 0xAD])),
+            other => panic!("Expected an unknown frame, got {:?}", other),
+        }
+        assert_eq!(decode_all(&mut decoder), vec![Ok(next)]);
+        assert_eq!(decoder.discarded(), 0);
+    }
+
+    proptest! {
+        #[test]
+        fn prop_frames_survive_any_split(
+            frames in prop::collection::vec((0..COMMANDS.len(), prop::collection::vec(any::<u8>(), 2..80)), 1..8),
+            noise in prop::collection::vec(prop::collection::vec(any::<u8>().prop_filter("not a preamble", |b| *b != 0xAA), 0..16), 8),
+            splits in prop::collection::vec(1usize..48, 1..64),
+        ) {
+            let expected: Vec<Vec<u8>> = frames.iter().map(|(command, payload)| frame(COMMANDS[*command], payload)).collect();
+            let stream: Vec<u8> = expected.iter().zip(&noise).flat_map(|(frame, noise)| [noise.clone(), frame.clone()].concat()).collect();
+
+            // Feed the stream in arbitrary pieces, draining frames after every read
+            let mut decoder = GcpDecoder::default();
+            let mut decoded = Vec::new();
+            let mut rest = stream.as_slice();
+            for split in splits.iter().cycle() {
+                if rest.is_empty() {
+                    break;
+                }
+                let (piece, tail) = rest.split_at((*split).min(rest.len()));
+                decoder.push(piece);
+                decoded.extend(decode_all(&mut decoder));
+                rest = tail;
+            }
+
+            prop_assert_eq!(decoded, expected.into_iter().map(Ok).collect::<Vec<_>>());
+            prop_assert_eq!(decoder.buffered(), 0);
+        }
+
+        #[test]
+        fn prop_arbitrary_bytes_never_panic(bytes in prop::collection::vec(any::<u8>(), 0..512), split in 1usize..64) {
+            let mut decoder = GcpDecoder::default();
+            for piece in bytes.chunks(split) {
+                decoder.push(piece);
+                while decoder.next_frame().is_some() {}
+            }
+            prop_assert!(decoder.buffered() <= bytes.len());
+        }
+    }
+}
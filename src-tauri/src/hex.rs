@@ -0,0 +1,51 @@
+//! Hex strings in Intel HEX records, trusted key lists and protocol captures
+//!
+//! Everything here comes from files a user can edit, so malformed input is an error
+//! and never a panic: digits are decoded byte by byte rather than by slicing the
+//! string, which would split multi-byte characters.
+
+// Lowercase, two digits per byte
+pub fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+// Upper- or lowercase digits, no separators
+pub fn decode_hex(hex: &str) -> Result<Vec<u8>, String> {
+    let digits = hex.as_bytes();
+    if digits.len() % 2 != 0 {
+        return Err("odd number of hex digits".to_string());
+    }
+
+    digits
+        .chunks(2)
+        .map(|pair| match (hex_digit(pair[0]), hex_digit(pair[1])) {
+            (Some(high), Some(low)) => Ok((high << 4) | low),
+            _ => Err("invalid hex digit".to_string()),
+        })
+        .collect()
+}
+
+fn hex_digit(digit: u8) -> Option<u8> {
+    (digit as char).to_digit(16).map(|value| value as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hex_round_trip_and_invalid_input() {
+        let bytes = [0x00, 0x5A, 0xAA, 0xFF];
+        assert_eq!(encode_hex(&bytes), "005aaaff");
+        assert_eq!(decode_hex("005aaaff").unwrap(), bytes);
+        assert_eq!(decode_hex("005AAAFF").unwrap(), bytes);
+        assert_eq!(decode_hex("").unwrap(), Vec::<u8>::new());
+
+        assert!(decode_hex("abc").is_err());
+        assert!(decode_hex("0g").is_err());
+        assert!(decode_hex("+1").is_err());
+        // Multi-byte characters whose bytes straddle a digit pair
+        assert!(decode_hex("aé0").is_err());
+        assert!(decode_hex("aéé0").is_err());
+    }
+}
@@ -1,14 +1,50 @@
 // Learn more about Tauri commands at https://tauri.app/v1/guides/features/command
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::collections::BTreeMap;
 use serde::{Deserialize, Serialize};
 use serialport::{SerialPortInfo, SerialPortType};
-use tauri::Emitter;
-
-mod gcp;
-use gcp::{GcpStatusData, GcpFwVersionData, GcpHardwareData, ConnectionState, connect_to_port, disconnect_from_port, get_connection_status, execute_with_connection, GCP_RECOMMENDED_CHUNK_SIZE, gcp_crc32};
-
-#[derive(Debug, Serialize, Deserialize)]
+use tauri::Manager;
+
+mod app_store;
+pub mod capture;
+pub mod fault_injection;
+mod connection_monitor;
+mod discovery;
+mod firmware_batch;
+mod firmware_checkpoint;
+mod firmware_image;
+mod firmware_job;
+mod firmware_repository;
+mod firmware_signing;
+mod firmware_transfer;
+mod fw_update_listener;
+pub mod gcp;
+mod gcp_client;
+pub mod gcp_decoder;
+mod hex;
+pub mod link_config;
+mod port_watcher;
+mod protocol_log;
+mod reconnect;
+pub mod simulator;
+pub mod transport;
+use capture::{CaptureSummary, ReplayReport};
+use discovery::{DiscoveryReport, DISCOVERY_DEFAULT_TIMEOUT_MS};
+use firmware_batch::{FirmwareBatchOptions, FirmwareBatchStatus};
+use firmware_checkpoint::FirmwareCheckpoint;
+use firmware_image::{load_firmware_image, FirmwareImage, FirmwareImageFormat, FIRMWARE_DEFAULT_PAD_BYTE};
+use firmware_job::{FirmwareJobOptions, FirmwareJobStatus};
+use firmware_repository::{FirmwareRelease, FirmwareRepository};
+use firmware_signing::FirmwareTrustConfig;
+use firmware_transfer::ThroughputSample;
+use link_config::LinkConfig;
+use port_watcher::PortWatchConfig;
+use protocol_log::{LogLevel, ProtocolEvent};
+use simulator::{SimulatorConfig, SIMULATOR_PORT_PREFIX};
+use gcp::{GcpStatusData, GcpFwVersionData, GcpHelloResponse, GcpSpecRevision, GcpDiagnosticsData, GcpConfig, GcpRtcTime, GcpCommError, ConnectionState, connect_to_port, disconnect_from_port, get_connection_status, gcp_crc32};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct COMPortInfo {
     pub port: String,
     pub description: String,
@@ -19,67 +55,40 @@ pub struct COMPortInfo {
     pub port_type: String,
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct FirmwareUpdateProgress {
-    pub stage: String,
-    pub current_chunk: u32,
-    pub total_chunks: u32,
-    pub bytes_sent: u32,
-    pub total_bytes: u32,
-    pub percentage: f64,
-    pub status: String,
-}
-
-#[derive(Debug, Serialize, Deserialize)]
-pub struct FirmwareUpdateResult {
-    pub success: bool,
-    pub message: String,
-    pub crc32_match: bool,
-    pub total_chunks: u32,
-    pub total_bytes: u32,
-}
-
 #[tauri::command]
-fn list_com_ports() -> Result<Vec<COMPortInfo>, String> {
+fn list_com_ports() -> Result<Vec<COMPortInfo>, GcpCommError> {
     match serialport::available_ports() {
         Ok(ports) => {
-            let port_info: Vec<COMPortInfo> = ports
-                .into_iter()
-                .map(|port| COMPortInfo {
-                    port: port.port_name.clone(),
-                    description: format_port_description(&port),
-                    manufacturer: extract_manufacturer(&port.port_type),
-                    serial_number: extract_serial_number(&port.port_type),
-                    vendor_id: extract_vendor_id(&port.port_type),
-                    product_id: extract_product_id(&port.port_type),
-                    port_type: format_port_type(&port.port_type),
-                })
-                .collect();
-            Ok(port_info)
+            let port_info: Vec<COMPortInfo> = ports.iter().map(com_port_info).collect();
+            Ok(with_simulated_port(port_info))
         }
-        Err(e) => Err(format!("Failed to list COM ports: {}", e)),
+        Err(e) => Err(GcpCommError::internal(format!("Failed to list COM ports: {}", e))),
     }
 }
 
 #[tauri::command]
-fn get_port_info(port_name: String) -> Result<Option<COMPortInfo>, String> {
+fn get_port_info(port_name: String) -> Result<Option<COMPortInfo>, GcpCommError> {
     match serialport::available_ports() {
         Ok(ports) => {
             let port_info = ports
-                .into_iter()
+                .iter()
                 .find(|port| port.port_name == port_name)
-                .map(|port| COMPortInfo {
-                    port: port.port_name.clone(),
-                    description: format_port_description(&port),
-                    manufacturer: extract_manufacturer(&port.port_type),
-                    serial_number: extract_serial_number(&port.port_type),
-                    vendor_id: extract_vendor_id(&port.port_type),
-                    product_id: extract_product_id(&port.port_type),
-                    port_type: format_port_type(&port.port_type),
-                });
+                .map(com_port_info);
             Ok(port_info)
         }
-        Err(e) => Err(format!("Failed to get port info: {}", e)),
+        Err(e) => Err(GcpCommError::internal(format!("Failed to get port info: {}", e))),
+    }
+}
+
+fn com_port_info(port: &SerialPortInfo) -> COMPortInfo {
+    COMPortInfo {
+        port: port.port_name.clone(),
+        description: format_port_description(port),
+        manufacturer: extract_manufacturer(&port.port_type),
+        serial_number: extract_serial_number(&port.port_type),
+        vendor_id: extract_vendor_id(&port.port_type),
+        product_id: extract_product_id(&port.port_type),
+        port_type: format_port_type(&port.port_type),
     }
 }
 
@@ -126,6 +135,23 @@ fn extract_product_id(port_type: &SerialPortType) -> Option<u16> {
     }
 }
 
+// Debug builds list an in-process simulated device so the UI works without hardware
+fn with_simulated_port(mut ports: Vec<COMPortInfo>) -> Vec<COMPortInfo> {
+    if cfg!(debug_assertions) {
+        let serial_number = SimulatorConfig::default().hardware.serial_number;
+        ports.push(COMPortInfo {
+            port: format!("{}{}", SIMULATOR_PORT_PREFIX, serial_number),
+            description: "Simulated Glitchi (GCP v2.2)".to_string(),
+            manufacturer: None,
+            serial_number: Some(serial_number.to_string()),
+            vendor_id: None,
+            product_id: None,
+            port_type: "Simulator".to_string(),
+        });
+    }
+    ports
+}
+
 fn format_port_type(port_type: &SerialPortType) -> String {
     match port_type {
         SerialPortType::UsbPort(_) => "USB".to_string(),
@@ -136,18 +162,109 @@ fn format_port_type(port_type: &SerialPortType) -> String {
 }
 
 // Connection Management Commands
+// Opening the port and probing it block, as does joining the background threads on
+// disconnect; both run off the async runtime's workers
 #[tauri::command]
-fn connect_port(port_name: String) -> Result<String, String> {
-    connect_to_port(port_name)
+async fn connect_port(port_name: String, link_config: Option<LinkConfig>, app: tauri::AppHandle) -> Result<String, GcpCommError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        // A config given here becomes the port's saved profile
+        let link = match link_config {
+            Some(config) => link_config::save_profile(&port_name, config)?,
+            None => link_config::profile_for(&port_name),
+        };
+        let message = connect_to_port(port_name.clone(), link)?;
+        fw_update_listener::start_listener(app.clone(), port_name.clone())?;
+        connection_monitor::start_monitor(app, port_name)?;
+        Ok(message)
+    })
+    .await
+    .map_err(|e| GcpCommError::internal(format!("Connect failed: {}", e)))?
 }
 
 #[tauri::command]
-fn disconnect_port(port_name: String) -> Result<String, String> {
-    disconnect_from_port(port_name)
+async fn disconnect_port(port_name: String, app: tauri::AppHandle) -> Result<String, GcpCommError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        fw_update_listener::stop_listener(&port_name);
+        connection_monitor::stop_monitor(&port_name);
+        reconnect::cancel_reconnect(&port_name);
+        gcp_client::close_client(&port_name);
+        if let Some(job) = firmware_job::active_job_for_port(&port_name) {
+            firmware_job::cancel_job(&job.job_id)?;
+        }
+        let message = disconnect_from_port(port_name.clone())?;
+        connection_monitor::report_state(&app, &port_name, ConnectionState::Disconnected);
+        Ok(message)
+    })
+    .await
+    .map_err(|e| GcpCommError::internal(format!("Disconnect failed: {}", e)))?
 }
 
+// Saved link parameters by port name; ports without a profile use the spec defaults
 #[tauri::command]
-fn get_port_connection_status(port_name: String) -> Result<String, String> {
+fn get_link_profiles() -> BTreeMap<String, LinkConfig> {
+    link_config::profiles()
+}
+
+// Takes effect the next time the port is connected
+#[tauri::command]
+fn save_link_profile(port_name: String, config: LinkConfig) -> Result<LinkConfig, GcpCommError> {
+    link_config::save_profile(&port_name, config)
+}
+
+#[tauri::command]
+fn delete_link_profile(port_name: String) -> Result<(), GcpCommError> {
+    link_config::delete_profile(&port_name)
+}
+
+#[tauri::command]
+fn get_protocol_console_level() -> LogLevel {
+    protocol_log::console_level()
+}
+
+// Lowest severity streamed as "protocol-log"; Trace includes raw frame bytes
+#[tauri::command]
+fn set_protocol_console_level(level: LogLevel) -> LogLevel {
+    protocol_log::set_console_level(level)
+}
+
+// Recent events at the console level, oldest first
+#[tauri::command]
+fn get_protocol_log() -> Vec<ProtocolEvent> {
+    protocol_log::recent_events()
+}
+
+// Record the port's raw traffic to `path`, or to a new file under the app data
+// directory's "captures" folder; returns the capture file
+#[tauri::command]
+async fn start_protocol_capture(port_name: String, path: Option<String>, app: tauri::AppHandle) -> Result<String, GcpCommError> {
+    let path = match path {
+        Some(path) => PathBuf::from(path),
+        None => {
+            let dir = app.path().app_data_dir()
+                .map_err(|e| GcpCommError::internal(format!("No app data directory: {}", e)))?;
+            capture::default_capture_path(&dir.join("captures"), &port_name)
+        }
+    };
+    let capture_path = path.display().to_string();
+    gcp_client::request(&port_name, move |handler| handler.start_capture(&path)).await?;
+    Ok(capture_path)
+}
+
+#[tauri::command]
+async fn stop_protocol_capture(port_name: String) -> Result<Option<CaptureSummary>, GcpCommError> {
+    gcp_client::request(&port_name, |handler| Ok(handler.stop_capture())).await
+}
+
+// Run a capture's requests through the protocol handler again, without a device
+#[tauri::command]
+async fn replay_protocol_capture(path: String) -> Result<ReplayReport, GcpCommError> {
+    tauri::async_runtime::spawn_blocking(move || capture::replay_capture(Path::new(&path)))
+        .await
+        .map_err(|e| GcpCommError::internal(format!("Replay failed: {}", e)))?
+}
+
+#[tauri::command]
+fn get_port_connection_status(port_name: String) -> Result<String, GcpCommError> {
     match get_connection_status(port_name)? {
         ConnectionState::Connected => Ok("Connected".to_string()),
         ConnectionState::Disconnected => Ok("Disconnected".to_string()),
@@ -155,194 +272,298 @@ fn get_port_connection_status(port_name: String) -> Result<String, String> {
     }
 }
 
+// Send HELLO on every listed port and report which ones are Glitchi devices.
+// Ports opened for this are closed again before it returns.
+#[tauri::command]
+async fn discover_devices(timeout_ms: Option<u64>) -> Result<DiscoveryReport, GcpCommError> {
+    let ports = list_com_ports()?;
+    let timeout_ms = timeout_ms.unwrap_or(DISCOVERY_DEFAULT_TIMEOUT_MS);
+    // Probing blocks on serial I/O; keep it off the async runtime's workers
+    tauri::async_runtime::spawn_blocking(move || discovery::discover_devices(ports, timeout_ms))
+        .await
+        .map_err(|e| GcpCommError::internal(format!("Discovery failed: {}", e)))
+}
+
+#[tauri::command]
+fn get_port_watch_config() -> Result<PortWatchConfig, GcpCommError> {
+    port_watcher::get_watch_config()
+}
+
+// Scan interval and optional USB VID/PID filter for "port-added"/"port-removed" events
+#[tauri::command]
+fn set_port_watch_config(config: PortWatchConfig) -> Result<PortWatchConfig, GcpCommError> {
+    port_watcher::set_watch_config(config)
+}
+
+// How long a connection may be idle before the health monitor sends PING
+#[tauri::command]
+fn get_health_check_interval() -> u64 {
+    connection_monitor::ping_interval().as_millis() as u64
+}
+
+#[tauri::command]
+fn set_health_check_interval(interval_ms: u64) -> Result<u64, GcpCommError> {
+    connection_monitor::set_ping_interval(interval_ms)
+}
+
 // GCP Commands using persistent connections
 #[tauri::command]
-fn gcp_send_hello(port_name: String) -> Result<GcpHardwareData, String> {
-    execute_with_connection(&port_name, |handler| handler.send_hello())
+async fn gcp_send_hello(port_name: String) -> Result<GcpHelloResponse, GcpCommError> {
+    gcp_client::request(&port_name, |handler| handler.send_hello()).await
+}
+
+// Older devices (GCP v2.0/v2.1) must be switched to their payload layout explicitly
+#[tauri::command]
+async fn gcp_set_spec_revision(port_name: String, revision: GcpSpecRevision) -> Result<GcpSpecRevision, GcpCommError> {
+    gcp_client::request(&port_name, move |handler| {
+        handler.set_spec_revision(revision);
+        Ok(revision)
+    }).await
+}
+
+#[tauri::command]
+async fn gcp_get_spec_revision(port_name: String) -> Result<GcpSpecRevision, GcpCommError> {
+    gcp_client::request(&port_name, |handler| Ok(handler.spec_revision())).await
+}
+
+#[tauri::command]
+async fn gcp_get_status(port_name: String) -> Result<GcpStatusData, GcpCommError> {
+    gcp_client::request(&port_name, |handler| handler.get_status()).await
+}
+
+#[tauri::command]
+async fn gcp_get_fw_version(port_name: String) -> Result<GcpFwVersionData, GcpCommError> {
+    gcp_client::request(&port_name, |handler| handler.get_fw_version()).await
 }
 
 #[tauri::command]
-fn gcp_get_status(port_name: String) -> Result<GcpStatusData, String> {
-    execute_with_connection(&port_name, |handler| handler.get_status())
+async fn gcp_get_diagnostics(port_name: String) -> Result<GcpDiagnosticsData, GcpCommError> {
+    gcp_client::request(&port_name, |handler| handler.get_diagnostics()).await
 }
 
+// SET_CONFIG Commands
 #[tauri::command]
-fn gcp_get_fw_version(port_name: String) -> Result<GcpFwVersionData, String> {
-    execute_with_connection(&port_name, |handler| handler.get_fw_version())
+async fn gcp_set_rtc_time(port_name: String, time: GcpRtcTime) -> Result<String, GcpCommError> {
+    gcp_client::request(&port_name, move |handler| {
+        handler.set_config(GcpConfig::Time(time))?;
+        Ok(format!("Device RTC set to 20{:02}-{:02}-{:02} {:02}:{:02}:{:02}",
+                   time.year, time.month, time.day, time.hour, time.minute, time.second))
+    }).await
+}
+
+#[tauri::command]
+async fn gcp_sync_device_time(port_name: String) -> Result<GcpRtcTime, GcpCommError> {
+    gcp_client::request(&port_name, move |handler| {
+        // Sample the host clock as late as possible so the device is set to the current second
+        let time = GcpRtcTime::now_local()?;
+        handler.set_config(GcpConfig::Time(time))?;
+        Ok(time)
+    }).await
+}
+
+#[tauri::command]
+async fn gcp_set_brightness(port_name: String, brightness: u8) -> Result<String, GcpCommError> {
+    gcp_client::request(&port_name, move |handler| {
+        handler.set_config(GcpConfig::Brightness(brightness))?;
+        Ok(format!("Device brightness set to {}%", brightness))
+    }).await
+}
+
+#[tauri::command]
+async fn gcp_set_sound(port_name: String, enabled: bool) -> Result<String, GcpCommError> {
+    gcp_client::request(&port_name, move |handler| {
+        handler.set_config(GcpConfig::Sound(enabled))?;
+        Ok(format!("Device sound turned {}", if enabled { "on" } else { "off" }))
+    }).await
 }
 
 // Firmware Update Commands
+// The update runs as a background job; progress arrives as "firmware-progress"
+// and "firmware-job-updated" events
 #[tauri::command]
 async fn gcp_firmware_update(
     port_name: String, 
     file_path: String, 
-    window: tauri::Window
-) -> Result<FirmwareUpdateResult, String> {
-    use std::time::Instant;
-    
-    // Read firmware file
-    let firmware_data = match fs::read(&file_path) {
-        Ok(data) => data,
-        Err(e) => return Err(format!("Failed to read firmware file: {}", e))
-    };
+    resume: Option<bool>,
+    pad_byte: Option<u8>,
+    app: tauri::AppHandle
+) -> Result<FirmwareJobStatus, GcpCommError> {
+    // Reading and flattening a large image blocks
+    tauri::async_runtime::spawn_blocking(move || {
+        // Intel HEX files are flattened; the device only ever receives the binary image
+        let image = load_firmware_image(&file_path, pad_byte.unwrap_or(FIRMWARE_DEFAULT_PAD_BYTE))?;
+
+        let options = FirmwareJobOptions {
+            image_path: Some(file_path),
+            resume: resume.unwrap_or(false),
+            checkpoint_dir: firmware_checkpoint::checkpoint_dir().ok(),
+        };
+        firmware_job::start_job(app, &port_name, image, options)
+    })
+    .await
+    .map_err(|e| GcpCommError::internal(format!("Failed to start firmware update: {}", e)))?
+}
 
-    let total_bytes = firmware_data.len() as u32;
-    let chunk_size = GCP_RECOMMENDED_CHUNK_SIZE;
-    let total_chunks = ((total_bytes as usize + chunk_size - 1) / chunk_size) as u32;
-    let firmware_crc32 = gcp_crc32(&firmware_data);
-
-    println!("Starting firmware update: {} bytes, {} chunks, CRC32: {:08X}", 
-           total_bytes, total_chunks, firmware_crc32);
-
-    // Helper function to emit progress
-    let emit_progress = |stage: &str, current: u32, status: &str, bytes_sent: u32| {
-        let progress = FirmwareUpdateProgress {
-            stage: stage.to_string(),
-            current_chunk: current,
-            total_chunks,
-            bytes_sent,
-            total_bytes,
-            percentage: (bytes_sent as f64 / total_bytes as f64) * 100.0,
-            status: status.to_string(),
+// Flash one image to many devices at once (production line). Each port runs its own
+// job; "firmware-batch-completed" carries the per-serial-number report.
+#[tauri::command]
+async fn gcp_start_firmware_batch(
+    port_names: Vec<String>,
+    file_path: String,
+    reset: Option<bool>,
+    pad_byte: Option<u8>,
+    app: tauri::AppHandle
+) -> Result<FirmwareBatchStatus, GcpCommError> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let image = load_firmware_image(&file_path, pad_byte.unwrap_or(FIRMWARE_DEFAULT_PAD_BYTE))?;
+
+        let options = FirmwareBatchOptions {
+            image_path: Some(file_path),
+            reset: reset.unwrap_or(true),
         };
-        let _ = window.emit("firmware-progress", &progress);
-    };
+        firmware_batch::start_batch(app, port_names, image, options)
+    })
+    .await
+    .map_err(|e| GcpCommError::internal(format!("Failed to start firmware batch: {}", e)))?
+}
 
-    // Execute the firmware update with the connection
-    let result = execute_with_connection(&port_name, |handler| {
-        let start_time = Instant::now();
+#[tauri::command]
+fn gcp_get_firmware_batch(batch_id: String) -> Result<FirmwareBatchStatus, GcpCommError> {
+    firmware_batch::batch_status(&batch_id)
+}
 
-        // Stage 1: Start firmware update
-        emit_progress("Initiating", 0, "Sending firmware update start command...", 0);
-        
-        match handler.start_firmware_update(&firmware_data, chunk_size as u16) {
-            Ok(()) => {
-                emit_progress("Initiated", 0, "Device acknowledged firmware update start", 0);
-            }
-            Err(e) => {
-                return Err(format!("Failed to start firmware update: {}", e));
-            }
-        }
+#[tauri::command]
+fn gcp_cancel_firmware_batch(batch_id: String) -> Result<FirmwareBatchStatus, GcpCommError> {
+    firmware_batch::cancel_batch(&batch_id)
+}
 
-        // Stage 2: Send firmware chunks
-        emit_progress("Transferring", 0, "Starting firmware data transfer...", 0);
-        
-        let mut bytes_sent = 0u32;
-        
-        for chunk_index in 0..total_chunks {
-            let chunk_start = (chunk_index as usize) * chunk_size;
-            let chunk_end = std::cmp::min(chunk_start + chunk_size, firmware_data.len());
-            let chunk_data = &firmware_data[chunk_start..chunk_end];
-            
-            let status_msg = format!("Sending chunk {} of {} ({} bytes)", 
-                                   chunk_index + 1, total_chunks, chunk_data.len());
-            emit_progress("Transferring", chunk_index + 1, &status_msg, bytes_sent);
-
-            match handler.send_firmware_chunk(chunk_data, chunk_start as u32) {
-                Ok(()) => {
-                    bytes_sent += chunk_data.len() as u32;
-                    
-                    // Emit progress every few chunks or at the end
-                    if chunk_index % 5 == 0 || chunk_index == total_chunks - 1 {
-                        let progress_msg = format!("Sent chunk {} of {} ({:.1}%)", 
-                                                 chunk_index + 1, total_chunks,
-                                                 (bytes_sent as f64 / total_bytes as f64) * 100.0);
-                        emit_progress("Transferring", chunk_index + 1, &progress_msg, bytes_sent);
-                    }
-                }
-                Err(e) => {
-                    let error_msg = format!("Failed to send chunk {}: {}", chunk_index, e);
-                    emit_progress("Error", chunk_index, &error_msg, bytes_sent);
-                    return Err(error_msg);
-                }
-            }
-        }
+#[tauri::command]
+fn gcp_get_firmware_job(job_id: String) -> Result<FirmwareJobStatus, GcpCommError> {
+    firmware_job::job_status(&job_id)
+}
 
-        // Stage 3: End firmware update and verify
-        emit_progress("Verifying", total_chunks, "Requesting firmware verification...", bytes_sent);
-
-        match handler.end_firmware_update() {
-            Ok(crc_match) => {
-                let elapsed = start_time.elapsed();
-                let transfer_rate = (bytes_sent as f64) / elapsed.as_secs_f64();
-                
-                if crc_match {
-                    let success_msg = format!("Firmware update completed successfully in {:.1}s ({:.1} KB/s)", 
-                                             elapsed.as_secs_f64(), transfer_rate / 1024.0);
-                    emit_progress("Completed", total_chunks, &success_msg, bytes_sent);
-                    
-                    Ok(FirmwareUpdateResult {
-                        success: true,
-                        message: success_msg,
-                        crc32_match: true,
-                        total_chunks,
-                        total_bytes: bytes_sent,
-                    })
-                } else {
-                    let error_msg = "Firmware verification failed - CRC32 mismatch".to_string();
-                    emit_progress("Failed", total_chunks, &error_msg, bytes_sent);
-                    
-                    Ok(FirmwareUpdateResult {
-                        success: false,
-                        message: error_msg,
-                        crc32_match: false,
-                        total_chunks,
-                        total_bytes: bytes_sent,
-                    })
-                }
-            }
-            Err(e) => {
-                let error_msg = format!("Firmware verification failed: {}", e);
-                emit_progress("Failed", total_chunks, &error_msg, bytes_sent);
-                
-                Err(error_msg)
-            }
+#[tauri::command]
+fn gcp_pause_firmware_job(job_id: String) -> Result<FirmwareJobStatus, GcpCommError> {
+    firmware_job::pause_job(&job_id)
+}
+
+#[tauri::command]
+fn gcp_resume_firmware_job(job_id: String) -> Result<FirmwareJobStatus, GcpCommError> {
+    firmware_job::resume_job(&job_id)
+}
+
+#[tauri::command]
+fn gcp_cancel_firmware_job(job_id: String) -> Result<FirmwareJobStatus, GcpCommError> {
+    firmware_job::cancel_job(&job_id)
+}
+
+// Measured throughput of recent successful transfers, oldest first
+#[tauri::command]
+fn get_firmware_throughput_history() -> Vec<ThroughputSample> {
+    firmware_transfer::throughput_history()
+}
+
+// Interrupted transfer for a device (serial number from HELLO), if any
+#[tauri::command]
+fn gcp_get_firmware_checkpoint(device_serial: u16) -> Result<Option<FirmwareCheckpoint>, GcpCommError> {
+    firmware_checkpoint::load_checkpoint(&firmware_checkpoint::checkpoint_dir()?, device_serial)
+}
+
+#[tauri::command]
+fn gcp_discard_firmware_checkpoint(device_serial: u16) -> Result<String, GcpCommError> {
+    firmware_checkpoint::clear_checkpoint(&firmware_checkpoint::checkpoint_dir()?, device_serial)?;
+    Ok(format!("Discarded firmware checkpoint for device {}", device_serial))
+}
+
+#[tauri::command]
+fn get_firmware_trust_config() -> FirmwareTrustConfig {
+    firmware_signing::trust_config()
+}
+
+// Device-initiated update checks are answered from this directory
+#[tauri::command]
+fn set_firmware_repository(path: Option<String>) -> Result<Vec<FirmwareRelease>, GcpCommError> {
+    match path {
+        Some(path) => {
+            let repository = FirmwareRepository::new(path)?;
+            let releases = repository.releases()?;
+            firmware_repository::set_firmware_repository(Some(repository))?;
+            Ok(releases)
         }
-    });
+        None => {
+            firmware_repository::set_firmware_repository(None)?;
+            Ok(Vec::new())
+        }
+    }
+}
 
-    result
+#[tauri::command]
+fn get_firmware_repository() -> Result<serde_json::Value, GcpCommError> {
+    match firmware_repository::get_firmware_repository() {
+        Some(repository) => Ok(serde_json::json!({
+            "path": repository.dir(),
+            "releases": repository.releases()?,
+        })),
+        None => Ok(serde_json::Value::Null),
+    }
 }
 
 #[tauri::command]
-fn gcp_abort_firmware_update(port_name: String) -> Result<String, String> {
-    execute_with_connection(&port_name, |handler| {
+async fn gcp_abort_firmware_update(port_name: String) -> Result<String, GcpCommError> {
+    // A running job owns the transfer; it sends FW_UPDATE_ABORT before its next chunk
+    if let Some(job) = firmware_job::active_job_for_port(&port_name) {
+        firmware_job::cancel_job(&job.job_id)?;
+        return Ok(format!("Cancelling firmware update job {}", job.job_id));
+    }
+
+    gcp_client::request(&port_name, move |handler| {
         handler.abort_firmware_update()?;
         Ok("Firmware update aborted".to_string())
-    })
+    }).await
 }
 
 #[tauri::command]
-fn gcp_send_firmware_chunk(port_name: String, chunk_data: Vec<u8>, sequence_number: u32) -> Result<String, String> {
-    execute_with_connection(&port_name, |handler| {
+async fn gcp_send_firmware_chunk(port_name: String, chunk_data: Vec<u8>, sequence_number: u32) -> Result<String, GcpCommError> {
+    gcp_client::request(&port_name, move |handler| {
         handler.send_firmware_chunk_single_try(&chunk_data, sequence_number)?;
         Ok(format!("Successfully sent {} bytes with sequence number {}", chunk_data.len(), sequence_number))
-    })
+    }).await
 }
 
 #[tauri::command]
-fn gcp_start_firmware_update(port_name: String, firmware_data: Vec<u8>, chunk_size: u16) -> Result<String, String> {
-    execute_with_connection(&port_name, |handler| {
+async fn gcp_start_firmware_update(port_name: String, firmware_data: Vec<u8>, chunk_size: u16) -> Result<String, GcpCommError> {
+    // Raw test payloads are unsigned, so this only works with the developer override
+    firmware_signing::verify_firmware_image(&FirmwareImage::from_binary(firmware_data.clone()), None)?;
+
+    gcp_client::request(&port_name, move |handler| {
         handler.start_firmware_update(&firmware_data, chunk_size)?;
         Ok(format!("Firmware update started for {} bytes", firmware_data.len()))
-    })
+    }).await
 }
 
+// The device reboots; the connection is restored in the background once it answers
+// HELLO again, and "device-rebooted" reports its firmware version
 #[tauri::command]
-fn gcp_reset_device(port_name: String, apply_firmware: bool) -> Result<String, String> {
+async fn gcp_reset_device(port_name: String, apply_firmware: bool, app: tauri::AppHandle) -> Result<String, GcpCommError> {
     let reset_type = if apply_firmware { 0x0002 } else { 0x0001 };
-    execute_with_connection(&port_name, |handler| {
+    let reset_port = port_name.clone();
+    let identity = gcp_client::request(&port_name, move |handler| {
+        let identity = reconnect::DeviceIdentity::capture(&reset_port, handler);
         handler.reset_device(reset_type)?;
-        Ok(if apply_firmware {
-            "Device reset with firmware application initiated".to_string()
-        } else {
-            "Device software reset initiated".to_string()
-        })
+        Ok(identity)
+    }).await?;
+    reconnect::start_reconnect(app, port_name, identity)?;
+
+    Ok(if apply_firmware {
+        "Device reset with firmware application initiated".to_string()
+    } else {
+        "Device software reset initiated".to_string()
     })
 }
 
 // Debug command to test CRC calculations
 #[tauri::command]
-fn test_gcp_frame_construction() -> Result<String, String> {
+fn test_gcp_frame_construction() -> Result<String, GcpCommError> {
     use gcp::{GcpFrame, GcpCommand, gcp_crc16};
     
     // Test a simple HELLO frame first
@@ -394,31 +615,39 @@ fn test_gcp_frame_construction() -> Result<String, String> {
 }
 
 #[tauri::command]
-fn get_firmware_file_info(file_path: String) -> Result<serde_json::Value, String> {
+async fn get_firmware_file_info(file_path: String, pad_byte: Option<u8>, port_name: Option<String>) -> Result<serde_json::Value, GcpCommError> {
+    // Reading, flattening and checking the signature of a large image blocks
+    tauri::async_runtime::spawn_blocking(move || firmware_file_info(file_path, pad_byte, port_name))
+        .await
+        .map_err(|e| GcpCommError::internal(format!("Failed to analyze firmware file: {}", e)))?
+}
+
+fn firmware_file_info(file_path: String, pad_byte: Option<u8>, port_name: Option<String>) -> Result<serde_json::Value, GcpCommError> {
     let path = Path::new(&file_path);
     
     // Validate file
     if let Some(extension) = path.extension() {
         if !["bin", "hex", "fw"].contains(&extension.to_str().unwrap_or("")) {
-            return Err("Only .bin, .hex, and .fw files are supported".to_string());
+            return Err(GcpCommError::file(path, "Only .bin, .hex, and .fw files are supported"));
         }
     } else {
-        return Err("File must have .bin, .hex, or .fw extension".to_string());
+        return Err(GcpCommError::file(path, "File must have .bin, .hex, or .fw extension"));
     }
 
-    // Read and analyze firmware file
-    let firmware_data = match fs::read(&file_path) {
-        Ok(data) => data,
-        Err(e) => return Err(format!("Failed to read firmware file: {}", e))
-    };
+    // Analyze the image as it will be sent, not the file as stored
+    let image = load_firmware_image(&file_path, pad_byte.unwrap_or(FIRMWARE_DEFAULT_PAD_BYTE))?;
+    let firmware_data = &image.data;
 
     let file_size = firmware_data.len();
-    let crc32 = gcp_crc32(&firmware_data);
-    let chunk_size = GCP_RECOMMENDED_CHUNK_SIZE;
+    let crc32 = gcp_crc32(firmware_data);
+    // Chunks and line rate of the port the image is for, the spec defaults without one
+    let link = port_name.as_deref().map_or_else(LinkConfig::default, link_config::profile_for);
+    let chunk_size = link.chunk_size as usize;
     let estimated_chunks = (file_size + chunk_size - 1) / chunk_size;
     
-    // Estimate transfer time (based on 115200 baud + protocol overhead)
-    let estimated_time_seconds = (file_size as f64 * 10.0) / 115200.0 * 1.5; // 1.5x for protocol overhead
+    // Estimate transfer time from recent transfers, or the UART line rate without any
+    let estimate = firmware_transfer::estimate_transfer(file_size, &link);
+    let estimated_time_seconds = estimate.seconds;
     
     let info = serde_json::json!({
         "fileName": path.file_name().and_then(|n| n.to_str()).unwrap_or("Unknown"),
@@ -430,6 +659,12 @@ fn get_firmware_file_info(file_path: String) -> Result<serde_json::Value, String
         "chunkSize": chunk_size,
         "estimatedTimeSeconds": estimated_time_seconds,
         "estimatedTimeFormatted": format_duration(estimated_time_seconds),
+        "estimatedBytesPerSecond": estimate.bytes_per_second,
+        "estimateSource": estimate.source,
+        "baseAddress": format!("0x{:08X}", image.base_address),
+        "entryPoint": image.entry_point.map(|address| format!("0x{:08X}", address)),
+        "isIntelHex": image.format == FirmwareImageFormat::IntelHex,
+        "signature": firmware_signing::signature_info(&image),
         "isValid": true,
         "fileType": extension_to_type(path.extension().and_then(|e| e.to_str()).unwrap_or("bin"))
     });
@@ -487,45 +722,45 @@ fn process_data(data: Vec<i32>) -> Vec<i32> {
 }
 
 #[tauri::command]
-fn read_bin_file(file_path: String) -> Result<Vec<u8>, String> {
+fn read_bin_file(file_path: String) -> Result<Vec<u8>, GcpCommError> {
     let path = Path::new(&file_path);
     
     // Check if file has .bin extension
     if let Some(extension) = path.extension() {
         if extension != "bin" {
-            return Err("Only .bin files are allowed".to_string());
+            return Err(GcpCommError::file(path, "Only .bin files are allowed"));
         }
     } else {
-        return Err("File must have .bin extension".to_string());
+        return Err(GcpCommError::file(path, "File must have .bin extension"));
     }
 
     match fs::read(&file_path) {
         Ok(data) => Ok(data),
-        Err(e) => Err(format!("Failed to read .bin file: {}", e))
+        Err(e) => Err(GcpCommError::file(path, format!("Failed to read .bin file: {}", e)))
     }
 }
 
 #[tauri::command]
-fn analyze_bin_file(file_path: String) -> Result<serde_json::Value, String> {
+fn analyze_bin_file(file_path: String) -> Result<serde_json::Value, GcpCommError> {
     let path = Path::new(&file_path);
     
     // Ensure it's a .bin file
     if let Some(extension) = path.extension() {
         if extension != "bin" {
-            return Err("Only .bin files are allowed".to_string());
+            return Err(GcpCommError::file(path, "Only .bin files are allowed"));
         }
     } else {
-        return Err("File must have .bin extension".to_string());
+        return Err(GcpCommError::file(path, "File must have .bin extension"));
     }
 
     let data = match fs::read(&file_path) {
         Ok(data) => data,
-        Err(e) => return Err(format!("Failed to read .bin file: {}", e))
+        Err(e) => return Err(GcpCommError::file(path, format!("Failed to read .bin file: {}", e)))
     };
 
     let metadata = match fs::metadata(&file_path) {
         Ok(meta) => meta,
-        Err(e) => return Err(format!("Failed to get file metadata: {}", e))
+        Err(e) => return Err(GcpCommError::file(path, format!("Failed to get file metadata: {}", e)))
     };
 
     // Analyze binary data
@@ -651,12 +886,22 @@ fn get_hex_preview(data: &[u8], lines: usize) -> String {
 pub fn run() {
   tauri::Builder::default()
     .setup(|app| {
-      if cfg!(debug_assertions) {
-        app.handle().plugin(
-          tauri_plugin_log::Builder::default()
-            .level(log::LevelFilter::Info)
-            .build(),
-        )?;
+      app.handle().plugin(
+        tauri_plugin_log::Builder::default()
+          .level(if cfg!(debug_assertions) { log::LevelFilter::Debug } else { log::LevelFilter::Info })
+          .build(),
+      )?;
+      protocol_log::start_console(app.handle().clone());
+      firmware_checkpoint::set_checkpoint_dir(app.path().app_data_dir()?);
+      firmware_transfer::set_history_dir(app.path().app_data_dir()?);
+      port_watcher::start_watcher(app.handle().clone())?;
+      if let Err(e) = firmware_signing::load_trust_config(&app.path().app_config_dir()?) {
+        // Without a valid trust config every image is treated as untrusted
+        log::warn!("Failed to load firmware trust config: {}", e);
+      }
+      if let Err(e) = link_config::load_profiles(app.path().app_config_dir()?) {
+        // Ports fall back to the spec defaults
+        log::warn!("Failed to load link profiles: {}", e);
       }
       Ok(())
     })
@@ -671,15 +916,49 @@ pub fn run() {
         analyze_bin_file,
         list_com_ports,
         get_port_info,
+        get_port_watch_config,
+        set_port_watch_config,
+        discover_devices,
         connect_port,
         disconnect_port,
         get_port_connection_status,
+        get_link_profiles,
+        save_link_profile,
+        delete_link_profile,
+        get_protocol_console_level,
+        set_protocol_console_level,
+        get_protocol_log,
+        start_protocol_capture,
+        stop_protocol_capture,
+        replay_protocol_capture,
+        get_health_check_interval,
+        set_health_check_interval,
         gcp_send_hello,
+        gcp_set_spec_revision,
+        gcp_get_spec_revision,
         gcp_get_status,
         gcp_get_fw_version,
+        gcp_get_diagnostics,
+        gcp_set_rtc_time,
+        gcp_sync_device_time,
+        gcp_set_brightness,
+        gcp_set_sound,
         gcp_firmware_update,
+        gcp_get_firmware_job,
+        gcp_start_firmware_batch,
+        gcp_get_firmware_batch,
+        gcp_cancel_firmware_batch,
+        gcp_pause_firmware_job,
+        gcp_resume_firmware_job,
+        gcp_cancel_firmware_job,
+        gcp_get_firmware_checkpoint,
+        get_firmware_throughput_history,
+        gcp_discard_firmware_checkpoint,
+        get_firmware_trust_config,
         gcp_abort_firmware_update,
         gcp_reset_device,
+        set_firmware_repository,
+        get_firmware_repository,
         gcp_send_firmware_chunk,
         gcp_start_firmware_update,
         get_firmware_file_info,
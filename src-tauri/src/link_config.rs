@@ -0,0 +1,185 @@
+//! Per-connection link parameters
+//!
+//! The defaults are GCP v2.2 §8: 115200 bps 8N1 with RTS/CTS, a 1 s ACK timeout,
+//! 3 attempts per request and 2036-byte firmware chunks. A connection can override
+//! them, e.g. for bench USB-UART adapters without RTS/CTS wired or faster baud rates
+//! on Apollo4 boards. Overrides are kept as per-port profiles in the app config
+//! directory; connecting with a config saves it as the port's profile, and ports
+//! opened without one (connect, discovery, batch flashing) use their saved profile.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::app_store::{load_json, save_json};
+use crate::firmware_transfer::TRANSFER_MIN_CHUNK_SIZE;
+use crate::gcp::{GcpCommError, GCP_MAX_RETRIES, GCP_RECOMMENDED_CHUNK_SIZE, GCP_TIMEOUT_MS, GCP_UART_BAUD};
+
+const LINK_PROFILES_FILE: &str = "link_profiles.json";
+const LINK_MAX_BAUD_RATE: u32 = 3_000_000;
+const LINK_MAX_RETRIES: u32 = 10;
+const LINK_MAX_TIMEOUT_MS: u64 = 60_000;
+const LINK_MAX_INTER_FRAME_DELAY_MS: u64 = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FlowControl {
+    None,
+    Hardware,    // RTS/CTS
+    Software,    // XON/XOFF
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LinkConfig {
+    pub baud_rate: u32,
+    pub flow_control: FlowControl,
+    pub timeout_ms: u64,              // Wait for each ACK or response
+    pub max_retries: u32,             // Attempts per request, including the first
+    pub inter_frame_delay_ms: u64,    // Pause before each frame, for adapters that drop back-to-back frames
+    pub chunk_size: u16,              // FW_UPDATE_DATA payload announced in FW_UPDATE_START
+}
+
+impl Default for LinkConfig {
+    fn default() -> Self {
+        Self {
+            baud_rate: GCP_UART_BAUD,
+            flow_control: FlowControl::Hardware,
+            timeout_ms: GCP_TIMEOUT_MS,
+            max_retries: GCP_MAX_RETRIES,
+            inter_frame_delay_ms: 0,
+            chunk_size: GCP_RECOMMENDED_CHUNK_SIZE as u16,
+        }
+    }
+}
+
+impl LinkConfig {
+    pub fn validate(&self) -> Result<(), GcpCommError> {
+        let invalid = |message: String| Err(GcpCommError::invalid_parameter(message));
+        if self.baud_rate == 0 || self.baud_rate > LINK_MAX_BAUD_RATE {
+            return invalid(format!("Baud rate must be between 1 and {}", LINK_MAX_BAUD_RATE));
+        }
+        if self.timeout_ms == 0 || self.timeout_ms > LINK_MAX_TIMEOUT_MS {
+            return invalid(format!("Timeout must be between 1 and {} ms", LINK_MAX_TIMEOUT_MS));
+        }
+        if self.max_retries == 0 || self.max_retries > LINK_MAX_RETRIES {
+            return invalid(format!("Retry count must be between 1 and {}", LINK_MAX_RETRIES));
+        }
+        if self.inter_frame_delay_ms > LINK_MAX_INTER_FRAME_DELAY_MS {
+            return invalid(format!("Inter-frame delay must be at most {} ms", LINK_MAX_INTER_FRAME_DELAY_MS));
+        }
+        // The MCU receives a whole frame into its 2048-byte RX buffer (§8)
+        let chunk_size = self.chunk_size as usize;
+        if !(TRANSFER_MIN_CHUNK_SIZE..=GCP_RECOMMENDED_CHUNK_SIZE).contains(&chunk_size) {
+            return invalid(format!("Chunk size must be between {} and {} bytes",
+                                   TRANSFER_MIN_CHUNK_SIZE, GCP_RECOMMENDED_CHUNK_SIZE));
+        }
+        Ok(())
+    }
+}
+
+struct LinkProfiles {
+    dir: Option<PathBuf>,
+    profiles: BTreeMap<String, LinkConfig>,
+}
+
+lazy_static::lazy_static! {
+    static ref PROFILES: Mutex<LinkProfiles> = Mutex::new(LinkProfiles { dir: None, profiles: BTreeMap::new() });
+}
+
+// Profiles live in the app config directory, loaded once during startup
+pub fn load_profiles(dir: PathBuf) -> Result<BTreeMap<String, LinkConfig>, GcpCommError> {
+    let profiles = read_profiles(&dir)?;
+    let mut state = PROFILES.lock().map_err(|_| GcpCommError::internal("Failed to lock link profiles"))?;
+    *state = LinkProfiles { dir: Some(dir), profiles: profiles.clone() };
+    Ok(profiles)
+}
+
+fn read_profiles(dir: &Path) -> Result<BTreeMap<String, LinkConfig>, GcpCommError> {
+    let loaded: BTreeMap<String, LinkConfig> = load_json(&dir.join(LINK_PROFILES_FILE), "link profiles")?
+        .unwrap_or_default();
+
+    // An invalid profile would make its port impossible to open
+    Ok(loaded
+        .into_iter()
+        .filter(|(port_name, config)| match config.validate() {
+            Ok(()) => true,
+            Err(e) => {
+                log::warn!(target: "gcp::link", "Ignoring link profile for {}: {}", port_name, e);
+                false
+            }
+        })
+        .collect())
+}
+
+pub fn profiles() -> BTreeMap<String, LinkConfig> {
+    PROFILES.lock().map(|state| state.profiles.clone()).unwrap_or_default()
+}
+
+// The port's saved profile, or the spec defaults
+pub fn profile_for(port_name: &str) -> LinkConfig {
+    PROFILES.lock()
+        .ok()
+        .and_then(|state| state.profiles.get(port_name).cloned())
+        .unwrap_or_default()
+}
+
+pub fn save_profile(port_name: &str, config: LinkConfig) -> Result<LinkConfig, GcpCommError> {
+    config.validate()?;
+    update_profiles(|profiles| {
+        profiles.insert(port_name.to_string(), config.clone());
+    })?;
+    Ok(config)
+}
+
+pub fn delete_profile(port_name: &str) -> Result<(), GcpCommError> {
+    update_profiles(|profiles| {
+        profiles.remove(port_name);
+    })
+}
+
+fn update_profiles(update: impl FnOnce(&mut BTreeMap<String, LinkConfig>)) -> Result<(), GcpCommError> {
+    let mut state = PROFILES.lock().map_err(|_| GcpCommError::internal("Failed to lock link profiles"))?;
+    update(&mut state.profiles);
+
+    if let Some(dir) = &state.dir {
+        save_json(&dir.join(LINK_PROFILES_FILE), &state.profiles, "link profiles")?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app_store::tests::test_dir;
+
+    #[test]
+    fn test_link_config_defaults_and_profiles() {
+        // Fields left out keep the spec defaults
+        let config: LinkConfig = serde_json::from_str(r#"{"baud_rate": 921600, "flow_control": "none"}"#).unwrap();
+        assert_eq!(config, LinkConfig { baud_rate: 921600, flow_control: FlowControl::None, ..LinkConfig::default() });
+        assert!(config.validate().is_ok());
+        assert!(LinkConfig { chunk_size: 4096, ..LinkConfig::default() }.validate().is_err());
+        assert!(LinkConfig { max_retries: 0, ..LinkConfig::default() }.validate().is_err());
+
+        let dir = test_dir("link_profiles");
+        let bench = LinkConfig { baud_rate: 921600, ..LinkConfig::default() };
+        let broken = LinkConfig { baud_rate: 0, ..LinkConfig::default() };
+        let saved = BTreeMap::from([
+            ("/dev/ttyBENCH1".to_string(), bench.clone()),
+            ("/dev/ttyBENCH2".to_string(), broken),
+        ]);
+        save_json(&dir.join(LINK_PROFILES_FILE), &saved, "link profiles").unwrap();
+
+        // Saved profiles survive a restart; invalid ones are dropped, not fatal
+        let loaded = read_profiles(&dir).unwrap();
+        assert_eq!(loaded, BTreeMap::from([("/dev/ttyBENCH1".to_string(), bench)]));
+        assert!(read_profiles(&test_dir("link_profiles_missing")).unwrap().is_empty());
+
+        // Ports without a profile keep the spec defaults
+        assert!(save_profile("/dev/ttyBENCH3", LinkConfig { max_retries: 0, ..LinkConfig::default() }).is_err());
+        assert_eq!(profile_for("/dev/ttyBENCH3"), LinkConfig::default());
+        let _ = std::fs::remove_dir_all(dir);
+    }
+}
@@ -0,0 +1,172 @@
+//! Hot-plug watcher for serial ports
+//!
+//! One background thread lists the serial ports on an interval and diffs the result
+//! against the previous list, emitting "port-added" and "port-removed" with the
+//! port's `COMPortInfo`. A port whose listing changes under the same name (another
+//! device on that port) is reported as removed and added again. An optional USB
+//! VID/PID filter limits the events to matching devices, e.g. only Glitchi USB
+//! bridges; changing it reports ports entering or leaving the filter the same way.
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{Emitter, Runtime};
+
+use crate::gcp::GcpCommError;
+use crate::COMPortInfo;
+
+pub const PORT_WATCH_DEFAULT_INTERVAL_MS: u64 = 1000;
+const PORT_WATCH_MIN_INTERVAL_MS: u64 = 200;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UsbIdFilter {
+    pub vendor_id: u16,
+    pub product_id: Option<u16>,   // None matches any product of the vendor
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PortWatchConfig {
+    pub interval_ms: u64,
+    pub usb_filter: Option<Vec<UsbIdFilter>>,   // None reports every port
+}
+
+impl Default for PortWatchConfig {
+    fn default() -> Self {
+        Self { interval_ms: PORT_WATCH_DEFAULT_INTERVAL_MS, usb_filter: None }
+    }
+}
+
+impl PortWatchConfig {
+    fn matches(&self, port: &COMPortInfo) -> bool {
+        let Some(filter) = &self.usb_filter else {
+            return true;
+        };
+        filter.iter().any(|id| {
+            port.vendor_id == Some(id.vendor_id)
+                && (id.product_id.is_none() || port.product_id == id.product_id)
+        })
+    }
+}
+
+static WATCHER_STARTED: AtomicBool = AtomicBool::new(false);
+
+lazy_static::lazy_static! {
+    static ref WATCH_CONFIG: Mutex<PortWatchConfig> = Mutex::new(PortWatchConfig::default());
+}
+
+pub fn get_watch_config() -> Result<PortWatchConfig, GcpCommError> {
+    WATCH_CONFIG.lock()
+        .map(|config| config.clone())
+        .map_err(|_| GcpCommError::internal("Failed to lock port watch config"))
+}
+
+// Takes effect on the watcher's next scan
+pub fn set_watch_config(config: PortWatchConfig) -> Result<PortWatchConfig, GcpCommError> {
+    if config.interval_ms < PORT_WATCH_MIN_INTERVAL_MS {
+        return Err(GcpCommError::invalid_parameter(format!(
+            "Port watch interval must be at least {} ms", PORT_WATCH_MIN_INTERVAL_MS)));
+    }
+    *WATCH_CONFIG.lock().map_err(|_| GcpCommError::internal("Failed to lock port watch config"))? = config.clone();
+    Ok(config)
+}
+
+// Start the watcher for the lifetime of the app. Ports present at startup are the
+// baseline and aren't reported; the frontend lists them once with list_com_ports.
+pub fn start_watcher<R, E>(emitter: E) -> Result<(), GcpCommError>
+where
+    R: Runtime,
+    E: Emitter<R> + Send + 'static,
+{
+    if WATCHER_STARTED.swap(true, Ordering::SeqCst) {
+        return Ok(());
+    }
+
+    std::thread::Builder::new()
+        .name("port-watcher".to_string())
+        .spawn(move || watch(emitter))
+        .map(|_| ())
+        .map_err(|e| {
+            WATCHER_STARTED.store(false, Ordering::SeqCst);
+            GcpCommError::internal(format!("Failed to start port watcher: {}", e))
+        })
+}
+
+fn watch<R: Runtime, E: Emitter<R>>(emitter: E) {
+    let mut known = scan(&get_watch_config().unwrap_or_default()).unwrap_or_default();
+
+    loop {
+        let config = get_watch_config().unwrap_or_default();
+        std::thread::sleep(Duration::from_millis(config.interval_ms));
+
+        // A failed scan says nothing about the ports; keep the last list
+        let Some(ports) = scan(&config) else {
+            continue;
+        };
+
+        let (added, removed) = diff_ports(&known, &ports);
+        for port in removed {
+            log::info!(target: "gcp::ports", "Port removed: {}", port.port);
+            let _ = emitter.emit("port-removed", port);
+        }
+        for port in added {
+            log::info!(target: "gcp::ports", "Port added: {}", port.port);
+            let _ = emitter.emit("port-added", port);
+        }
+        known = ports;
+    }
+}
+
+fn scan(config: &PortWatchConfig) -> Option<Vec<COMPortInfo>> {
+    match crate::list_com_ports() {
+        Ok(ports) => Some(ports.into_iter().filter(|port| config.matches(port)).collect()),
+        Err(e) => {
+            log::warn!(target: "gcp::ports", "Port watcher scan failed: {}", e);
+            None
+        }
+    }
+}
+
+// Ports only in `current` and ports only in `previous`, compared by full listing
+fn diff_ports<'a>(previous: &'a [COMPortInfo], current: &'a [COMPortInfo]) -> (Vec<&'a COMPortInfo>, Vec<&'a COMPortInfo>) {
+    let added = current.iter().filter(|port| !previous.contains(port)).collect();
+    let removed = previous.iter().filter(|port| !current.contains(port)).collect();
+    (added, removed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn usb_port(port: &str, vendor_id: u16, product_id: u16) -> COMPortInfo {
+        COMPortInfo {
+            port: port.to_string(),
+            description: format!("USB Serial Port ({})", port),
+            manufacturer: None,
+            serial_number: None,
+            vendor_id: Some(vendor_id),
+            product_id: Some(product_id),
+            port_type: "USB".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_filter_and_diff() {
+        let bridge = usb_port("COM3", 0x10C4, 0xEA60);
+        let other = usb_port("COM4", 0x0403, 0x6001);
+
+        let config = PortWatchConfig {
+            usb_filter: Some(vec![UsbIdFilter { vendor_id: 0x10C4, product_id: None }]),
+            ..PortWatchConfig::default()
+        };
+        assert!(config.matches(&bridge));
+        assert!(!config.matches(&other));
+        assert!(PortWatchConfig::default().matches(&other));
+
+        // A different device on the same name is a removal plus an addition
+        let replaced = usb_port("COM3", 0x10C4, 0xEA70);
+        let previous = [bridge.clone(), other.clone()];
+        let current = [other.clone(), replaced.clone()];
+        assert_eq!(diff_ports(&previous, &current), (vec![&replaced], vec![&bridge]));
+    }
+}
@@ -0,0 +1,216 @@
+//! Structured protocol logging
+//!
+//! Protocol events go through the `log` crate with a target per subsystem and
+//! key-value fields (port, msg_type, seq, len, crc_ok, elapsed_ms):
+//!
+//! - `gcp::tx` / `gcp::rx`: one Debug event per frame, raw bytes at Trace
+//! - `gcp::link`: connections opening and closing, stale input being dropped
+//! - `gcp::fw`: FW_UPDATE_START / END / ABORT and RESET
+//! - `gcp::sim`: the simulated device and the faults injected into its link
+//!
+//! The same events feed the GUI's protocol console: events at or above the console
+//! level are emitted as "protocol-log", and the most recent ones are kept so a
+//! console opened later can show what led up to it.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::{Emitter, Runtime};
+
+pub const TARGET_TX: &str = "gcp::tx";
+pub const TARGET_RX: &str = "gcp::rx";
+pub const TARGET_LINK: &str = "gcp::link";
+pub const TARGET_FW: &str = "gcp::fw";
+pub const TARGET_SIM: &str = "gcp::sim";
+
+const CONSOLE_HISTORY_LEN: usize = 500;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LogLevel {
+    Error,
+    Warn,
+    Info,
+    Debug,
+    Trace,
+}
+
+impl From<LogLevel> for log::Level {
+    fn from(level: LogLevel) -> Self {
+        match level {
+            LogLevel::Error => log::Level::Error,
+            LogLevel::Warn => log::Level::Warn,
+            LogLevel::Info => log::Level::Info,
+            LogLevel::Debug => log::Level::Debug,
+            LogLevel::Trace => log::Level::Trace,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProtocolEvent {
+    pub timestamp_ms: u64,       // Unix time
+    pub level: LogLevel,
+    pub target: String,
+    pub port: Option<String>,
+    pub msg_type: Option<u16>,
+    pub seq: Option<u32>,
+    pub len: Option<usize>,
+    pub crc_ok: Option<bool>,
+    pub elapsed_ms: Option<u64>, // Since the request this event answers was sent
+    pub message: String,
+}
+
+// Shared so `log` can emit without holding the console lock: the sink may log itself
+type ConsoleSink = Arc<dyn Fn(&ProtocolEvent) + Send + Sync>;
+
+struct Console {
+    level: LogLevel,
+    sink: Option<ConsoleSink>,
+    recent: VecDeque<ProtocolEvent>,
+}
+
+lazy_static::lazy_static! {
+    static ref CONSOLE: Mutex<Console> = Mutex::new(Console {
+        level: LogLevel::Info,
+        sink: None,
+        recent: VecDeque::new(),
+    });
+}
+
+// Stream console events to the frontend for the lifetime of the app
+pub fn start_console<R, E>(emitter: E)
+where
+    R: Runtime,
+    E: Emitter<R> + Send + Sync + 'static,
+{
+    if let Ok(mut console) = CONSOLE.lock() {
+        console.sink = Some(Arc::new(move |event| {
+            let _ = emitter.emit("protocol-log", event);
+        }));
+    }
+}
+
+pub fn console_level() -> LogLevel {
+    CONSOLE.lock().map(|console| console.level).unwrap_or(LogLevel::Info)
+}
+
+pub fn set_console_level(level: LogLevel) -> LogLevel {
+    if let Ok(mut console) = CONSOLE.lock() {
+        console.level = level;
+    }
+    level
+}
+
+pub fn recent_events() -> Vec<ProtocolEvent> {
+    CONSOLE.lock().map(|console| console.recent.iter().cloned().collect()).unwrap_or_default()
+}
+
+// Whether an event would be logged or shown; guards formatting hex dumps nobody reads
+pub fn enabled(level: LogLevel, target: &str) -> bool {
+    level <= console_level() || log::log_enabled!(target: target, level.into())
+}
+
+impl ProtocolEvent {
+    pub fn new(level: LogLevel, target: &str, port: &str) -> Self {
+        Self {
+            timestamp_ms: SystemTime::now().duration_since(UNIX_EPOCH).map(|t| t.as_millis() as u64).unwrap_or(0),
+            level,
+            target: target.to_string(),
+            port: (!port.is_empty()).then(|| port.to_string()),
+            msg_type: None,
+            seq: None,
+            len: None,
+            crc_ok: None,
+            elapsed_ms: None,
+            message: String::new(),
+        }
+    }
+
+    pub fn msg_type(self, msg_type: u16) -> Self {
+        Self { msg_type: Some(msg_type), ..self }
+    }
+
+    pub fn seq(self, seq: Option<u32>) -> Self {
+        Self { seq, ..self }
+    }
+
+    pub fn len(self, len: usize) -> Self {
+        Self { len: Some(len), ..self }
+    }
+
+    pub fn crc_ok(self, crc_ok: bool) -> Self {
+        Self { crc_ok: Some(crc_ok), ..self }
+    }
+
+    pub fn elapsed(self, elapsed: Duration) -> Self {
+        Self { elapsed_ms: Some(elapsed.as_millis() as u64), ..self }
+    }
+
+    pub fn log(self, message: impl Into<String>) {
+        let event = Self { message: message.into(), ..self };
+
+        log::log!(
+            target: &event.target,
+            event.level.into(),
+            port = event.port.as_deref(),
+            msg_type = event.msg_type,
+            seq = event.seq,
+            len = event.len,
+            crc_ok = event.crc_ok,
+            elapsed_ms = event.elapsed_ms;
+            "{}", event.message
+        );
+
+        let sink = {
+            let Ok(mut console) = CONSOLE.lock() else {
+                return;
+            };
+            if event.level > console.level {
+                return;
+            }
+            if console.recent.len() == CONSOLE_HISTORY_LEN {
+                console.recent.pop_front();
+            }
+            console.recent.push_back(event.clone());
+            console.sink.clone()
+        };
+        if let Some(sink) = sink {
+            sink(&event);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_console_keeps_events_at_its_level() {
+        let port_name = "memory://test_protocol_log";
+        let events = || -> Vec<ProtocolEvent> {
+            recent_events().into_iter().filter(|event| event.port.as_deref() == Some(port_name)).collect()
+        };
+
+        set_console_level(LogLevel::Debug);
+        ProtocolEvent::new(LogLevel::Debug, TARGET_RX, port_name)
+            .msg_type(0x0002)
+            .seq(Some(4072))
+            .len(14)
+            .crc_ok(true)
+            .elapsed(Duration::from_millis(12))
+            .log("RX Ack");
+        ProtocolEvent::new(LogLevel::Trace, TARGET_RX, port_name).log("RX [AA, 55]");
+        assert!(!enabled(LogLevel::Trace, "gcp::test"));
+        set_console_level(LogLevel::Info);
+
+        // Trace was below the console level
+        let events = events();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].message, "RX Ack");
+        assert_eq!((events[0].msg_type, events[0].seq, events[0].len), (Some(0x0002), Some(4072), Some(14)));
+        assert_eq!((events[0].crc_ok, events[0].elapsed_ms), (Some(true), Some(12)));
+        assert_eq!(serde_json::to_value(&events[0]).unwrap()["level"], "debug");
+    }
+}
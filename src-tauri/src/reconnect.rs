@@ -0,0 +1,337 @@
+//! Automatic reconnect after a device reset
+//!
+//! RESET reboots the board, and a native USB port goes away with it, possibly coming
+//! back under another name. The pooled handler would be left holding a dead handle,
+//! so after a reset one thread per connection re-establishes it:
+//!
+//! - While the old link still works (USB-UART bridges, TCP, simulators), HELLO is
+//!   retried on it
+//! - Otherwise the ports with the old port's USB VID/PID/serial string are waited for
+//!   and tried in turn; the one that answers is swapped into the connection pool
+//! - The device must answer HELLO with the serial number of the one that was reset;
+//!   a port where a different device answers is skipped
+//! - Its firmware version is then queried and emitted as "device-rebooted", so the
+//!   version a firmware update installed can be checked
+//!
+//! A device that hasn't come back within RECONNECT_TIMEOUT_MS puts the connection in
+//! `ConnectionState::Error`.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use tauri::{AppHandle, Emitter, Runtime};
+
+use crate::connection_monitor::{self, report_state};
+use crate::fw_update_listener;
+use crate::link_config::LinkConfig;
+use crate::gcp::{get_connection_handle, replace_connection, ConnectionState, GcpCommError, GcpFwVersionData, GcpHardwareData, GcpHelloResponse, GcpSpecRevision, GcpUartHandler};
+use crate::COMPortInfo;
+
+const RECONNECT_TIMEOUT_MS: u64 = 30000;
+// Time for the device to act on RESET, so the first HELLO can't be answered by the old firmware
+const RECONNECT_SETTLE_MS: u64 = 1000;
+const RECONNECT_POLL_INTERVAL_MS: u64 = 500;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceRebootedEvent {
+    pub previous_port_name: String,
+    pub port_name: String,
+    pub hardware: Option<GcpHardwareData>,
+    pub fw_version: GcpFwVersionData,
+    pub fw_version_string: String,
+}
+
+// What recognises a device when it comes back from a reset
+pub struct DeviceIdentity {
+    usb: Option<COMPortInfo>,      // None for TCP, simulated and non-USB ports
+    serial_number: Option<u16>,    // None for devices answering HELLO with legacy status data
+    spec_revision: GcpSpecRevision,
+    link: LinkConfig,              // Reopened with the same link parameters, whatever the new port name
+}
+
+impl DeviceIdentity {
+    // Identify the device on a connection before resetting it. Runs HELLO unless one
+    // already returned hardware data; a device that doesn't answer is only matched by port.
+    pub fn capture(port_name: &str, handler: &mut GcpUartHandler) -> Self {
+        let serial_number = match handler.hardware() {
+            Some(hardware) => Some(hardware.serial_number),
+            None => match handler.send_hello() {
+                Ok(GcpHelloResponse::Hardware(hardware)) => Some(hardware.serial_number),
+                Ok(GcpHelloResponse::Status(_)) => None,
+                Err(e) => {
+                    log::warn!(target: "gcp::reconnect", "Could not identify device on {} before reset: {}", port_name, e);
+                    None
+                }
+            },
+        };
+
+        let usb = crate::get_port_info(port_name.to_string())
+            .ok()
+            .flatten()
+            .filter(|port| port.vendor_id.is_some() && port.product_id.is_some());
+
+        Self { usb, serial_number, spec_revision: handler.spec_revision(), link: handler.link_config().clone() }
+    }
+
+    // Listed ports with the same USB VID/PID/serial string, or the old name for other
+    // ports. Boards without a USB serial string all match, hence several candidates.
+    fn find_ports(&self, port_name: &str) -> Vec<String> {
+        let Some(usb) = &self.usb else {
+            return vec![port_name.to_string()];
+        };
+        crate::list_com_ports()
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|port| {
+                port.vendor_id == usb.vendor_id
+                    && port.product_id == usb.product_id
+                    && port.serial_number == usb.serial_number
+            })
+            .map(|port| port.port)
+            .collect()
+    }
+
+    // HELLO and GET_FW_VERSION on a candidate link. None while the device doesn't
+    // answer; an error if a different device does.
+    fn identify(&self, handler: &mut GcpUartHandler) -> Result<Option<(Option<GcpHardwareData>, GcpFwVersionData)>, GcpCommError> {
+        handler.set_spec_revision(self.spec_revision);
+        let hardware = match handler.send_hello() {
+            Ok(GcpHelloResponse::Hardware(hardware)) => Some(hardware),
+            Ok(GcpHelloResponse::Status(_)) => None,
+            Err(_) => return Ok(None),
+        };
+
+        if let (Some(expected), Some(hardware)) = (self.serial_number, &hardware) {
+            if hardware.serial_number != expected {
+                return Err(GcpCommError::invalid_response(format!(
+                    "Device {} answered after reset instead of device {}", hardware.serial_number, expected)));
+            }
+        }
+
+        match handler.get_fw_version() {
+            Ok(fw_version) => Ok(Some((hardware, fw_version))),
+            Err(_) => Ok(None),
+        }
+    }
+}
+
+struct Reconnected {
+    port_name: String,
+    handler: Option<GcpUartHandler>,   // None when the old link survived the reset
+    hardware: Option<GcpHardwareData>,
+    fw_version: GcpFwVersionData,
+}
+
+struct ReconnectHandle {
+    stop: Arc<AtomicBool>,
+    thread: JoinHandle<()>,
+}
+
+lazy_static::lazy_static! {
+    static ref RECONNECTS: Mutex<HashMap<String, ReconnectHandle>> = Mutex::new(HashMap::new());
+}
+
+// Wait for a device that was just reset to come back. Does nothing if a reconnect is
+// already running for the port.
+pub fn start_reconnect<R: Runtime>(app: AppHandle<R>, port_name: String, identity: DeviceIdentity) -> Result<(), GcpCommError> {
+    let mut reconnects = RECONNECTS.lock()
+        .map_err(|_| GcpCommError::internal("Failed to lock reconnect registry"))?;
+
+    if let Some(existing) = reconnects.get(&port_name) {
+        if !existing.thread.is_finished() {
+            return Ok(());
+        }
+    }
+
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread_stop = stop.clone();
+    let thread_port = port_name.clone();
+    let thread = std::thread::Builder::new()
+        .name(format!("gcp-reconnect-{}", port_name))
+        .spawn(move || reconnect(app, thread_port, identity, thread_stop))
+        .map_err(|e| GcpCommError::internal(format!("Failed to start reconnect: {}", e)))?;
+
+    reconnects.insert(port_name, ReconnectHandle { stop, thread });
+    Ok(())
+}
+
+pub fn cancel_reconnect(port_name: &str) {
+    let handle = match RECONNECTS.lock() {
+        Ok(mut reconnects) => reconnects.remove(port_name),
+        Err(_) => None,
+    };
+
+    if let Some(handle) = handle {
+        handle.stop.store(true, Ordering::Relaxed);
+        let _ = handle.thread.join();
+    }
+}
+
+fn reconnect<R: Runtime>(app: AppHandle<R>, port_name: String, identity: DeviceIdentity, stop: Arc<AtomicBool>) {
+    log::info!(target: "gcp::reconnect", "Waiting for device on {} to come back from reset", port_name);
+    let started = Instant::now();
+    let mut rejected = None;
+    std::thread::sleep(Duration::from_millis(RECONNECT_SETTLE_MS));
+
+    while !stop.load(Ordering::Relaxed) {
+        match try_reconnect(&port_name, &identity, &mut rejected) {
+            Ok(Some(reconnected)) => {
+                finish(&app, &port_name, reconnected);
+                return;
+            }
+            Ok(None) => {}
+            // Disconnected meanwhile: nothing left to restore
+            Err(GcpCommError::NotConnected { .. }) => return,
+            Err(e) => {
+                report_state(&app, &port_name, ConnectionState::Error(e.to_string()));
+                return;
+            }
+        }
+
+        if started.elapsed() >= Duration::from_millis(RECONNECT_TIMEOUT_MS) {
+            let mut message = format!("Device did not come back within {} s of reset", RECONNECT_TIMEOUT_MS / 1000);
+            if let Some(e) = &rejected {
+                message = format!("{} ({})", message, e);
+            }
+            report_state(&app, &port_name, ConnectionState::Error(message));
+            return;
+        }
+        std::thread::sleep(Duration::from_millis(RECONNECT_POLL_INTERVAL_MS));
+    }
+}
+
+// The device answering on its old link or a reappeared port, or None if it isn't back
+// yet. Links where a different device answers are skipped and the last such error is
+// kept in `rejected`.
+fn try_reconnect(port_name: &str, identity: &DeviceIdentity, rejected: &mut Option<GcpCommError>) -> Result<Option<Reconnected>, GcpCommError> {
+    let handler_arc = get_connection_handle(port_name)
+        .ok_or_else(|| GcpCommError::NotConnected { port_name: port_name.to_string() })?;
+
+    {
+        let mut handler = handler_arc.lock()
+            .map_err(|_| GcpCommError::internal("Failed to lock handler"))?;
+        if handler.check_link().is_ok() {
+            return Ok(identified(port_name, identity.identify(&mut handler), rejected).map(|(hardware, fw_version)| Reconnected {
+                port_name: port_name.to_string(),
+                handler: None,
+                hardware,
+                fw_version,
+            }));
+        }
+    }
+
+    for candidate in identity.find_ports(port_name) {
+        // Still enumerating, or the old name not yet released
+        let Ok(mut handler) = GcpUartHandler::open(&candidate, identity.link.clone()) else {
+            continue;
+        };
+        if let Some((hardware, fw_version)) = identified(&candidate, identity.identify(&mut handler), rejected) {
+            return Ok(Some(Reconnected { port_name: candidate, handler: Some(handler), hardware, fw_version }));
+        }
+    }
+    Ok(None)
+}
+
+fn identified<T>(candidate: &str, identity: Result<Option<T>, GcpCommError>, rejected: &mut Option<GcpCommError>) -> Option<T> {
+    match identity {
+        Ok(identity) => identity,
+        Err(e) => {
+            log::warn!(target: "gcp::reconnect", "Skipping {}: {}", candidate, e);
+            *rejected = Some(e);
+            None
+        }
+    }
+}
+
+fn finish<R: Runtime>(app: &AppHandle<R>, previous_port_name: &str, reconnected: Reconnected) {
+    let port_name = reconnected.port_name;
+
+    if let Some(handler) = reconnected.handler {
+        if let Err(e) = replace_connection(previous_port_name, port_name.clone(), handler) {
+            log::error!(target: "gcp::reconnect", "Failed to restore connection to {}: {}", port_name, e);
+            report_state(app, previous_port_name, ConnectionState::Error(e.to_string()));
+            return;
+        }
+
+        // Background tasks follow the connection to its new name
+        if port_name != previous_port_name {
+            fw_update_listener::stop_listener(previous_port_name);
+            connection_monitor::stop_monitor(previous_port_name);
+            report_state(app, previous_port_name, ConnectionState::Disconnected);
+            if let Err(e) = fw_update_listener::start_listener(app.clone(), port_name.clone()) {
+                log::warn!(target: "gcp::reconnect", "Failed to start FW update listener on {}: {}", port_name, e);
+            }
+            if let Err(e) = connection_monitor::start_monitor(app.clone(), port_name.clone()) {
+                log::warn!(target: "gcp::reconnect", "Failed to start health monitor on {}: {}", port_name, e);
+            }
+        }
+    }
+
+    log::info!(target: "gcp::reconnect", "Device back on {} running firmware {}", port_name, reconnected.fw_version.version_string());
+    report_state(app, &port_name, ConnectionState::Connected);
+    let _ = app.emit("device-rebooted", DeviceRebootedEvent {
+        previous_port_name: previous_port_name.to_string(),
+        port_name,
+        hardware: reconnected.hardware,
+        fw_version_string: reconnected.fw_version.version_string(),
+        fw_version: reconnected.fw_version,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gcp::{connect_with_transport, disconnect_from_port, execute_with_connection};
+    use crate::simulator::{spawn_memory_device, SimulatorConfig};
+    use std::sync::mpsc;
+    use tauri::Listener;
+
+    const RESET_SOFTWARE: u16 = 0x0001;
+
+    fn simulated_device(port_name: &str, serial_number: u16) -> DeviceIdentity {
+        let mut config = SimulatorConfig::default();
+        config.hardware.serial_number = serial_number;
+        connect_with_transport(port_name.to_string(), Box::new(spawn_memory_device(config))).unwrap();
+        execute_with_connection(port_name, |handler| Ok(DeviceIdentity::capture(port_name, handler))).unwrap()
+    }
+
+    #[test]
+    fn test_reset_device_is_identified_again() {
+        let port_name = "memory://test_reconnect";
+        let identity = simulated_device(port_name, 5);
+        assert_eq!(identity.serial_number, Some(5));
+        execute_with_connection(port_name, |handler| handler.reset_device(RESET_SOFTWARE)).unwrap();
+
+        let app = tauri::test::mock_app();
+        let (rebooted_tx, rebooted) = mpsc::channel();
+        app.handle().listen_any("device-rebooted", move |event| {
+            let _ = rebooted_tx.send(serde_json::from_str::<DeviceRebootedEvent>(event.payload()).unwrap());
+        });
+        start_reconnect(app.handle().clone(), port_name.to_string(), identity).unwrap();
+
+        let event = rebooted.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert_eq!((event.previous_port_name.as_str(), event.port_name.as_str()), (port_name, port_name));
+        assert_eq!(event.hardware.map(|hardware| hardware.serial_number), Some(5));
+        assert_eq!(event.fw_version_string, SimulatorConfig::default().fw_version.version_string());
+
+        cancel_reconnect(port_name);
+        disconnect_from_port(port_name.to_string()).unwrap();
+    }
+
+    #[test]
+    fn test_different_device_is_skipped() {
+        let port_name = "memory://test_reconnect_other_device";
+        let mut identity = simulated_device(port_name, 5);
+        identity.serial_number = Some(6);
+
+        let mut rejected = None;
+        assert!(try_reconnect(port_name, &identity, &mut rejected).unwrap().is_none());
+        let rejected = rejected.unwrap().to_string();
+        assert!(rejected.contains("Device 5 answered after reset instead of device 6"), "{}", rejected);
+
+        disconnect_from_port(port_name.to_string()).unwrap();
+    }
+}
@@ -0,0 +1,623 @@
+//! Software GCP v2.2 device
+//!
+//! `SimulatedDevice` answers host frames the way the Glitchi firmware does, so the
+//! app and the firmware update path can be exercised without hardware:
+//!
+//! - HELLO, GET_STATUS, GET_DIAGNOSTICS and GET_FW_VERSION return the data in
+//!   `SimulatorConfig`; SET_CONFIG updates it
+//! - FW_UPDATE_DATA chunks are written to a simulated MRAM buffer while a running
+//!   CRC-32 is kept, and FW_UPDATE_END reports match or mismatch against the CRC32
+//!   announced in FW_UPDATE_START (§4.3-4.5)
+//! - Corrupt frames, unknown commands and out-of-order chunks are NACK'd with the
+//!   §3 error codes
+//! - With `request_update` set, the device sends FW_UPDATE_REQUEST (§4.6) as soon as
+//!   it starts, as if the user had picked firmware update on the device
+//! - A reboot forgets the firmware transfer in progress, so the host has to start
+//!   it again with FW_UPDATE_START
+//! - A `FaultScript` in the config disturbs the link on purpose (see fault_injection.rs)
+//!
+//! The device runs on any `GcpTransport`. Inside the app it is opened as a
+//! `sim://[serial]` port backed by a `MemoryTransport`; the `gcp-simulator` binary
+//! serves it on a PTY or a TCP socket instead.
+
+use serde::{Deserialize, Serialize};
+use std::io;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::Duration;
+
+use crate::fault_injection::{Fault, FaultInjector, FaultScript};
+use crate::gcp_decoder::GcpDecoder;
+use crate::protocol_log::{LogLevel, ProtocolEvent, TARGET_SIM};
+use crate::gcp::{
+    gcp_crc32_update, FramingError, GcpCommError, GcpCommand, GcpDiagnosticsData, GcpError, GcpFrame,
+    GcpConfig, GcpFwVersionData, GcpHardwareData, GcpMessage, GcpStatusData,
+};
+use crate::transport::{GcpTransport, MemoryTransport};
+
+pub const SIMULATOR_PORT_PREFIX: &str = "sim://";
+pub const SIMULATOR_DEFAULT_MRAM_SIZE: usize = 1024 * 1024;
+// MCU RX buffer (§8); a full frame, CRC included, must fit
+const SIMULATOR_RX_BUFFER_SIZE: usize = 2048;
+// Preamble(2) + Length(2) + MsgType(2) + SeqNo(4) + CRC(2) around each FW_UPDATE_DATA chunk
+const SIMULATOR_DATA_FRAME_OVERHEAD: usize = 12;
+const SIMULATOR_POLL_MS: u64 = 50;
+// Gap between the pieces of a split response, long enough for the host to read each one
+const SIMULATOR_SPLIT_GAP_MS: u64 = 2;
+
+// FW_UPDATE_END result values (§4.5)
+const FW_VERIFY_MATCH: u32 = 0x00000000;
+const FW_VERIFY_MISMATCH: u32 = 0xFFFFFFFF;
+
+// RESET types (§4.8)
+const RESET_SOFTWARE: u16 = 0x0001;
+const RESET_APPLY_UPDATE: u16 = 0x0002;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SimulatorConfig {
+    pub hardware: GcpHardwareData,
+    pub status: GcpStatusData,
+    pub diagnostics: GcpDiagnosticsData,
+    pub fw_version: GcpFwVersionData,
+    pub mram_size: usize,
+    pub request_update: bool,
+    pub faults: Option<FaultScript>,
+}
+
+impl Default for SimulatorConfig {
+    fn default() -> Self {
+        Self {
+            hardware: GcpHardwareData {
+                manufacture_date: 0x0719,
+                serial_number: 1,
+                board_type: 0x10,    // REV0
+                hw_revision: 0,
+                chip_model: 0x40,    // Apollo4Lite
+                features: 0x04,      // EXT_MRAM_A
+            },
+            status: GcpStatusData {
+                battery_level: 80,
+                system_state: 0,
+                led_color: 0x07E0,
+                led_brightness: 50,
+                current_game_idx: 0,
+                rtc_time: [25, 1, 1, 0, 0, 0, 2, 0],
+            },
+            diagnostics: GcpDiagnosticsData {
+                step_counter: 0,
+                full_power_time: 0,
+                silent_time: 0,
+                charging_time: 0,
+                btn_counter_l: 0,
+                btn_counter_r: 0,
+                fram_read: 0,
+                fram_write: 0,
+            },
+            fw_version: GcpFwVersionData {
+                fw_version_major: 0,
+                fw_version_minor: 1,
+                fw_version_patch: 4,
+                fw_version_suffix: *b"a\0\0",
+            },
+            mram_size: SIMULATOR_DEFAULT_MRAM_SIZE,
+            request_update: false,
+            faults: None,
+        }
+    }
+}
+
+// Transfer announced by FW_UPDATE_START
+#[derive(Debug, Clone)]
+struct UpdateSession {
+    size: u32,
+    crc32: u32,
+    chunk_size: u16,
+    next_offset: u32,
+    last_chunk: Option<(u32, usize)>,   // Offset and length of the last chunk written
+    running_crc: u32,
+}
+
+pub struct SimulatedDevice {
+    config: SimulatorConfig,
+    sound_enabled: bool,
+    mram: Vec<u8>,
+    update: Option<UpdateSession>,
+    verified_crc32: Option<u32>,        // Image that passed FW_UPDATE_END, waiting for RESET(2)
+    applied_crc32: Option<u32>,
+    end_result: Option<u32>,            // Result of the last FW_UPDATE_END, repeated if it is resent
+    reset_count: u32,
+    decoder: GcpDecoder,
+    faults: Option<FaultInjector>,
+    last_response: Vec<u8>,
+}
+
+// Response bytes as a faulty link delivers them
+struct Transmission {
+    bytes: Vec<u8>,
+    delay: Duration,
+    piece_size: Option<usize>,
+}
+
+impl Transmission {
+    fn send(&self, transport: &mut dyn GcpTransport) -> io::Result<()> {
+        thread::sleep(self.delay);
+        match self.piece_size {
+            Some(piece_size) => {
+                for piece in self.bytes.chunks(piece_size) {
+                    transport.write_all(piece)?;
+                    transport.flush()?;
+                    thread::sleep(Duration::from_millis(SIMULATOR_SPLIT_GAP_MS));
+                }
+                Ok(())
+            }
+            None => {
+                transport.write_all(&self.bytes)?;
+                transport.flush()
+            }
+        }
+    }
+}
+
+impl SimulatedDevice {
+    pub fn new(config: SimulatorConfig) -> Self {
+        Self {
+            mram: vec![0xFF; config.mram_size],
+            faults: config.faults.clone().map(FaultInjector::new),
+            config,
+            sound_enabled: true,
+            update: None,
+            verified_crc32: None,
+            applied_crc32: None,
+            end_result: None,
+            reset_count: 0,
+            // Oversized frames are still taken in whole, to be NACK'd with SIZE
+            decoder: GcpDecoder::new(u16::MAX as usize + 4),
+            last_response: Vec::new(),
+        }
+    }
+
+    pub fn config(&self) -> &SimulatorConfig {
+        &self.config
+    }
+
+    pub fn mram(&self) -> &[u8] {
+        &self.mram
+    }
+
+    pub fn sound_enabled(&self) -> bool {
+        self.sound_enabled
+    }
+
+    pub fn reset_count(&self) -> u32 {
+        self.reset_count
+    }
+
+    // CRC32 of the last image applied by RESET(2)
+    pub fn applied_crc32(&self) -> Option<u32> {
+        self.applied_crc32
+    }
+
+    pub fn faults(&self) -> Option<&FaultInjector> {
+        self.faults.as_ref()
+    }
+
+    // Buffer received bytes and answer every complete frame among them
+    pub fn handle_bytes(&mut self, bytes: &[u8]) -> Vec<GcpFrame> {
+        self.decoder.push(bytes);
+        let mut responses = Vec::new();
+        while let Some((raw_type, frame_bytes)) = self.next_frame() {
+            responses.extend(self.handle_raw_frame(raw_type, &frame_bytes));
+        }
+        responses
+    }
+
+    // As handle_bytes, with the fault script deciding how each response goes out
+    fn transmissions(&mut self, bytes: &[u8]) -> Vec<Transmission> {
+        self.decoder.push(bytes);
+        let mut transmissions = Vec::new();
+        while let Some((raw_type, frame_bytes)) = self.next_frame() {
+            let fault = self.faults.as_mut().and_then(|faults| faults.next_fault(raw_type));
+            transmissions.extend(self.respond_with_fault(raw_type, &frame_bytes, fault));
+        }
+        transmissions
+    }
+
+    fn respond_with_fault(&mut self, raw_type: u16, frame_bytes: &[u8], fault: Option<Fault>) -> Option<Transmission> {
+        let response = match fault {
+            Some(Fault::DropRequest) => return None,
+            Some(Fault::Reboot) => {
+                self.reboot();
+                return None;
+            }
+            Some(Fault::Nack(error)) => nack(raw_type, request_seq_no(raw_type, frame_bytes), error),
+            _ => self.handle_raw_frame(raw_type, frame_bytes)?,
+        };
+
+        let clean = response.serialize();
+        let mut transmission = Transmission { bytes: clean.clone(), delay: Duration::ZERO, piece_size: None };
+        match fault {
+            Some(Fault::DropResponse) => return None,
+            Some(Fault::CorruptCrc) => {
+                if let Some(crc_byte) = transmission.bytes.last_mut() {
+                    *crc_byte ^= 0xFF;
+                }
+            }
+            Some(Fault::Garbage(len)) => {
+                let mut noisy = self.faults.as_mut().map(|faults| faults.garbage(len)).unwrap_or_default();
+                noisy.extend_from_slice(&clean);
+                transmission.bytes = noisy;
+            }
+            Some(Fault::Split(piece_size)) => transmission.piece_size = Some(piece_size.max(1)),
+            Some(Fault::Coalesce) => transmission.bytes = [self.last_response.as_slice(), &clean].concat(),
+            Some(Fault::Delay(ms)) => transmission.delay = Duration::from_millis(ms),
+            _ => {}
+        }
+
+        self.last_response = clean;
+        Some(transmission)
+    }
+
+    // Take the next complete frame out of the receive buffer, with its raw MsgType
+    fn next_frame(&mut self) -> Option<(u16, Vec<u8>)> {
+        let frame_bytes = self.decoder.next_raw_frame()?;
+        Some((u16::from_le_bytes([frame_bytes[4], frame_bytes[5]]), frame_bytes))
+    }
+
+    fn handle_raw_frame(&mut self, raw_type: u16, frame_bytes: &[u8]) -> Option<GcpFrame> {
+        if frame_bytes.len() > SIMULATOR_RX_BUFFER_SIZE {
+            return Some(nack(raw_type, 0, GcpError::Size));
+        }
+
+        match GcpMessage::decode(frame_bytes) {
+            Ok(message) => self.handle_message(&message),
+            Err(GcpCommError::Framing { reason: FramingError::Crc16 { .. } }) => Some(nack(raw_type, 0, GcpError::Crc)),
+            Err(e @ GcpCommError::Framing { .. }) => {
+                ProtocolEvent::new(LogLevel::Warn, TARGET_SIM, "")
+                    .msg_type(raw_type)
+                    .log(format!("Simulator dropped malformed frame: {}", e));
+                None
+            }
+            // An intact frame whose payload does not fit its command's layout
+            Err(e) => Some(nack(raw_type, 0, payload_error(&e))),
+        }
+    }
+
+    // Answer one host message; `None` when the spec defines no response
+    pub fn handle_message(&mut self, message: &GcpMessage) -> Option<GcpFrame> {
+        match message {
+            // §4.1: ACK carrying the bare HWVersion_t
+            GcpMessage::Hello => Some(GcpFrame::with_data(GcpCommand::Ack, Vec::new(), self.config.hardware.to_bytes())),
+            GcpMessage::Ping => Some(ack(GcpCommand::Ping, 0, &[])),
+            GcpMessage::Reset { reset_type } => Some(self.reset(*reset_type)),
+            GcpMessage::FwUpdateStart { size, crc32, chunk_size } => Some(self.start_update(*size, *crc32, *chunk_size)),
+            GcpMessage::FwUpdateData { offset, data } => Some(self.write_chunk(*offset, data)),
+            GcpMessage::FwUpdateEnd => Some(self.end_update()),
+            GcpMessage::FwUpdateAbort => {
+                self.update = None;
+                self.end_result = None;
+                Some(ack(GcpCommand::FwUpdateAbort, 0, &[]))
+            }
+            // §4.7: the device leaves update mode, nothing is sent back
+            GcpMessage::FwNoUpdateAvailable => {
+                self.update = None;
+                None
+            }
+            // §4.9: MsgType + status data, no SeqNo
+            GcpMessage::GetStatus => {
+                let mut data = (GcpCommand::GetStatus as u16).to_le_bytes().to_vec();
+                data.extend_from_slice(&self.config.status.to_bytes());
+                Some(GcpFrame::with_data(GcpCommand::Ack, Vec::new(), data))
+            }
+            GcpMessage::SetConfig(config) => Some(self.set_config(config)),
+            // The host parses these with a SeqNo, like every other data-carrying ACK
+            GcpMessage::GetDiagnostics => Some(ack(GcpCommand::GetDiagnostics, 0, &self.config.diagnostics.to_bytes())),
+            GcpMessage::GetFwVersion => Some(ack(GcpCommand::GetFwVersion, 0, &self.config.fw_version.to_bytes())),
+            // Listed in §2.3 but without a payload definition in v2.2
+            GcpMessage::GetInfo => Some(nack(GcpCommand::GetInfo as u16, 0, GcpError::UnknownCmd)),
+            GcpMessage::Unknown(msg_type, _) => Some(nack(*msg_type, 0, GcpError::UnknownCmd)),
+            // Device-to-host messages are never answered
+            GcpMessage::Ack(_) | GcpMessage::Nack(_) | GcpMessage::FwUpdateRequest(_) => None,
+        }
+    }
+
+    fn reset(&mut self, reset_type: u16) -> GcpFrame {
+        match reset_type {
+            RESET_SOFTWARE => {}
+            RESET_APPLY_UPDATE => match self.verified_crc32.take() {
+                Some(crc32) => self.applied_crc32 = Some(crc32),
+                None => return nack(GcpCommand::Reset as u16, 0, GcpError::InvalidParam),
+            },
+            _ => return nack(GcpCommand::Reset as u16, 0, GcpError::InvalidParam),
+        }
+
+        // A commanded reset also ends any transfer in progress
+        self.update = None;
+        self.end_result = None;
+        self.reboot();
+        ack(GcpCommand::Reset, 0, &[])
+    }
+
+    // Power cycle: whatever was being received is lost, along with the transfer in
+    // progress. MRAM survives.
+    fn reboot(&mut self) {
+        self.decoder.clear();
+        self.update = None;
+        self.reset_count += 1;
+    }
+
+    fn start_update(&mut self, size: u32, crc32: u32, chunk_size: u16) -> GcpFrame {
+        let reject = |error| nack(GcpCommand::FwUpdateStart as u16, 0, error);
+        if size == 0 || chunk_size == 0 {
+            return reject(GcpError::InvalidParam);
+        }
+        if size as usize > self.mram.len() || chunk_size as usize + SIMULATOR_DATA_FRAME_OVERHEAD > SIMULATOR_RX_BUFFER_SIZE {
+            return reject(GcpError::Size);
+        }
+
+        // Every START begins a new transfer (§4.3), even one announcing the same image
+        self.update = Some(UpdateSession {
+            size,
+            crc32,
+            chunk_size,
+            next_offset: 0,
+            last_chunk: None,
+            running_crc: 0xFFFFFFFF,
+        });
+        self.verified_crc32 = None;
+        self.end_result = None;
+
+        ack(GcpCommand::FwUpdateStart, 0, &[])
+    }
+
+    // FW_UPDATE_DATA: SeqNo is the chunk's byte offset
+    fn write_chunk(&mut self, offset: u32, chunk: &[u8]) -> GcpFrame {
+        let reject = |error| nack(GcpCommand::FwUpdateData as u16, offset, error);
+
+        let Some(session) = self.update.as_mut() else {
+            return reject(GcpError::InvalidParam);
+        };
+        if chunk.is_empty() || chunk.len() > session.chunk_size as usize || offset as usize + chunk.len() > session.size as usize {
+            return reject(GcpError::Size);
+        }
+
+        // The host resends a chunk whose ACK it missed; it is already written
+        if session.last_chunk == Some((offset, chunk.len())) {
+            return ack(GcpCommand::FwUpdateData, offset, &[]);
+        }
+
+        // Offset 0 restarts the transfer; anything else must follow the last chunk
+        if offset == 0 {
+            session.next_offset = 0;
+            session.running_crc = 0xFFFFFFFF;
+        } else if offset != session.next_offset {
+            return reject(GcpError::Seq);
+        }
+
+        let start = offset as usize;
+        self.mram[start..start + chunk.len()].copy_from_slice(chunk);
+        session.running_crc = gcp_crc32_update(session.running_crc, chunk);
+        session.next_offset = offset + chunk.len() as u32;
+        session.last_chunk = Some((offset, chunk.len()));
+
+        ack(GcpCommand::FwUpdateData, offset, &[])
+    }
+
+    fn end_update(&mut self) -> GcpFrame {
+        let Some(session) = self.update.take() else {
+            // END resent because its ACK was lost: report the same result again
+            return match self.end_result {
+                Some(result) => ack(GcpCommand::FwUpdateEnd, 0, &result.to_le_bytes()),
+                None => nack(GcpCommand::FwUpdateEnd as u16, 0, GcpError::InvalidParam),
+            };
+        };
+
+        let complete = session.next_offset == session.size;
+        let result = if complete && session.running_crc ^ 0xFFFFFFFF == session.crc32 {
+            self.verified_crc32 = Some(session.crc32);
+            FW_VERIFY_MATCH
+        } else {
+            FW_VERIFY_MISMATCH
+        };
+        self.end_result = Some(result);
+
+        ack(GcpCommand::FwUpdateEnd, 0, &result.to_le_bytes())
+    }
+
+    // SET_CONFIG (§4.12); sub-command and size were checked when decoding
+    fn set_config(&mut self, config: &GcpConfig) -> GcpFrame {
+        if config.validate().is_err() {
+            return nack(GcpCommand::SetConfig as u16, 0, GcpError::InvalidParam);
+        }
+
+        match *config {
+            GcpConfig::Time(time) => {
+                self.config.status.rtc_time[..7].copy_from_slice(&time.to_bytes());
+                self.config.status.rtc_time[7] = 0;
+            }
+            GcpConfig::Brightness(level) => self.config.status.led_brightness = level,
+            GcpConfig::Sound(enabled) => self.sound_enabled = enabled,
+        }
+
+        ack(GcpCommand::SetConfig, 0, &[])
+    }
+
+    // Answer frames from `transport` until the peer closes it or `stop` is set
+    pub fn serve(&mut self, transport: &mut dyn GcpTransport, stop: &AtomicBool) -> io::Result<()> {
+        transport.set_timeout(Duration::from_millis(SIMULATOR_POLL_MS))?;
+        let mut buf = [0u8; 4096];
+
+        if self.config.request_update {
+            transport.write_all(&GcpMessage::FwUpdateRequest(self.config.fw_version.clone()).encode())?;
+            transport.flush()?;
+        }
+
+        while !stop.load(Ordering::Relaxed) {
+            match transport.read(&mut buf) {
+                Ok(0) => return Ok(()),
+                Ok(count) => {
+                    for transmission in self.transmissions(&buf[..count]) {
+                        transmission.send(transport)?;
+                    }
+                }
+                Err(e) if matches!(e.kind(), io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted) => {}
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(())
+    }
+}
+
+// Start a simulated device on its own thread and return the host end of the link.
+// The device thread exits when the returned transport is dropped.
+pub fn spawn_memory_device(config: SimulatorConfig) -> MemoryTransport {
+    let (host, mut device_link) = MemoryTransport::pair();
+    let serial_number = config.hardware.serial_number;
+
+    thread::spawn(move || {
+        let mut device = SimulatedDevice::new(config);
+        if let Err(e) = device.serve(&mut device_link, &AtomicBool::new(false)) {
+            ProtocolEvent::new(LogLevel::Warn, TARGET_SIM, "")
+                .log(format!("Simulated device {} stopped: {}", serial_number, e));
+        }
+    });
+
+    host
+}
+
+// SeqNo a NACK should echo: the chunk offset for FW_UPDATE_DATA, 0 otherwise
+fn request_seq_no(raw_type: u16, frame_bytes: &[u8]) -> u32 {
+    match frame_bytes.get(6..10) {
+        Some(seq) if raw_type == GcpCommand::FwUpdateData as u16 => u32::from_le_bytes([seq[0], seq[1], seq[2], seq[3]]),
+        _ => 0,
+    }
+}
+
+// NACK code for a payload that does not fit its command: too short is a size error,
+// anything else (too long, non-zero reserved bytes, unknown sub-command) a bad parameter
+fn payload_error(error: &GcpCommError) -> GcpError {
+    match error {
+        GcpCommError::TruncatedPayload { .. } => GcpError::Size,
+        _ => GcpError::InvalidParam,
+    }
+}
+
+// ACK payload: MsgType(2) + SeqNo(4) + response data (§4.2)
+fn ack(request: GcpCommand, seq_no: u32, data: &[u8]) -> GcpFrame {
+    let mut payload = (request as u16).to_le_bytes().to_vec();
+    payload.extend_from_slice(&seq_no.to_le_bytes());
+    payload.extend_from_slice(data);
+    GcpFrame::with_data(GcpCommand::Ack, Vec::new(), payload)
+}
+
+// NACK payload: MsgType(2) + SeqNo(4) + Error(2); the raw MsgType is echoed, even unknown ones
+fn nack(msg_type: u16, seq_no: u32, error: GcpError) -> GcpFrame {
+    let mut payload = msg_type.to_le_bytes().to_vec();
+    payload.extend_from_slice(&seq_no.to_le_bytes());
+    payload.extend_from_slice(&(error as u16).to_le_bytes());
+    GcpFrame::with_data(GcpCommand::Nack, Vec::new(), payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gcp::{
+        connect_to_port, disconnect_from_port, execute_with_connection, gcp_crc16, gcp_crc32, GcpConfig, GcpHelloResponse,
+        NackFrame,
+    };
+    use crate::link_config::LinkConfig;
+
+    fn single_response(device: &mut SimulatedDevice, frame: &GcpFrame) -> GcpFrame {
+        let mut responses = device.handle_bytes(&frame.serialize());
+        assert_eq!(responses.len(), 1);
+        responses.remove(0)
+    }
+
+    #[test]
+    fn test_firmware_update_against_simulator() {
+        let port = format!("{}4242", SIMULATOR_PORT_PREFIX);
+        connect_to_port(port.clone(), LinkConfig::default()).unwrap();
+
+        let image: Vec<u8> = (0..5000u32).map(|i| (i * 7) as u8).collect();
+        let mut corrupted = image.clone();
+        corrupted[4000] ^= 0x01;
+
+        let result = execute_with_connection(&port, |handler| {
+            let serial = match handler.send_hello()? {
+                GcpHelloResponse::Hardware(hardware) => hardware.serial_number,
+                GcpHelloResponse::Status(_) => panic!("Simulator answered HELLO with status data"),
+            };
+
+            handler.set_config(GcpConfig::Brightness(75))?;
+            let status = handler.get_status()?;
+            let version = handler.get_fw_version()?;
+            handler.get_diagnostics()?;
+
+            let mut results = Vec::new();
+            for data in [&image, &corrupted] {
+                // START always announces the CRC32 of the genuine image
+                handler.start_firmware_update(&image, 1024)?;
+                for (index, chunk) in data.chunks(1024).enumerate() {
+                    handler.send_firmware_chunk(chunk, (index * 1024) as u32)?;
+                }
+                results.push(handler.end_firmware_update()?);
+            }
+            Ok((serial, status.led_brightness, version.version_string(), results))
+        });
+        disconnect_from_port(port).unwrap();
+
+        let (serial, brightness, version, results) = result.unwrap();
+        assert_eq!(serial, 4242);
+        assert_eq!(brightness, 75);
+        assert_eq!(version, "0.1.4a");
+        assert_eq!(results, vec![true, false]);
+    }
+
+    #[test]
+    fn test_simulator_rejects_bad_frames() {
+        let mut device = SimulatedDevice::new(SimulatorConfig::default());
+        let image = [0x5Au8; 300];
+
+        let mut start = Vec::new();
+        start.extend_from_slice(&(image.len() as u32).to_le_bytes());
+        start.extend_from_slice(&gcp_crc32(&image).to_le_bytes());
+        start.extend_from_slice(&100u16.to_le_bytes());
+        start.extend_from_slice(&[0, 0]);
+        let response = single_response(&mut device, &GcpFrame::with_parameters(GcpCommand::FwUpdateStart, start));
+        assert_eq!(response.msg_type, GcpCommand::Ack);
+
+        let chunk = |offset: u32, len: usize| {
+            GcpFrame::with_data(GcpCommand::FwUpdateData, offset.to_le_bytes().to_vec(), image[..len].to_vec())
+        };
+        assert_eq!(single_response(&mut device, &chunk(0, 100)).msg_type, GcpCommand::Ack);
+        // A resent chunk is ACK'd again, a skipped one is a sequence error
+        assert_eq!(single_response(&mut device, &chunk(0, 100)).msg_type, GcpCommand::Ack);
+        let nack = NackFrame::decode(&single_response(&mut device, &chunk(200, 100))).unwrap();
+        assert_eq!((nack.seq_no, nack.error()), (200, Some(GcpError::Seq)));
+        let nack = NackFrame::decode(&single_response(&mut device, &chunk(100, 101))).unwrap();
+        assert_eq!(nack.error(), Some(GcpError::Size));
+        assert_eq!(&device.mram()[..100], &image[..100]);
+
+        // Corrupt CRC16, unknown command, and line noise before a valid frame
+        let mut corrupt = GcpFrame::new(GcpCommand::Ping).serialize();
+        corrupt[6] ^= 0xFF;
+        let nack = NackFrame::decode(&device.handle_bytes(&corrupt)[0]).unwrap();
+        assert_eq!(nack.error(), Some(GcpError::Crc));
+
+        let mut bytes = GcpFrame::new(GcpCommand::Ping).serialize();
+        bytes[4..6].copy_from_slice(&0x3001u16.to_le_bytes());
+        let crc = gcp_crc16(&bytes[2..8]);
+        bytes[8..].copy_from_slice(&crc.to_le_bytes());
+        let nack = NackFrame::decode(&device.handle_bytes(&bytes)[0]).unwrap();
+        assert_eq!((nack.msg_type, nack.error()), (0x3001, Some(GcpError::UnknownCmd)));
+
+        let mut noisy = vec![0x00, 0xAA, 0x13];
+        noisy.extend_from_slice(&GcpFrame::new(GcpCommand::Ping).serialize());
+        assert_eq!(device.handle_bytes(&noisy).len(), 1);
+
+        // Nothing verified yet, so there is no update to apply
+        let reset = GcpFrame::with_parameters(GcpCommand::Reset, 2u16.to_le_bytes().to_vec());
+        assert_eq!(single_response(&mut device, &reset).msg_type, GcpCommand::Nack);
+    }
+}
@@ -0,0 +1,365 @@
+//! Byte transports for GCP connections
+//!
+//! `GcpUartHandler` only needs a bidirectional byte stream with read timeouts, so
+//! the link is abstracted behind `GcpTransport`:
+//!
+//! - `SerialTransport`: a local UART, 8N1 at the connection's `LinkConfig` baud rate
+//!   and flow control (115200 with RTS/CTS per spec §8 by default)
+//! - `TcpTransport`: a raw TCP socket, e.g. a ser2net port on a bench machine,
+//!   addressed as `tcp://host:port`
+//! - `MemoryTransport`: an in-process duplex pipe for tests and the simulator,
+//!   which is opened as `sim://[serial]`
+//!
+//! Reads that time out fail with `ErrorKind::TimedOut` on every transport.
+
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::gcp::{GcpCommError, TransportOp, GCP_TIMEOUT_MS};
+use crate::link_config::{FlowControl, LinkConfig};
+use crate::simulator::{spawn_memory_device, SimulatorConfig, SIMULATOR_PORT_PREFIX};
+
+pub const TCP_TRANSPORT_PREFIX: &str = "tcp://";
+const TCP_CONNECT_TIMEOUT_MS: u64 = 3000;
+
+pub trait GcpTransport: Send {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize>;
+    fn write_all(&mut self, data: &[u8]) -> io::Result<()>;
+    fn flush(&mut self) -> io::Result<()>;
+    fn set_timeout(&mut self, timeout: Duration) -> io::Result<()>;
+    // Bytes that can be read without blocking
+    fn bytes_to_read(&mut self) -> io::Result<usize>;
+    // Drop everything received but not yet read; returns the number of bytes dropped
+    fn discard_input(&mut self) -> io::Result<usize>;
+}
+
+// Open the transport a connection name refers to: `tcp://host:port`, `sim://[serial]`
+// or a serial port
+pub fn open_transport(port_name: &str, link: &LinkConfig) -> Result<Box<dyn GcpTransport>, GcpCommError> {
+    if let Some(address) = port_name.strip_prefix(TCP_TRANSPORT_PREFIX) {
+        return Ok(Box::new(TcpTransport::connect(address, link)?));
+    }
+    if let Some(serial) = port_name.strip_prefix(SIMULATOR_PORT_PREFIX) {
+        let mut config = SimulatorConfig::default();
+        if let Ok(serial_number) = serial.parse() {
+            config.hardware.serial_number = serial_number;
+        }
+        return Ok(Box::new(spawn_memory_device(config)));
+    }
+    Ok(Box::new(SerialTransport::open(port_name, link)?))
+}
+
+pub struct SerialTransport {
+    port: Box<dyn serialport::SerialPort>,
+}
+
+impl SerialTransport {
+    pub fn open(port_name: &str, link: &LinkConfig) -> Result<Self, GcpCommError> {
+        let flow_control = match link.flow_control {
+            FlowControl::None => serialport::FlowControl::None,
+            FlowControl::Hardware => serialport::FlowControl::Hardware,
+            FlowControl::Software => serialport::FlowControl::Software,
+        };
+        let port = serialport::new(port_name, link.baud_rate)
+            .timeout(Duration::from_millis(link.timeout_ms))
+            .data_bits(serialport::DataBits::Eight)
+            .flow_control(flow_control)
+            .parity(serialport::Parity::None)
+            .stop_bits(serialport::StopBits::One)
+            .open()
+            .map_err(|e| GcpCommError::transport(TransportOp::Open, format!("Failed to open port {}: {}", port_name, e)))?;
+
+        Ok(Self { port })
+    }
+
+    // Wrap an already open port, e.g. one end of a PTY pair
+    pub fn from_port(port: Box<dyn serialport::SerialPort>) -> Self {
+        Self { port }
+    }
+}
+
+impl GcpTransport for SerialTransport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.port.read(buf)
+    }
+
+    fn write_all(&mut self, data: &[u8]) -> io::Result<()> {
+        self.port.write_all(data)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.port.flush()
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.port.set_timeout(timeout).map_err(io::Error::from)
+    }
+
+    fn bytes_to_read(&mut self) -> io::Result<usize> {
+        self.port.bytes_to_read().map(|n| n as usize).map_err(io::Error::from)
+    }
+
+    fn discard_input(&mut self) -> io::Result<usize> {
+        let pending = self.bytes_to_read()?;
+        self.port.clear(serialport::ClearBuffer::Input).map_err(io::Error::from)?;
+        Ok(pending)
+    }
+}
+
+pub struct TcpTransport {
+    stream: TcpStream,
+}
+
+impl TcpTransport {
+    pub fn connect(address: &str, link: &LinkConfig) -> Result<Self, GcpCommError> {
+        let open_error = |message: String| GcpCommError::transport(TransportOp::Open, message);
+
+        let socket_address = address.to_socket_addrs()
+            .map_err(|e| open_error(format!("Invalid TCP address {}: {}", address, e)))?
+            .next()
+            .ok_or_else(|| open_error(format!("TCP address {} did not resolve", address)))?;
+        let stream = TcpStream::connect_timeout(&socket_address, Duration::from_millis(TCP_CONNECT_TIMEOUT_MS))
+            .map_err(|e| open_error(format!("Failed to connect to {}: {}", address, e)))?;
+
+        // Frames are small; send each one immediately
+        stream.set_nodelay(true)
+            .and_then(|()| stream.set_read_timeout(Some(Duration::from_millis(link.timeout_ms))))
+            .map_err(|e| GcpCommError::transport(TransportOp::Configure, format!("Failed to configure {}: {}", address, e)))?;
+
+        Ok(Self { stream })
+    }
+
+    pub fn from_stream(stream: TcpStream, link: &LinkConfig) -> io::Result<Self> {
+        stream.set_nodelay(true)?;
+        stream.set_read_timeout(Some(Duration::from_millis(link.timeout_ms)))?;
+        Ok(Self { stream })
+    }
+
+    // Run `f` with the socket in non-blocking mode
+    fn nonblocking<T>(&mut self, f: impl FnOnce(&mut TcpStream) -> io::Result<T>) -> io::Result<T> {
+        self.stream.set_nonblocking(true)?;
+        let result = f(&mut self.stream);
+        self.stream.set_nonblocking(false)?;
+        result
+    }
+}
+
+impl GcpTransport for TcpTransport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self.stream.read(buf) {
+            // Unix reports an expired read timeout as WouldBlock
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Err(io::Error::new(io::ErrorKind::TimedOut, e)),
+            other => other,
+        }
+    }
+
+    fn write_all(&mut self, data: &[u8]) -> io::Result<()> {
+        self.stream.write_all(data)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stream.flush()
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        // A zero timeout would mean "block forever" for sockets
+        self.stream.set_read_timeout(Some(timeout.max(Duration::from_millis(1))))
+    }
+
+    fn bytes_to_read(&mut self) -> io::Result<usize> {
+        let mut buf = [0u8; 4096];
+        self.nonblocking(|stream| match stream.peek(&mut buf) {
+            Ok(0) => Err(io::Error::new(io::ErrorKind::ConnectionAborted, "TCP connection closed")),
+            Ok(n) => Ok(n),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => Ok(0),
+            Err(e) => Err(e),
+        })
+    }
+
+    fn discard_input(&mut self) -> io::Result<usize> {
+        let mut buf = [0u8; 1024];
+        self.nonblocking(|stream| {
+            let mut discarded = 0;
+            loop {
+                match stream.read(&mut buf) {
+                    Ok(0) => return Ok(discarded),
+                    Ok(n) => discarded += n,
+                    Err(e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(discarded),
+                    Err(e) => return Err(e),
+                }
+            }
+        })
+    }
+}
+
+// One direction of a memory pipe
+#[derive(Default)]
+struct Pipe {
+    buffer: Mutex<PipeState>,
+    ready: Condvar,
+}
+
+#[derive(Default)]
+struct PipeState {
+    data: VecDeque<u8>,
+    closed: bool,
+}
+
+// In-memory duplex link; bytes written to one end are read from the other
+pub struct MemoryTransport {
+    rx: Arc<Pipe>,
+    tx: Arc<Pipe>,
+    timeout: Duration,
+}
+
+impl MemoryTransport {
+    pub fn pair() -> (MemoryTransport, MemoryTransport) {
+        let a_to_b = Arc::new(Pipe::default());
+        let b_to_a = Arc::new(Pipe::default());
+        let timeout = Duration::from_millis(GCP_TIMEOUT_MS);
+        (
+            MemoryTransport { rx: b_to_a.clone(), tx: a_to_b.clone(), timeout },
+            MemoryTransport { rx: a_to_b, tx: b_to_a, timeout },
+        )
+    }
+
+    fn lock(pipe: &Pipe) -> io::Result<std::sync::MutexGuard<'_, PipeState>> {
+        pipe.buffer.lock().map_err(|_| io::Error::other("Memory transport lock poisoned"))
+    }
+}
+
+impl GcpTransport for MemoryTransport {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let deadline = Instant::now() + self.timeout;
+        let mut state = Self::lock(&self.rx)?;
+
+        while state.data.is_empty() {
+            if state.closed {
+                return Ok(0);
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Err(io::Error::new(io::ErrorKind::TimedOut, "Memory transport read timed out"));
+            }
+            state = self.rx.ready.wait_timeout(state, remaining)
+                .map_err(|_| io::Error::other("Memory transport lock poisoned"))?
+                .0;
+        }
+
+        let count = buf.len().min(state.data.len());
+        for (slot, byte) in buf.iter_mut().zip(state.data.drain(..count)) {
+            *slot = byte;
+        }
+        Ok(count)
+    }
+
+    fn write_all(&mut self, data: &[u8]) -> io::Result<()> {
+        let mut state = Self::lock(&self.tx)?;
+        if state.closed {
+            return Err(io::Error::new(io::ErrorKind::BrokenPipe, "Memory transport peer closed"));
+        }
+        state.data.extend(data);
+        self.tx.ready.notify_all();
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn set_timeout(&mut self, timeout: Duration) -> io::Result<()> {
+        self.timeout = timeout;
+        Ok(())
+    }
+
+    fn bytes_to_read(&mut self) -> io::Result<usize> {
+        let state = Self::lock(&self.rx)?;
+        // Like an unplugged USB device: nothing more will ever arrive
+        if state.closed && state.data.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::BrokenPipe, "Memory transport peer closed"));
+        }
+        Ok(state.data.len())
+    }
+
+    fn discard_input(&mut self) -> io::Result<usize> {
+        let mut state = Self::lock(&self.rx)?;
+        let discarded = state.data.len();
+        state.data.clear();
+        Ok(discarded)
+    }
+}
+
+impl Drop for MemoryTransport {
+    fn drop(&mut self) {
+        // Wake a peer blocked in read so it sees the link close
+        for pipe in [&self.rx, &self.tx] {
+            if let Ok(mut state) = pipe.buffer.lock() {
+                state.closed = true;
+            }
+            pipe.ready.notify_all();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_memory_transport() {
+        let (mut host, mut device) = MemoryTransport::pair();
+        host.write_all(&[0xAA, 0x55, 0x06]).unwrap();
+        assert_eq!(device.bytes_to_read().unwrap(), 3);
+
+        let mut buf = [0u8; 8];
+        assert_eq!(device.read(&mut buf).unwrap(), 3);
+        assert_eq!(&buf[..3], &[0xAA, 0x55, 0x06]);
+
+        // Nothing pending: reads time out
+        device.set_timeout(Duration::from_millis(20)).unwrap();
+        assert_eq!(device.read(&mut buf).unwrap_err().kind(), io::ErrorKind::TimedOut);
+
+        device.write_all(&[1, 2, 3, 4]).unwrap();
+        assert_eq!(host.discard_input().unwrap(), 4);
+        assert_eq!(host.bytes_to_read().unwrap(), 0);
+
+        // Closing one end ends the other's reads
+        drop(device);
+        assert_eq!(host.read(&mut buf).unwrap(), 0);
+        assert!(host.write_all(&[1]).is_err());
+        assert!(host.bytes_to_read().is_err());
+    }
+
+    #[test]
+    fn test_tcp_transport() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap().to_string();
+
+        let mut host = match open_transport(&format!("{}{}", TCP_TRANSPORT_PREFIX, address), &LinkConfig::default()) {
+            Ok(transport) => transport,
+            Err(e) => panic!("Failed to open TCP transport: {}", e),
+        };
+        let (stream, _) = listener.accept().unwrap();
+        let mut device = TcpTransport::from_stream(stream, &LinkConfig::default()).unwrap();
+
+        host.write_all(&[0xAA, 0x55]).unwrap();
+        host.flush().unwrap();
+        let mut buf = [0u8; 4];
+        assert_eq!(device.read(&mut buf).unwrap(), 2);
+
+        host.set_timeout(Duration::from_millis(20)).unwrap();
+        assert_eq!(host.read(&mut buf).unwrap_err().kind(), io::ErrorKind::TimedOut);
+
+        device.write_all(&[9, 9, 9]).unwrap();
+        let start = Instant::now();
+        while host.bytes_to_read().unwrap() < 3 && start.elapsed() < Duration::from_secs(1) {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(host.discard_input().unwrap(), 3);
+        assert_eq!(host.bytes_to_read().unwrap(), 0);
+    }
+}
@@ -6,6 +6,7 @@ import DeviceInfoBox from './components/DeviceInfoBox';
 import FirmwareUpdate from './components/FirmwareUpdate';
 import GCPCommunication from './components/GCPCommunication';
 import ProgressBarComponent from './components/ProgressBarComponent';
+import ProtocolConsole from './components/ProtocolConsole';
 import TauriIntegrationComponent from './components/TauriIntegrationComponent';
 import {
   ConnectionProvider,
@@ -70,6 +71,9 @@ function AppContent() {
         {/* GCP Communication Section */}
         <GCPCommunication />
 
+        {/* Protocol Console Section */}
+        <ProtocolConsole />
+
         {/* Binary File Viewer Section */}
         <BinaryFileViewer />
 
@@ -1,3 +1,4 @@
+import { formatGcpError } from '@/lib/gcpErrors';
 import { invoke } from '@tauri-apps/api/core';
 import { open } from '@tauri-apps/plugin-dialog';
 import React, { useState } from 'react';
@@ -75,7 +76,7 @@ const BinaryFileViewer: React.FC<BinaryFileViewerProps> = ({
       setBinaryData(data);
     } catch (error) {
       console.error('Error analyzing file:', error);
-      setError(`Failed to analyze file: ${error}`);
+      setError(`Failed to analyze file: ${formatGcpError(error)}`);
       setFileAnalysis(null);
       setBinaryData([]);
     }
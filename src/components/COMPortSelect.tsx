@@ -16,7 +16,9 @@ import {
   formatFirmwareVersion,
   formatManufactureDate,
 } from '@/lib/formatters';
-import type { COMPort } from '@/types/ConnectionTypes';
+import { formatGcpError } from '@/lib/gcpErrors';
+import { PortService } from '@/services/PortService';
+import type { COMPort, DiscoveryReport } from '@/types/ConnectionTypes';
 import { useState } from 'react';
 
 const COMPortSelect = () => {
@@ -36,6 +38,26 @@ const COMPortSelect = () => {
   const { connectToPort, refreshPorts, clearError } = useConnectionActions();
 
   const [selectedPort, setSelectedPort] = useState<string>('');
+  const [isDiscovering, setIsDiscovering] = useState(false);
+  const [discovery, setDiscovery] = useState<DiscoveryReport | null>(null);
+  const [discoveryError, setDiscoveryError] = useState<string | null>(null);
+
+  // Probe all ports and preselect the first Glitchi device found
+  const handleDiscover = async () => {
+    setIsDiscovering(true);
+    setDiscoveryError(null);
+    try {
+      const report = await PortService.discoverDevices();
+      setDiscovery(report);
+      if (report.devices.length > 0) {
+        setSelectedPort(report.devices[0].port.port);
+      }
+    } catch (err) {
+      setDiscoveryError(`Discovery failed: ${formatGcpError(err)}`);
+    } finally {
+      setIsDiscovering(false);
+    }
+  };
 
   const handleConnect = async () => {
     if (!selectedPort) return;
@@ -69,6 +91,8 @@ const COMPortSelect = () => {
             Connecting...
           </Badge>
         );
+      case 'error':
+        return <Badge variant="destructive">Not Responding</Badge>;
       default:
         return <Badge variant="outline">Disconnected</Badge>;
     }
@@ -96,7 +120,9 @@ const COMPortSelect = () => {
             <Select
               value={selectedPort}
               onValueChange={setSelectedPort}
-              disabled={connectionStatus === 'connected'}
+              disabled={
+                connectionStatus === 'connected' || connectionStatus === 'error'
+              }
             >
               <SelectTrigger className="flex-1">
                 <SelectValue placeholder="Choose a COM port..." />
@@ -124,7 +150,33 @@ const COMPortSelect = () => {
             >
               {isScanning ? 'Scanning...' : 'Refresh'}
             </Button>
+            <Button
+              variant="secondary"
+              size="sm"
+              className="transparent-button"
+              onClick={handleDiscover}
+              disabled={
+                isDemoMode || isDiscovering || connectionStatus !== 'disconnected'
+              }
+            >
+              {isDiscovering ? 'Searching...' : 'Find Devices'}
+            </Button>
           </div>
+          {discoveryError && (
+            <div className="mt-1 text-xs text-red-800">{discoveryError}</div>
+          )}
+          {discovery && (
+            <div className="mt-1 text-xs text-muted-foreground">
+              {discovery.devices.length === 0
+                ? `No Glitchi devices found (${discovery.skipped.length} ports checked)`
+                : discovery.devices
+                    .map(
+                      device =>
+                        `${device.port.port}: #${device.serial_number} ${device.board_type} ${device.chip_model}`
+                    )
+                    .join(', ')}
+            </div>
+          )}
         </div>
 
         {/* Connection Controls */}
@@ -8,9 +8,15 @@ import {
 } from '@/components/ui/card';
 import { useConnection } from '@/contexts/ConnectionContext';
 import { useConnectionActions } from '@/hooks/useConnectionActions';
+import { formatGcpError } from '@/lib/gcpErrors';
+import type {
+  DeviceRebootedEvent,
+  FirmwareCheckpoint,
+  FirmwareSignatureInfo,
+  FirmwareTrustConfig,
+} from '@/types/ConnectionTypes';
 import { invoke } from '@tauri-apps/api/core';
 import { listen } from '@tauri-apps/api/event';
-import { getCurrentWindow } from '@tauri-apps/api/window';
 import { open } from '@tauri-apps/plugin-dialog';
 import React, { useEffect, useRef, useState } from 'react';
 
@@ -24,17 +30,27 @@ interface FirmwareFile {
   chunkSize: number;
   estimatedTimeSeconds: number;
   estimatedTimeFormatted: string;
+  estimatedBytesPerSecond: number;
+  estimateSource: 'measured' | 'line_rate';
+  baseAddress: string;
+  entryPoint: string | null;
+  isIntelHex: boolean;
+  signature: FirmwareSignatureInfo;
   isValid: boolean;
   fileType: string;
 }
 
 interface FirmwareUpdateProgress {
+  job_id: string;
+  port_name: string;
   stage: string;
   current_chunk: number;
   total_chunks: number;
   bytes_sent: number;
   total_bytes: number;
   percentage: number;
+  chunk_size: number;
+  bytes_per_second: number;
   status: string;
 }
 
@@ -44,14 +60,50 @@ interface FirmwareUpdateResult {
   crc32_match: boolean;
   total_chunks: number;
   total_bytes: number;
+  bytes_per_second: number;
 }
 
+type FirmwareJobState =
+  | 'running'
+  | 'paused'
+  | 'completed'
+  | 'failed'
+  | 'cancelled';
+
+interface FirmwareJobStatus {
+  job_id: string;
+  port_name: string;
+  state: FirmwareJobState;
+  bytes_acked: number;
+  device_serial: number | null;
+  signature: FirmwareSignatureInfo | null;
+  total_bytes: number;
+  total_chunks: number;
+  chunk_size: number;
+  bytes_per_second: number;
+  message: string;
+  result: FirmwareUpdateResult | null;
+  error: unknown | null;
+}
+
+const isJobFinished = (state: FirmwareJobState) =>
+  state === 'completed' || state === 'failed' || state === 'cancelled';
+
+const describeSignature = (signature: FirmwareSignatureInfo) => {
+  if (!signature.signed) {
+    return 'Unsigned';
+  }
+  return signature.trusted_key
+    ? `Signed by trusted key '${signature.trusted_key}'`
+    : 'Signed by an untrusted key';
+};
+
 interface FirmwareUpdateProps {
   className?: string;
 }
 
 const FirmwareUpdate: React.FC<FirmwareUpdateProps> = ({ className = '' }) => {
-  const { isConnected, connectedPort } = useConnection();
+  const { isConnected, connectedPort, hardwareInfo } = useConnection();
   const { invokeGCPCommand } = useConnectionActions();
 
   const [selectedFile, setSelectedFile] = useState<string>('');
@@ -60,6 +112,11 @@ const FirmwareUpdate: React.FC<FirmwareUpdateProps> = ({ className = '' }) => {
   const [isUpdating, setIsUpdating] = useState<boolean>(false);
   const [updateProgress, setUpdateProgress] =
     useState<FirmwareUpdateProgress | null>(null);
+  const [activeJob, setActiveJob] = useState<FirmwareJobStatus | null>(null);
+  const [allowUntrusted, setAllowUntrusted] = useState<boolean>(false);
+  const [checkpoint, setCheckpoint] = useState<FirmwareCheckpoint | null>(
+    null
+  );
   const [debugLogs, setDebugLogs] = useState<string[]>([]);
   const [error, setError] = useState<string>('');
 
@@ -77,15 +134,123 @@ const FirmwareUpdate: React.FC<FirmwareUpdateProps> = ({ className = '' }) => {
   useEffect(() => {
     const unlisten = listen('firmware-progress', event => {
       const progress = event.payload as FirmwareUpdateProgress;
+      // Batch jobs on other ports report here too
+      if (progress.port_name !== connectedPort) return;
       setUpdateProgress(progress);
       addDebugLog(`${progress.stage}: ${progress.status}`);
     });
 
+    return () => {
+      unlisten.then(fn => fn());
+    };
+  }, [connectedPort]);
+
+  // Report the firmware the device runs after a reset
+  useEffect(() => {
+    const unlisten = listen('device-rebooted', event => {
+      const rebooted = event.payload as DeviceRebootedEvent;
+      addDebugLog(
+        `Device back on ${rebooted.port_name} running firmware ${rebooted.fw_version_string}`
+      );
+    });
+
     return () => {
       unlisten.then(fn => fn());
     };
   }, []);
 
+  // Listen for firmware update job state changes
+  useEffect(() => {
+    const unlisten = listen('firmware-job-updated', event => {
+      const job = event.payload as FirmwareJobStatus;
+      setActiveJob(current =>
+        current === null || current.job_id === job.job_id ? job : current
+      );
+    });
+
+    return () => {
+      unlisten.then(fn => fn());
+    };
+  }, []);
+
+  // Developer override for unsigned firmware, set in firmware_trust.json
+  useEffect(() => {
+    invoke<FirmwareTrustConfig>('get_firmware_trust_config')
+      .then(config => setAllowUntrusted(config.allow_untrusted))
+      .catch(error =>
+        addDebugLog(
+          `Failed to load firmware trust config: ${formatGcpError(error)}`
+        )
+      );
+  }, []);
+
+  // Offer to resume a transfer that was interrupted on this device
+  useEffect(() => {
+    if (!isConnected || !hardwareInfo || isUpdating) {
+      setCheckpoint(null);
+      return;
+    }
+
+    invoke<FirmwareCheckpoint | null>('gcp_get_firmware_checkpoint', {
+      deviceSerial: hardwareInfo.serial_number,
+    })
+      .then(saved => {
+        setCheckpoint(saved);
+        if (saved) {
+          addDebugLog(
+            `Found interrupted transfer for device ${saved.device_serial} at offset ${saved.acked_offset} of ${saved.image_size}`
+          );
+        }
+      })
+      .catch(error =>
+        addDebugLog(
+          `Failed to load firmware checkpoint: ${formatGcpError(error)}`
+        )
+      );
+  }, [isConnected, hardwareInfo?.serial_number, isUpdating]);
+
+  // Report the outcome once the running job finishes
+  useEffect(() => {
+    if (!activeJob) {
+      return;
+    }
+
+    if (activeJob.state === 'paused') {
+      addDebugLog(`PAUSED: ${activeJob.message}`);
+      return;
+    }
+
+    if (!isJobFinished(activeJob.state)) {
+      return;
+    }
+
+    const result = activeJob.result;
+    if (activeJob.state === 'cancelled') {
+      addDebugLog(`CANCELLED: ${activeJob.message}`);
+    } else if (result && result.success) {
+      addDebugLog(`SUCCESS: ${result.message}`);
+      addDebugLog(`Chunks sent: ${result.total_chunks}`);
+      addDebugLog(`Total bytes: ${result.total_bytes}`);
+      addDebugLog(
+        `CRC32 verification: ${result.crc32_match ? 'PASSED' : 'FAILED'}`
+      );
+      addDebugLog('Firmware update completed successfully!');
+      addDebugLog('You can now reset the device to apply the new firmware.');
+    } else if (result) {
+      setError(result.message);
+      addDebugLog(`FAILED: ${result.message}`);
+    } else {
+      const errorMsg = `Firmware update failed: ${formatGcpError(
+        activeJob.error ?? activeJob.message
+      )}`;
+      setError(errorMsg);
+      addDebugLog(`ERROR: ${errorMsg}`);
+    }
+
+    setIsUpdating(false);
+    addDebugLog('=== FIRMWARE UPDATE PROCESS COMPLETED ===');
+  }, [activeJob?.job_id, activeJob?.state]);
+
   const addDebugLog = (message: string) => {
     const timestamp = new Date().toLocaleTimeString();
     const logEntry = `[${timestamp}] ${message}`;
@@ -139,18 +304,27 @@ const FirmwareUpdate: React.FC<FirmwareUpdateProps> = ({ className = '' }) => {
       // Use the new get_firmware_file_info command for better analysis
       const analysis = await invoke<FirmwareFile>('get_firmware_file_info', {
         filePath,
+        portName: connectedPort || null,
       });
       setFirmwareFile(analysis);
       addDebugLog(
         `File analysis complete: ${analysis.fileName} (${analysis.fileSizeFormatted})`
       );
+      if (analysis.isIntelHex) {
+        addDebugLog(`Intel HEX image base address: ${analysis.baseAddress}`);
+      }
       addDebugLog(`CRC32: ${analysis.crc32}`);
+      addDebugLog(`Signature: ${describeSignature(analysis.signature)}`);
       addDebugLog(`Estimated chunks: ${analysis.estimatedChunks}`);
       addDebugLog(
-        `Estimated transfer time: ${analysis.estimatedTimeFormatted}`
+        `Estimated transfer time: ${analysis.estimatedTimeFormatted} (${
+          analysis.estimateSource === 'measured'
+            ? 'from recent transfers'
+            : 'from UART line rate'
+        })`
       );
     } catch (error) {
-      const errorMsg = `Failed to analyze firmware file: ${error}`;
+      const errorMsg = `Failed to analyze firmware file: ${formatGcpError(error)}`;
       console.error('Error analyzing file:', error);
       setError(errorMsg);
       addDebugLog(`ERROR: ${errorMsg}`);
@@ -158,7 +332,11 @@ const FirmwareUpdate: React.FC<FirmwareUpdateProps> = ({ className = '' }) => {
     }
   };
 
-  const startFirmwareUpdate = async () => {
+  const startFirmwareUpdate = async (
+    resume: boolean = false,
+    filePath: string = selectedFile,
+    file: FirmwareFile | null = firmwareFile
+  ) => {
     if (!isConnected || !connectedPort) {
       const errorMsg = 'No device connected. Please connect to a device first.';
       setError(errorMsg);
@@ -166,7 +344,7 @@ const FirmwareUpdate: React.FC<FirmwareUpdateProps> = ({ className = '' }) => {
       return;
     }
 
-    if (!firmwareFile || !selectedFile) {
+    if (!file || !filePath) {
       const errorMsg =
         'No firmware file loaded. Please load a firmware file first.';
       setError(errorMsg);
@@ -177,11 +355,12 @@ const FirmwareUpdate: React.FC<FirmwareUpdateProps> = ({ className = '' }) => {
     setIsUpdating(true);
     setError('');
     setUpdateProgress(null);
+    setActiveJob(null);
     addDebugLog('=== STARTING FIRMWARE UPDATE PROCESS ===');
     addDebugLog(`Connected port: ${connectedPort}`);
-    addDebugLog(`Firmware file: ${firmwareFile.fileName}`);
-    addDebugLog(`File size: ${firmwareFile.fileSize} bytes`);
-    addDebugLog(`CRC32: ${firmwareFile.crc32}`);
+    addDebugLog(`Firmware file: ${file.fileName}`);
+    addDebugLog(`File size: ${file.fileSize} bytes`);
+    addDebugLog(`CRC32: ${file.crc32}`);
 
     try {
       // Step 1: Send HELLO command to verify connection
@@ -191,45 +370,95 @@ const FirmwareUpdate: React.FC<FirmwareUpdateProps> = ({ className = '' }) => {
         `Device responded to HELLO: ${JSON.stringify(helloResponse)}`
       );
 
-      // Step 2: Start the actual firmware update using Rust backend
+      // Step 2: Start the firmware update as a background job
       addDebugLog('Step 2: Starting firmware update...');
-      const window = getCurrentWindow();
-
-      const result = await invoke<FirmwareUpdateResult>('gcp_firmware_update', {
+      const job = await invoke<FirmwareJobStatus>('gcp_firmware_update', {
         portName: connectedPort,
-        filePath: selectedFile,
-        window,
+        filePath,
+        resume,
       });
-
-      if (result.success) {
-        addDebugLog(`SUCCESS: ${result.message}`);
-        addDebugLog(`Chunks sent: ${result.total_chunks}`);
-        addDebugLog(`Total bytes: ${result.total_bytes}`);
-        addDebugLog(
-          `CRC32 verification: ${result.crc32_match ? 'PASSED' : 'FAILED'}`
-        );
-
-        if (result.crc32_match) {
-          addDebugLog('Firmware update completed successfully!');
-          // Optionally ask user if they want to reset the device
-          addDebugLog(
-            'You can now reset the device to apply the new firmware.'
-          );
-        } else {
-          setError('Firmware verification failed - CRC32 mismatch');
-        }
-      } else {
-        setError(result.message);
-        addDebugLog(`FAILED: ${result.message}`);
-      }
+      setActiveJob(job);
+      addDebugLog(`Firmware update job ${job.job_id} started`);
     } catch (error) {
-      const errorMsg = `Firmware update failed: ${error}`;
+      const errorMsg = `Firmware update failed: ${formatGcpError(error)}`;
       console.error('Firmware update error:', error);
       setError(errorMsg);
       addDebugLog(`ERROR: ${errorMsg}`);
-    } finally {
       setIsUpdating(false);
-      addDebugLog('=== FIRMWARE UPDATE PROCESS COMPLETED ===');
+    }
+  };
+
+  const resumeInterruptedUpdate = async () => {
+    if (!checkpoint?.image_path) {
+      setError('The interrupted transfer did not record its firmware file.');
+      return;
+    }
+
+    addDebugLog(
+      `Restarting interrupted transfer of ${checkpoint.image_path} from offset 0`
+    );
+    setSelectedFile(checkpoint.image_path);
+    try {
+      const analysis = await invoke<FirmwareFile>('get_firmware_file_info', {
+        filePath: checkpoint.image_path,
+        portName: connectedPort || null,
+      });
+      setFirmwareFile(analysis);
+      await startFirmwareUpdate(true, checkpoint.image_path, analysis);
+    } catch (error) {
+      const errorMsg = `Failed to restart firmware update: ${formatGcpError(error)}`;
+      setError(errorMsg);
+      addDebugLog(`ERROR: ${errorMsg}`);
+    }
+  };
+
+  const discardCheckpoint = async () => {
+    if (!checkpoint) {
+      return;
+    }
+
+    try {
+      const result = await invoke<string>('gcp_discard_firmware_checkpoint', {
+        deviceSerial: checkpoint.device_serial,
+      });
+      setCheckpoint(null);
+      addDebugLog(result);
+    } catch (error) {
+      addDebugLog(`Failed to discard checkpoint: ${formatGcpError(error)}`);
+    }
+  };
+
+  const pauseFirmwareUpdate = async () => {
+    if (!activeJob) {
+      return;
+    }
+
+    try {
+      addDebugLog('Pausing firmware update after the current chunk...');
+      await invoke<FirmwareJobStatus>('gcp_pause_firmware_job', {
+        jobId: activeJob.job_id,
+      });
+    } catch (error) {
+      addDebugLog(`Failed to pause firmware update: ${formatGcpError(error)}`);
+    }
+  };
+
+  const resumeFirmwareUpdate = async () => {
+    if (!activeJob) {
+      return;
+    }
+
+    try {
+      addDebugLog(
+        `Resuming firmware update from offset ${activeJob.bytes_acked}...`
+      );
+      await invoke<FirmwareJobStatus>('gcp_resume_firmware_job', {
+        jobId: activeJob.job_id,
+      });
+    } catch (error) {
+      addDebugLog(
+        `Failed to resume firmware update: ${formatGcpError(error)}`
+      );
     }
   };
 
@@ -245,7 +474,7 @@ const FirmwareUpdate: React.FC<FirmwareUpdateProps> = ({ className = '' }) => {
       });
       addDebugLog(`Abort result: ${result}`);
     } catch (error) {
-      addDebugLog(`Failed to abort firmware update: ${error}`);
+      addDebugLog(`Failed to abort firmware update: ${formatGcpError(error)}`);
     }
   };
 
@@ -262,7 +491,7 @@ const FirmwareUpdate: React.FC<FirmwareUpdateProps> = ({ className = '' }) => {
       });
       addDebugLog(`Reset result: ${result}`);
     } catch (error) {
-      addDebugLog(`Failed to reset device: ${error}`);
+      addDebugLog(`Failed to reset device: ${formatGcpError(error)}`);
     }
   };
 
@@ -270,6 +499,7 @@ const FirmwareUpdate: React.FC<FirmwareUpdateProps> = ({ className = '' }) => {
     setSelectedFile('');
     setFirmwareFile(null);
     setUpdateProgress(null);
+    setActiveJob(null);
     setError('');
     clearDebugLogs();
     addDebugLog('Session cleared - ready for new firmware update');
@@ -297,7 +527,7 @@ const FirmwareUpdate: React.FC<FirmwareUpdateProps> = ({ className = '' }) => {
             </Button>
 
             <Button
-              onClick={startFirmwareUpdate}
+              onClick={() => startFirmwareUpdate()}
               disabled={
                 !isConnected || !firmwareFile || isLoading || isUpdating
               }
@@ -306,6 +536,22 @@ const FirmwareUpdate: React.FC<FirmwareUpdateProps> = ({ className = '' }) => {
               {isUpdating ? 'Updating...' : 'Start Update'}
             </Button>
 
+            {isUpdating && activeJob?.state === 'running' && (
+              <Button onClick={pauseFirmwareUpdate} variant="outline" size="sm">
+                Pause
+              </Button>
+            )}
+
+            {isUpdating && activeJob?.state === 'paused' && (
+              <Button
+                onClick={resumeFirmwareUpdate}
+                variant="outline"
+                size="sm"
+              >
+                Resume
+              </Button>
+            )}
+
             {isUpdating && (
               <Button
                 onClick={abortFirmwareUpdate}
@@ -363,7 +609,7 @@ const FirmwareUpdate: React.FC<FirmwareUpdateProps> = ({ className = '' }) => {
                   addDebugLog('=== FRAME CONSTRUCTION TEST ===');
                   result.split('\n').forEach(line => addDebugLog(line));
                 } catch (error) {
-                  addDebugLog(`Test error: ${error}`);
+                  addDebugLog(`Test error: ${formatGcpError(error)}`);
                 }
               }}
               disabled={isLoading || isUpdating}
@@ -374,6 +620,36 @@ const FirmwareUpdate: React.FC<FirmwareUpdateProps> = ({ className = '' }) => {
             </Button>
           </div>
 
+          {allowUntrusted && (
+            <p className="text-sm text-red-700">
+              Developer override is enabled in firmware_trust.json: unsigned or
+              untrusted firmware will be flashed
+            </p>
+          )}
+
+          {/* Interrupted Transfer */}
+          {checkpoint && !isUpdating && (
+            <div className="p-3 bg-yellow-50 border border-yellow-200 rounded-md text-sm text-yellow-800">
+              <p className="mb-2">
+                An earlier firmware transfer to this device stopped at{' '}
+                {checkpoint.acked_offset} of {checkpoint.image_size} bytes
+                {checkpoint.image_path && ` (${checkpoint.image_path})`}.
+              </p>
+              <div className="flex gap-2">
+                <Button
+                  onClick={resumeInterruptedUpdate}
+                  disabled={!checkpoint.image_path || isLoading}
+                  size="sm"
+                >
+                  Restart Transfer
+                </Button>
+                <Button onClick={discardCheckpoint} variant="outline" size="sm">
+                  Discard
+                </Button>
+              </div>
+            </div>
+          )}
+
           {/* Progress Bar */}
           {updateProgress && (
             <div className="p-3 bg-blue-50 border border-blue-200 rounded-md">
@@ -395,6 +671,8 @@ const FirmwareUpdate: React.FC<FirmwareUpdateProps> = ({ className = '' }) => {
                   Chunk {updateProgress.current_chunk} of{' '}
                   {updateProgress.total_chunks}({updateProgress.bytes_sent} /{' '}
                   {updateProgress.total_bytes} bytes)
+                  {updateProgress.bytes_per_second > 0 &&
+                    ` · ${(updateProgress.bytes_per_second / 1024).toFixed(1)} KB/s, ${updateProgress.chunk_size}-byte chunks`}
                 </div>
               </div>
             </div>
@@ -424,9 +702,28 @@ const FirmwareUpdate: React.FC<FirmwareUpdateProps> = ({ className = '' }) => {
                   <strong>Size:</strong> {firmwareFile.fileSizeFormatted} (
                   {firmwareFile.fileSize} bytes)
                 </div>
+                {firmwareFile.isIntelHex && (
+                  <div>
+                    <strong>Base Address:</strong> {firmwareFile.baseAddress}
+                    {firmwareFile.entryPoint &&
+                      ` (entry ${firmwareFile.entryPoint})`}
+                  </div>
+                )}
                 <div>
                   <strong>CRC32:</strong> {firmwareFile.crc32}
                 </div>
+                <div>
+                  <strong>Signature:</strong>{' '}
+                  <span
+                    className={
+                      firmwareFile.signature.trusted_key
+                        ? 'text-green-600'
+                        : 'text-red-600'
+                    }
+                  >
+                    {describeSignature(firmwareFile.signature)}
+                  </span>
+                </div>
                 <div>
                   <strong>Chunks:</strong> {firmwareFile.estimatedChunks} ×{' '}
                   {(firmwareFile.chunkSize / 1024).toFixed(1)}KB
@@ -1,5 +1,6 @@
 import { useConnection } from '@/contexts/ConnectionContext';
 import { useConnectionActions } from '@/hooks/useConnectionActions';
+import { formatGcpError } from '@/lib/gcpErrors';
 import {
   formatBoardType,
   formatChipModel,
@@ -76,7 +77,7 @@ const GCPCommunication: React.FC = () => {
         setError('');
       } catch (err) {
         console.error('Status polling error:', err);
-        setError(`Status polling failed: ${err}`);
+        setError(`Status polling failed: ${formatGcpError(err)}`);
         // Don't stop polling on single errors - device might be busy
       }
     }, 1000); // 1Hz = 1000ms interval
@@ -152,7 +153,7 @@ const GCPCommunication: React.FC = () => {
         console.error('Invalid HELLO response:', response);
       }
     } catch (err) {
-      setError(`HELLO command failed: ${err}`);
+      setError(`HELLO command failed: ${formatGcpError(err)}`);
       console.error('Error sending HELLO:', err);
     } finally {
       setIsLoading(false);
@@ -173,7 +174,7 @@ const GCPCommunication: React.FC = () => {
       setStatusData(response);
       console.log('Status Response:', response);
     } catch (err) {
-      setError(`Get status failed: ${err}`);
+      setError(`Get status failed: ${formatGcpError(err)}`);
       console.error('Error getting status:', err);
     } finally {
       setIsLoading(false);
@@ -196,7 +197,7 @@ const GCPCommunication: React.FC = () => {
       setFwVersionData(response);
       console.log('Firmware Version Response:', response);
     } catch (err) {
-      setError(`Get firmware version failed: ${err}`);
+      setError(`Get firmware version failed: ${formatGcpError(err)}`);
       console.error('Error getting firmware version:', err);
     } finally {
       setIsLoading(false);
@@ -239,7 +240,7 @@ const GCPCommunication: React.FC = () => {
       setFwUpdateStatus('Firmware update start acknowledged!');
       console.log('Firmware Update Start Response:', response);
     } catch (err) {
-      setError(`Firmware update start failed: ${err}`);
+      setError(`Firmware update start failed: ${formatGcpError(err)}`);
       setFwUpdateStatus('Firmware update start failed');
       console.error('Error starting firmware update:', err);
     } finally {
@@ -294,7 +295,7 @@ const GCPCommunication: React.FC = () => {
       console.log('Robustness Test Response:', response);
     } catch (err) {
       setRobustnessTestStatus(`❌ FAILED: ${randomSize}-byte packet failed`);
-      setError(`Robustness test failed: ${err}`);
+      setError(`Robustness test failed: ${formatGcpError(err)}`);
       console.error('Error in robustness test:', err);
     } finally {
       setIsLoading(false);
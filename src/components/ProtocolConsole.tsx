@@ -0,0 +1,178 @@
+import { Button } from '@/components/ui/button';
+import {
+  Card,
+  CardContent,
+  CardDescription,
+  CardHeader,
+  CardTitle,
+} from '@/components/ui/card';
+import { ScrollArea } from '@/components/ui/scroll-area';
+import {
+  Select,
+  SelectContent,
+  SelectItem,
+  SelectTrigger,
+  SelectValue,
+} from '@/components/ui/select';
+import { useConnection } from '@/contexts/ConnectionContext';
+import { formatGcpError } from '@/lib/gcpErrors';
+import { GCPService } from '@/services/GCPService';
+import type {
+  ProtocolEvent,
+  ProtocolLogLevel,
+} from '@/types/ConnectionTypes';
+import { invoke } from '@tauri-apps/api/core';
+import { listen } from '@tauri-apps/api/event';
+import { useEffect, useState } from 'react';
+
+const LEVELS: ProtocolLogLevel[] = ['error', 'warn', 'info', 'debug', 'trace'];
+const MAX_EVENTS = 500;
+
+const LEVEL_COLORS: Record<ProtocolLogLevel, string> = {
+  error: 'text-red-600',
+  warn: 'text-orange-600',
+  info: 'text-gray-800',
+  debug: 'text-blue-600',
+  trace: 'text-gray-400',
+};
+
+const formatFields = (event: ProtocolEvent) =>
+  [
+    event.msg_type !== null &&
+      `type=0x${event.msg_type.toString(16).padStart(4, '0').toUpperCase()}`,
+    event.seq !== null && `seq=${event.seq}`,
+    event.len !== null && `len=${event.len}`,
+    event.crc_ok !== null && `crc=${event.crc_ok ? 'ok' : 'bad'}`,
+    event.elapsed_ms !== null && `${event.elapsed_ms}ms`,
+  ]
+    .filter(Boolean)
+    .join(' ');
+
+const ProtocolConsole = () => {
+  const { connectedPort, isDemoMode } = useConnection();
+  const [events, setEvents] = useState<ProtocolEvent[]>([]);
+  const [level, setLevel] = useState<ProtocolLogLevel>('info');
+  const [capturePath, setCapturePath] = useState<string | null>(null);
+  const [captureStatus, setCaptureStatus] = useState<string>('');
+
+  // Start with what happened before the console was opened
+  useEffect(() => {
+    invoke<ProtocolLogLevel>('get_protocol_console_level')
+      .then(setLevel)
+      .catch(console.error);
+    invoke<ProtocolEvent[]>('get_protocol_log')
+      .then(setEvents)
+      .catch(console.error);
+  }, []);
+
+  useEffect(() => {
+    const unlisten = listen('protocol-log', event => {
+      const protocolEvent = event.payload as ProtocolEvent;
+      setEvents(current => [...current, protocolEvent].slice(-MAX_EVENTS));
+    });
+
+    return () => {
+      unlisten.then(fn => fn());
+    };
+  }, []);
+
+  // The backend only streams events at or above this level
+  const changeLevel = async (value: string) => {
+    try {
+      setLevel(
+        await invoke<ProtocolLogLevel>('set_protocol_console_level', {
+          level: value,
+        })
+      );
+    } catch (error) {
+      console.error('Failed to set protocol console level:', error);
+    }
+  };
+
+  // Raw traffic for bug reports; replay it with gcp-replay
+  const toggleCapture = async () => {
+    try {
+      if (capturePath === null) {
+        const path = await GCPService.startCapture(connectedPort);
+        setCapturePath(path);
+        setCaptureStatus(`Capturing to ${path}`);
+      } else {
+        const summary = await GCPService.stopCapture(connectedPort);
+        setCapturePath(null);
+        setCaptureStatus(
+          summary
+            ? `Saved ${summary.path} (${summary.records} records, ${summary.tx_bytes} bytes TX, ${summary.rx_bytes} bytes RX)`
+            : ''
+        );
+      }
+    } catch (error) {
+      setCaptureStatus(formatGcpError(error));
+    }
+  };
+
+  const visible = events.filter(
+    event =>
+      LEVELS.indexOf(event.level) <= LEVELS.indexOf(level) &&
+      (event.port === null || event.port === connectedPort)
+  );
+
+  return (
+    <div className="demo-section">
+      <Card>
+        <CardHeader>
+          <CardTitle>Protocol Console</CardTitle>
+          <CardDescription>
+            GCP frames and link events on {connectedPort}
+          </CardDescription>
+        </CardHeader>
+        <CardContent className="space-y-3">
+          <div className="flex items-center gap-3">
+            <Select value={level} onValueChange={changeLevel}>
+              <SelectTrigger className="w-40">
+                <SelectValue />
+              </SelectTrigger>
+              <SelectContent>
+                {LEVELS.map(option => (
+                  <SelectItem key={option} value={option}>
+                    {option}
+                  </SelectItem>
+                ))}
+              </SelectContent>
+            </Select>
+            <Button variant="outline" onClick={() => setEvents([])}>
+              Clear
+            </Button>
+            <Button
+              variant={capturePath === null ? 'outline' : 'destructive'}
+              onClick={toggleCapture}
+              disabled={isDemoMode}
+            >
+              {capturePath === null ? 'Start Capture' : 'Stop Capture'}
+            </Button>
+            <span className="text-xs text-gray-500">
+              {visible.length} events
+            </span>
+          </div>
+          {captureStatus && (
+            <div className="text-xs text-gray-600 text-left break-all">
+              {captureStatus}
+            </div>
+          )}
+          <ScrollArea className="h-80 rounded-md border bg-gray-50 p-3">
+            <div className="font-mono text-xs text-left space-y-0.5">
+              {visible.map((event, index) => (
+                <div key={index} className={LEVEL_COLORS[event.level]}>
+                  {new Date(event.timestamp_ms).toLocaleTimeString()}{' '}
+                  [{event.target}] {event.message}{' '}
+                  <span className="text-gray-500">{formatFields(event)}</span>
+                </div>
+              ))}
+            </div>
+          </ScrollArea>
+        </CardContent>
+      </Card>
+    </div>
+  );
+};
+
+export default ProtocolConsole;
@@ -1,6 +1,13 @@
 import { useConnection } from '@/contexts/ConnectionContext';
+import { formatGcpError } from '@/lib/gcpErrors';
 import { GCPService } from '@/services/GCPService';
 import { PortService } from '@/services/PortService';
+import type {
+  COMPortInfo,
+  ConnectionStateEvent,
+  DeviceRebootedEvent,
+} from '@/types/ConnectionTypes';
+import { listen } from '@tauri-apps/api/event';
 import { useEffect } from 'react';
 
 /**
@@ -32,6 +39,95 @@ export const useConnectionActions = () => {
     // eslint-disable-next-line react-hooks/exhaustive-deps
   }, []);
 
+  // Keep the port list current from the backend port watcher
+  useEffect(() => {
+    const unlistenAdded = listen('port-added', event => {
+      const added = event.payload as COMPortInfo;
+      setAvailablePorts(prevPorts => [
+        ...prevPorts.filter(port => port.port !== added.port),
+        {
+          ...added,
+          status: added.port === connectedPort ? 'connected' : 'available',
+        },
+      ]);
+    });
+
+    // The connected port stays listed so it can still be disconnected
+    const unlistenRemoved = listen('port-removed', event => {
+      const removed = event.payload as COMPortInfo;
+      if (removed.port === connectedPort) return;
+      setAvailablePorts(prevPorts =>
+        prevPorts.filter(port => port.port !== removed.port)
+      );
+    });
+
+    return () => {
+      unlistenAdded.then(fn => fn());
+      unlistenRemoved.then(fn => fn());
+    };
+    // eslint-disable-next-line react-hooks/exhaustive-deps
+  }, [connectedPort]);
+
+  // Follow the backend health monitor for the connected port
+  useEffect(() => {
+    if (!connectedPort) return;
+
+    const unlisten = listen('connection-state-changed', event => {
+      const change = event.payload as ConnectionStateEvent;
+      if (change.port_name !== connectedPort) return;
+
+      if (change.state === 'error') {
+        setConnectionStatus('error');
+        setError(`Connection lost: ${change.message}`);
+      } else if (change.state === 'connected') {
+        setConnectionStatus('connected');
+        setError(null);
+      }
+    });
+
+    return () => {
+      unlisten.then(fn => fn());
+    };
+    // eslint-disable-next-line react-hooks/exhaustive-deps
+  }, [connectedPort]);
+
+  // Pick up the device again after a reset, possibly on a new port
+  useEffect(() => {
+    if (!connectedPort) return;
+
+    const unlisten = listen('device-rebooted', event => {
+      const rebooted = event.payload as DeviceRebootedEvent;
+      if (rebooted.previous_port_name !== connectedPort) return;
+
+      setConnectedPort(rebooted.port_name);
+      setConnectionStatus('connected');
+      if (rebooted.hardware) {
+        setHardwareInfo(rebooted.hardware);
+      }
+      setFirmwareVersionInfo(rebooted.fw_version);
+      setError(null);
+
+      if (rebooted.port_name !== rebooted.previous_port_name) {
+        setAvailablePorts(prevPorts =>
+          PortService.updatePortStatus(
+            PortService.updatePortStatus(
+              prevPorts,
+              rebooted.previous_port_name,
+              'available'
+            ),
+            rebooted.port_name,
+            'connected'
+          )
+        );
+      }
+    });
+
+    return () => {
+      unlisten.then(fn => fn());
+    };
+    // eslint-disable-next-line react-hooks/exhaustive-deps
+  }, [connectedPort]);
+
   /**
    * Refresh the list of available COM ports
    */
@@ -106,10 +202,10 @@ export const useConnectionActions = () => {
       } catch (error) {
         console.warn('Failed to get device info:', error);
         // Don't fail the connection if device info fails, just log it
-        setError(`Connected but failed to get device info: ${error}`);
+        setError(`Connected but failed to get device info: ${formatGcpError(error)}`);
       }
     } catch (err) {
-      setError(`Connection failed: ${err}`);
+      setError(`Connection failed: ${formatGcpError(err)}`);
       setConnectionStatus('disconnected');
       setIsConnected(false);
       console.error('Error connecting:', err);
@@ -149,7 +245,7 @@ export const useConnectionActions = () => {
         PortService.updatePortStatus(prevPorts, previousPort, 'available')
       );
     } catch (err) {
-      setError(`Disconnection failed: ${err}`);
+      setError(`Disconnection failed: ${formatGcpError(err)}`);
       console.error('Error disconnecting:', err);
     } finally {
       setIsLoading(false);
@@ -172,7 +268,7 @@ export const useConnectionActions = () => {
       setHardwareInfo(hwInfo);
     } catch (error) {
       console.warn('Failed to get hardware info:', error);
-      setError(`Failed to get hardware info: ${error}`);
+      setError(formatGcpError(error));
     }
   };
 
@@ -192,7 +288,7 @@ export const useConnectionActions = () => {
       setFirmwareVersionInfo(fwInfo);
     } catch (error) {
       console.warn('Failed to get firmware info:', error);
-      setError(`Failed to get firmware info: ${error}`);
+      setError(formatGcpError(error));
     }
   };
 
@@ -215,7 +311,7 @@ export const useConnectionActions = () => {
     } catch (error) {
       console.warn('Failed to get device info:', error);
       // Don't fail the connection if device info fails, just log it
-      setError(`Connected but failed to get device info: ${error}`);
+      setError(`Connected but failed to get device info: ${formatGcpError(error)}`);
     }
   };
 
@@ -0,0 +1,81 @@
+import type { GcpCommError } from '@/types/ConnectionTypes';
+
+/**
+ * Helpers for errors returned by GCP Tauri commands.
+ * The backend rejects with a structured GcpCommError object; anything else
+ * (demo mode, plain Error) is passed through as text.
+ */
+
+const hex16 = (value: number): string =>
+  `0x${value.toString(16).padStart(4, '0').toUpperCase()}`;
+
+export const isGcpCommError = (error: unknown): error is GcpCommError =>
+  typeof error === 'object' &&
+  error !== null &&
+  typeof (error as { kind?: unknown }).kind === 'string';
+
+export const formatGcpError = (error: unknown): string => {
+  if (error instanceof GcpCommandError) {
+    return error.message;
+  }
+  if (!isGcpCommError(error)) {
+    return error instanceof Error ? error.message : String(error);
+  }
+
+  switch (error.kind) {
+    case 'transport':
+      return `Port ${error.op} error: ${error.message}`;
+    case 'framing':
+      switch (error.reason.type) {
+        case 'preamble':
+          return 'Invalid frame preamble';
+        case 'crc16':
+          return `CRC mismatch: calculated=${hex16(error.reason.calculated)}, received=${hex16(error.reason.received)}`;
+        case 'length':
+          return `Invalid frame length ${error.reason.length}`;
+        case 'truncated':
+          return `Incomplete frame: expected ${error.reason.expected} bytes, got ${error.reason.received}`;
+      }
+      break;
+    case 'timeout':
+      return `Timeout waiting for response (${error.timeout_ms} ms)`;
+    case 'nack':
+      return `Device rejected ${hex16(error.msg_type)} (seq ${error.seq_no}): ${
+        error.error ?? 'error code'
+      } (${hex16(error.error_code)})`;
+    case 'not_connected':
+      return `No connection found for ${error.port_name}. Please connect first.`;
+    case 'file':
+      return `${error.path}: ${error.message}`;
+    case 'truncated_payload':
+      return `Truncated ${error.payload} payload: got ${error.received} bytes, need ${error.expected}`;
+    case 'untrusted_firmware':
+      return `Firmware image rejected: ${error.reason}`;
+    case 'invalid_response':
+      return `Invalid response: ${error.message}`;
+    case 'unexpected_response':
+    case 'invalid_parameter':
+    case 'internal':
+      return error.message;
+  }
+  return String(error);
+};
+
+/**
+ * Error thrown by GCPService, keeping the backend error for callers that
+ * need to branch on error.detail.kind
+ */
+export class GcpCommandError extends Error {
+  readonly detail: GcpCommError | null;
+
+  constructor(context: string, cause: unknown) {
+    super(`${context}: ${formatGcpError(cause)}`);
+    this.name = 'GcpCommandError';
+    this.detail =
+      cause instanceof GcpCommandError
+        ? cause.detail
+        : isGcpCommError(cause)
+          ? cause
+          : null;
+  }
+}
@@ -1,7 +1,12 @@
 import type {
+  CaptureSummary,
+  DiagnosticsInfo,
   FirmwareVersionInfo,
+  GcpSpecRevision,
   HardwareInfo,
+  ReplayReport,
 } from '@/types/ConnectionTypes';
+import { GcpCommandError } from '@/lib/gcpErrors';
 import { invoke } from '@tauri-apps/api/core';
 import { MockDataService } from './MockDataService';
 
@@ -30,10 +35,16 @@ export class GCPService {
           portName,
         });
         console.log('Hardware info received:', hwInfo);
+        if (!('manufacture_date' in hwInfo)) {
+          // Pre-v2.2 devices answer HELLO with status data only
+          throw new Error(
+            'Device HELLO response carries no hardware information (GCP v2.1 or earlier)'
+          );
+        }
         return hwInfo;
       } catch (error) {
         console.error('Failed to get hardware info via HELLO:', error);
-        throw new Error(`Failed to get hardware info: ${error}`);
+        throw new GcpCommandError('Failed to get hardware info', error);
       }
     }
   }
@@ -65,11 +76,128 @@ export class GCPService {
         return fwInfo;
       } catch (error) {
         console.error('Failed to get firmware version info:', error);
-        throw new Error(`Failed to get firmware version: ${error}`);
+        throw new GcpCommandError('Failed to get firmware version', error);
       }
     }
   }
 
+  /**
+   * Send GET_DIAGNOSTICS command to read the device wear counters
+   * @param portName - Name of the connected port
+   * @param isDemoMode - Whether we're in demo mode
+   * @returns Promise<DiagnosticsInfo> - Diagnostics counters
+   */
+  static async getDiagnosticsInfo(
+    portName: string,
+    isDemoMode: boolean
+  ): Promise<DiagnosticsInfo> {
+    if (!portName) {
+      throw new Error('No port connected');
+    }
+
+    if (isDemoMode) {
+      // Return mock data for demo mode
+      return await MockDataService.getDiagnosticsInfo();
+    } else {
+      // Send real GET_DIAGNOSTICS command
+      try {
+        const diagnostics: DiagnosticsInfo = await invoke(
+          'gcp_get_diagnostics',
+          {
+            portName,
+          }
+        );
+        console.log('Diagnostics info received:', diagnostics);
+        return diagnostics;
+      } catch (error) {
+        console.error('Failed to get diagnostics info:', error);
+        throw new GcpCommandError('Failed to get diagnostics', error);
+      }
+    }
+  }
+
+  /**
+   * Select the GCP revision used to parse responses on this connection.
+   * Only needed for devices running pre-v2.2 firmware.
+   * @param portName - Name of the connected port
+   * @param revision - Protocol revision implemented by the device
+   * @param isDemoMode - Whether we're in demo mode
+   * @returns Promise<GcpSpecRevision> - Revision now in effect
+   */
+  static async setSpecRevision(
+    portName: string,
+    revision: GcpSpecRevision,
+    isDemoMode: boolean
+  ): Promise<GcpSpecRevision> {
+    if (!portName) {
+      throw new Error('No port connected');
+    }
+
+    if (isDemoMode) {
+      return revision;
+    }
+
+    try {
+      return await invoke<GcpSpecRevision>('gcp_set_spec_revision', {
+        portName,
+        revision,
+      });
+    } catch (error) {
+      console.error('Failed to set GCP spec revision:', error);
+      throw new GcpCommandError('Failed to set protocol revision', error);
+    }
+  }
+
+  /**
+   * Record the port's raw TX/RX traffic to a JSON Lines capture file
+   * @param portName - Name of the connected port
+   * @param path - Capture file; defaults to a new file in the app data directory
+   * @returns Promise<string> - Path of the capture file
+   */
+  static async startCapture(portName: string, path?: string): Promise<string> {
+    try {
+      return await this.invokeWithPort<string>(
+        'start_protocol_capture',
+        portName,
+        { path: path ?? null }
+      );
+    } catch (error) {
+      console.error('Failed to start protocol capture:', error);
+      throw new GcpCommandError('Failed to start capture', error);
+    }
+  }
+
+  /**
+   * Stop recording the port's traffic
+   * @param portName - Name of the connected port
+   * @returns Promise<CaptureSummary | null> - The finished capture, if one was running
+   */
+  static async stopCapture(portName: string): Promise<CaptureSummary | null> {
+    try {
+      return await this.invokeWithPort<CaptureSummary | null>(
+        'stop_protocol_capture',
+        portName
+      );
+    } catch (error) {
+      console.error('Failed to stop protocol capture:', error);
+      throw new GcpCommandError('Failed to stop capture', error);
+    }
+  }
+
+  /**
+   * Run a capture's requests through the protocol handler again, without a device
+   * @param path - Capture file
+   * @returns Promise<ReplayReport> - Each request's response or error, and divergences
+   */
+  static async replayCapture(path: string): Promise<ReplayReport> {
+    try {
+      return await invoke<ReplayReport>('replay_protocol_capture', { path });
+    } catch (error) {
+      console.error('Failed to replay protocol capture:', error);
+      throw new GcpCommandError('Failed to replay capture', error);
+    }
+  }
+
   /**
    * Get both hardware and firmware information in one call
    * @param portName - Name of the connected port
@@ -95,7 +223,8 @@ export class GCPService {
   }
 
   /**
-   * Generic method for invoking GCP commands with connected port
+   * Generic method for invoking GCP commands with connected port.
+   * Rejects with the backend GcpCommError; use formatGcpError to display it.
    * @param command - Tauri command name
    * @param portName - Name of the connected port
    * @param args - Additional arguments for the command
@@ -1,5 +1,6 @@
 import type {
   COMPort,
+  DiagnosticsInfo,
   FirmwareVersionInfo,
   HardwareInfo,
 } from '@/types/ConnectionTypes';
@@ -56,6 +57,18 @@ export const mockFirmwareInfo: FirmwareVersionInfo = {
   fw_version_suffix: [100, 101, 118], // 'dev' in ASCII
 };
 
+// Mock diagnostics counters for demo mode
+export const mockDiagnosticsInfo: DiagnosticsInfo = {
+  step_counter: 15230,
+  full_power_time: 86400,
+  silent_time: 3600,
+  charging_time: 7200,
+  btn_counter_l: 842,
+  btn_counter_r: 913,
+  fram_read: 120450,
+  fram_write: 30211,
+};
+
 export class MockDataService {
   /**
    * Simulate async port scanning with delay
@@ -90,6 +103,14 @@ export class MockDataService {
     return { ...mockFirmwareInfo };
   }
 
+  /**
+   * Get mock diagnostics counters with delay
+   */
+  static async getDiagnosticsInfo(): Promise<DiagnosticsInfo> {
+    await new Promise(resolve => setTimeout(resolve, 300));
+    return { ...mockDiagnosticsInfo };
+  }
+
   /**
    * Update port status for demo mode
    */
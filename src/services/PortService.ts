@@ -1,5 +1,10 @@
 import { safeInvoke } from '@/lib/tauriUtils';
-import type { COMPort, COMPortInfo } from '@/types/ConnectionTypes';
+import type {
+  COMPort,
+  COMPortInfo,
+  DiscoveryReport,
+  LinkConfig,
+} from '@/types/ConnectionTypes';
 import { invoke } from '@tauri-apps/api/core';
 import { MockDataService } from './MockDataService';
 
@@ -47,9 +52,14 @@ export class PortService {
    * Connect to a specific COM port
    * @param portName - Name of the port to connect to
    * @param isDemoMode - Whether we're in demo mode
+   * @param linkConfig - Link parameters, saved as the port's profile (optional)
    * @returns Promise<string> - Connection result message
    */
-  static async connect(portName: string, isDemoMode: boolean): Promise<string> {
+  static async connect(
+    portName: string,
+    isDemoMode: boolean,
+    linkConfig?: Partial<LinkConfig>
+  ): Promise<string> {
     if (!portName) {
       throw new Error('Please select a COM port first');
     }
@@ -60,7 +70,11 @@ export class PortService {
       return `Connected to ${portName} (Demo Mode)`;
     } else {
       // Try real connection
-      const result: string = await invoke('connect_port', { portName });
+      // Without a config the port's saved link profile (or the defaults) is used
+      const result: string = await invoke('connect_port', {
+        portName,
+        linkConfig,
+      });
       console.log('Connect result:', result);
       return result;
     }
@@ -91,6 +105,44 @@ export class PortService {
     }
   }
 
+  /**
+   * Probe every port with HELLO to find Glitchi devices
+   * @param timeoutMs - HELLO timeout per port (backend default if omitted)
+   * @returns Promise<DiscoveryReport> - Devices found and ports skipped with reasons
+   */
+  static async discoverDevices(timeoutMs?: number): Promise<DiscoveryReport> {
+    return invoke<DiscoveryReport>('discover_devices', { timeoutMs });
+  }
+
+  /**
+   * Saved link profiles by port name
+   * @returns Promise<Record<string, LinkConfig>> - Ports without a profile use the defaults
+   */
+  static async getLinkProfiles(): Promise<Record<string, LinkConfig>> {
+    return invoke<Record<string, LinkConfig>>('get_link_profiles');
+  }
+
+  /**
+   * Save link parameters for a port, used the next time it is connected
+   * @param portName - Port the profile belongs to
+   * @param config - Link parameters; omitted fields keep the defaults
+   * @returns Promise<LinkConfig> - The saved profile
+   */
+  static async saveLinkProfile(
+    portName: string,
+    config: Partial<LinkConfig>
+  ): Promise<LinkConfig> {
+    return invoke<LinkConfig>('save_link_profile', { portName, config });
+  }
+
+  /**
+   * Forget a port's link profile
+   * @param portName - Port whose profile is removed
+   */
+  static async deleteLinkProfile(portName: string): Promise<void> {
+    await invoke('delete_link_profile', { portName });
+  }
+
   /**
    * Update port status in the ports list
    * @param ports - Current ports list
@@ -9,10 +9,43 @@ export interface COMPortInfo {
   port_type: string;
 }
 
+// Port watcher settings (get_port_watch_config / set_port_watch_config)
+export interface PortWatchConfig {
+  interval_ms: number;
+  usb_filter: { vendor_id: number; product_id: number | null }[] | null;
+}
+
+export type FlowControl = 'none' | 'hardware' | 'software';
+
+// Per-connection link parameters; omitted fields keep the GCP v2.2 defaults
+export interface LinkConfig {
+  baud_rate: number;
+  flow_control: FlowControl;
+  timeout_ms: number;
+  max_retries: number;
+  inter_frame_delay_ms: number;
+  chunk_size: number;
+}
+
 export interface COMPort extends COMPortInfo {
   status: 'available' | 'busy' | 'connected';
 }
 
+// discover_devices result: ports that answered HELLO with HWVersion, and the rest with a reason
+export interface DiscoveredDevice {
+  port: COMPortInfo;
+  serial_number: number;
+  board_type: string;
+  chip_model: string;
+  features: string[];
+  hardware: HardwareInfo;
+}
+
+export interface DiscoveryReport {
+  devices: DiscoveredDevice[];
+  skipped: { port: COMPortInfo; reason: string }[];
+}
+
 // Hardware information types
 export interface HardwareInfo {
   manufacture_date: number;
@@ -31,8 +64,142 @@ export interface FirmwareVersionInfo {
   fw_version_suffix: number[];
 }
 
-// Connection status types
-export type ConnectionStatus = 'disconnected' | 'connecting' | 'connected';
+// Diagnostics counter types (GET_DIAGNOSTICS)
+export interface DiagnosticsInfo {
+  step_counter: number;
+  full_power_time: number;
+  silent_time: number;
+  charging_time: number;
+  btn_counter_l: number;
+  btn_counter_r: number;
+  fram_read: number;
+  fram_write: number;
+}
+
+// GCP error types (serialized GcpCommError from the backend, tagged by `kind`)
+export type GcpDeviceError =
+  | 'CRC'
+  | 'SEQ'
+  | 'SIZE'
+  | 'TIMEOUT'
+  | 'MRAM'
+  | 'UNKNOWN_CMD'
+  | 'INVALID_PARAM'
+  | 'BUSY';
+
+export type GcpFramingError =
+  | { type: 'preamble' }
+  | { type: 'crc16'; calculated: number; received: number }
+  | { type: 'length'; length: number }
+  | { type: 'truncated'; expected: number; received: number };
+
+export type GcpCommError =
+  | {
+      kind: 'transport';
+      op: 'open' | 'configure' | 'read' | 'write';
+      message: string;
+    }
+  | { kind: 'framing'; reason: GcpFramingError }
+  | { kind: 'timeout'; timeout_ms: number }
+  | {
+      kind: 'nack';
+      msg_type: number;
+      seq_no: number;
+      error_code: number;
+      error: GcpDeviceError | null;
+    }
+  | { kind: 'unexpected_response'; msg_type: number; message: string }
+  | {
+      kind: 'truncated_payload';
+      payload: string;
+      expected: number;
+      received: number;
+    }
+  | { kind: 'invalid_response'; message: string }
+  | { kind: 'invalid_parameter'; message: string }
+  | { kind: 'not_connected'; port_name: string }
+  | { kind: 'file'; path: string; message: string }
+  | { kind: 'untrusted_firmware'; reason: string }
+  | { kind: 'internal'; message: string };
+
+// GCP protocol revision used to parse device payloads (v2.0/v2.1 compatibility)
+export type GcpSpecRevision = '2.0' | '2.1' | '2.2';
+
+// Interrupted firmware transfer persisted per device serial number
+export interface FirmwareCheckpoint {
+  device_serial: number;
+  image_path: string | null;
+  image_size: number;
+  image_crc32: number;
+  chunk_size: number;
+  acked_offset: number;
+  updated_at: number;
+}
+
+// Signature check result for a firmware image (.fw containers are signed)
+export interface FirmwareSignatureInfo {
+  signed: boolean;
+  trusted_key: string | null;
+  target_board_type: number | null;
+  target_chip_model: number | null;
+  payload_sha256: string | null;
+  developer_override: boolean;
+}
+
+export interface FirmwareTrustConfig {
+  trusted_keys: { name: string; public_key: string }[];
+  allow_untrusted: boolean;
+}
+
+// Production line batch flashing ("firmware-batch-updated" / "firmware-batch-completed")
+export type BatchDeviceState =
+  | 'pending'
+  | 'flashing'
+  | 'resetting'
+  | 'completed'
+  | 'failed'
+  | 'cancelled';
+
+export interface BatchDeviceStatus {
+  port_name: string;
+  job_id: string | null;
+  device_serial: number | null;
+  state: BatchDeviceState;
+  bytes_acked: number;
+  message: string;
+}
+
+export interface FirmwareBatchStatus {
+  batch_id: string;
+  total_bytes: number;
+  image_crc32: number;
+  finished: boolean;
+  succeeded: number;
+  failed: number;
+  devices: BatchDeviceStatus[];
+}
+
+// Connection status types ('error': still connected, but the link or device stopped responding)
+export type ConnectionStatus =
+  | 'disconnected'
+  | 'connecting'
+  | 'connected'
+  | 'error';
+
+// Health monitor state change ("connection-state-changed" event)
+export type ConnectionStateEvent =
+  | { port_name: string; state: 'connected' }
+  | { port_name: string; state: 'disconnected' }
+  | { port_name: string; state: 'error'; message: string };
+
+// Device answered again after a reset ("device-rebooted" event); the port may have been renamed
+export interface DeviceRebootedEvent {
+  previous_port_name: string;
+  port_name: string;
+  hardware: HardwareInfo | null;
+  fw_version: FirmwareVersionInfo;
+  fw_version_string: string;
+}
 
 // Context interface for data storage only
 export interface ConnectionContextType {
@@ -62,3 +229,59 @@ export interface ConnectionContextType {
   setHardwareInfo: (info: HardwareInfo | null) => void;
   setFirmwareVersionInfo: (info: FirmwareVersionInfo | null) => void;
 }
+
+export type ProtocolLogLevel = 'error' | 'warn' | 'info' | 'debug' | 'trace';
+
+// Structured protocol event ("protocol-log" event, get_protocol_log)
+export interface ProtocolEvent {
+  timestamp_ms: number;
+  level: ProtocolLogLevel;
+  target: string; // gcp::tx, gcp::rx, gcp::link, gcp::fw
+  port: string | null;
+  msg_type: number | null;
+  seq: number | null;
+  len: number | null;
+  crc_ok: boolean | null;
+  elapsed_ms: number | null;
+  message: string;
+}
+
+// Finished protocol capture (stop_protocol_capture)
+export interface CaptureSummary {
+  path: string;
+  records: number;
+  tx_bytes: number;
+  rx_bytes: number;
+  duration_ms: number;
+}
+
+// Response to one replayed request, tagged by `type`
+export type GcpResponse =
+  | { type: 'none' }
+  | { type: 'ack' }
+  | { type: 'hello'; data: HardwareInfo }
+  | { type: 'status'; data: Record<string, unknown> }
+  | { type: 'fw_version'; data: FirmwareVersionInfo }
+  | { type: 'diagnostics'; data: DiagnosticsInfo }
+  | { type: 'fw_update_end'; data: { verified: boolean } };
+
+export interface ReplayStep {
+  t_us: number; // Since the capture started
+  msg_type: string | null; // GcpCommand name; null for RX bytes that were not a frame
+  raw_msg_type: number | null; // As received, also for a MsgType outside the spec
+  unsolicited: boolean;
+  response: GcpResponse | null;
+  error: GcpCommError | null;
+}
+
+// Result of replay_protocol_capture
+export interface ReplayReport {
+  header: {
+    port_name: string;
+    started_at: number;
+    link: LinkConfig;
+    spec_revision: GcpSpecRevision;
+  };
+  steps: ReplayStep[];
+  divergences: string[];
+}